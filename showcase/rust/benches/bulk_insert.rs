@@ -0,0 +1,65 @@
+//! Allocations made by bulk inserts that copy strings versus sharing them
+//!
+//! Run with `cargo bench --bench bulk_insert`; `GVS_BENCH_ENTRIES` sets the
+//! size. The copying store is what `DbOperation::execute` did with `String`
+//! keys and values before they became `Arc<str>`.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use gvs_showcase::{perf, Database, DbOperation, MemoryDatabase};
+
+#[global_allocator]
+static ALLOCATOR: perf::CountingAlloc<std::alloc::System> = perf::CountingAlloc(std::alloc::System);
+
+fn main() {
+    let entries: usize = std::env::var("GVS_BENCH_ENTRIES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(200_000);
+    let ops: Vec<DbOperation> = (0..entries)
+        .map(|i| DbOperation::Insert {
+            key: format!("user:{:08}", i).into(),
+            value: format!("{{\"score\":{}}}", i).into(),
+        })
+        .collect();
+    let pairs = || {
+        ops.iter().map(|op| match op {
+            DbOperation::Insert { key, value } => (key, value),
+            _ => unreachable!(),
+        })
+    };
+    println!("{} inserts", entries);
+
+    let mut copied: HashMap<String, String> = HashMap::with_capacity(entries);
+    let mut shared = MemoryDatabase::new();
+    shared.reserve(entries);
+    let runs: [(&str, &mut dyn FnMut()); 2] = [
+        ("cloned String", &mut || {
+            for (key, value) in pairs() {
+                copied.insert(key.to_string(), value.to_string());
+            }
+        }),
+        ("shared Arc<str>", &mut || {
+            for (key, value) in pairs() {
+                shared.insert(Arc::clone(key), Arc::clone(value));
+            }
+        }),
+    ];
+    for (name, run) in runs {
+        perf::reset();
+        perf::enable();
+        let started = Instant::now();
+        run();
+        let elapsed = started.elapsed();
+        perf::disable();
+        let report = perf::report();
+        println!(
+            "{:<16} {:>8.1} ms {:>6.2} allocations/insert {:>10} KiB allocated",
+            name,
+            elapsed.as_secs_f64() * 1000.0,
+            report.allocations as f64 / entries as f64,
+            report.allocated_bytes / 1024
+        );
+    }
+}
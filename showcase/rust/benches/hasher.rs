@@ -0,0 +1,53 @@
+//! Compares the default SipHash hasher with aHash and FxHash on short keys
+//!
+//! Run with `cargo bench --bench hasher --features ahash,fxhash`;
+//! `GVS_BENCH_ENTRIES` sets the size.
+use std::hash::BuildHasher;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gvs_showcase::{AHashDatabase, Database, FxDatabase, MemoryDatabase, StrDatabase};
+
+/// Time taken to insert every key, then to look each one up
+fn measure<S: BuildHasher + Clone>(
+    mut db: StrDatabase<S>,
+    keys: &[String],
+) -> (Duration, Duration) {
+    let started = Instant::now();
+    for key in keys {
+        db.insert(key.as_str(), "1");
+    }
+    let inserted = started.elapsed();
+
+    let started = Instant::now();
+    for key in keys {
+        black_box(db.retrieve(key));
+    }
+    (inserted, started.elapsed())
+}
+
+fn main() {
+    let entries: usize = std::env::var("GVS_BENCH_ENTRIES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(200_000);
+    let keys: Vec<String> = (0..entries).map(|i| format!("k{}", i)).collect();
+    println!(
+        "{} entries with keys of at most {} bytes",
+        entries,
+        keys[entries - 1].len()
+    );
+
+    for (name, (inserted, retrieved)) in [
+        ("SipHash (default)", measure(MemoryDatabase::new(), &keys)),
+        ("aHash", measure(AHashDatabase::default(), &keys)),
+        ("FxHash", measure(FxDatabase::default(), &keys)),
+    ] {
+        println!(
+            "{:<18} insert {:>8.1} ms   retrieve {:>8.1} ms",
+            name,
+            inserted.as_secs_f64() * 1000.0,
+            retrieved.as_secs_f64() * 1000.0
+        );
+    }
+}
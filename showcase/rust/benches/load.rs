@@ -0,0 +1,70 @@
+//! Compares `load_from_file` with reading the whole file up front
+//!
+//! Run with `cargo bench --bench load`; `GVS_BENCH_ENTRIES` sets the size.
+use std::time::{Duration, Instant};
+
+use gvs_showcase::{perf, persist, Database, MemoryDatabase};
+
+#[global_allocator]
+static ALLOCATOR: perf::CountingAlloc<std::alloc::System> = perf::CountingAlloc(std::alloc::System);
+
+/// What `load_from_file` did before it streamed
+///
+/// Checksums are stripped without being verified, so its time is a lower bound.
+fn load_whole_file(path: &str) -> MemoryDatabase {
+    let contents = std::fs::read_to_string(path).unwrap();
+    let mut db = MemoryDatabase::new();
+    for line in contents.lines() {
+        let line = line.rsplit_once('\t').map_or(line, |(body, _)| body);
+        let (key, value) = persist::decode_line(line).unwrap();
+        db.insert(key, value);
+    }
+    db
+}
+
+/// Time taken and bytes allocated by `load`
+fn measure(load: impl FnOnce() -> MemoryDatabase) -> (Duration, u64, usize) {
+    perf::reset();
+    perf::enable();
+    let started = Instant::now();
+    let entries = load().len();
+    let elapsed = started.elapsed();
+    perf::disable();
+    (elapsed, perf::report().allocated_bytes, entries)
+}
+
+fn main() {
+    let entries: usize = std::env::var("GVS_BENCH_ENTRIES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(200_000);
+    let path = std::env::temp_dir().join("gvs_bench_load.db");
+    let path = path.to_str().unwrap();
+
+    let db: MemoryDatabase = (0..entries)
+        .map(|i| (format!("user:{:08}", i), format!("{{\"score\":{}}}", i)))
+        .collect();
+    db.save_to_file(path).unwrap();
+    let size = std::fs::metadata(path).unwrap().len();
+    println!("{} entries, {} KiB on disk", entries, size / 1024);
+
+    for (name, load) in [
+        (
+            "read whole file",
+            load_whole_file as fn(&str) -> MemoryDatabase,
+        ),
+        ("load_from_file", |path: &str| {
+            MemoryDatabase::load_from_file(path).unwrap()
+        }),
+    ] {
+        let (elapsed, allocated, loaded) = measure(|| load(path));
+        assert_eq!(loaded, entries);
+        println!(
+            "{:<16} {:>8.1} ms {:>10} KiB allocated",
+            name,
+            elapsed.as_secs_f64() * 1000.0,
+            allocated / 1024
+        );
+    }
+    std::fs::remove_file(path).unwrap();
+}
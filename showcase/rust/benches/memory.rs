@@ -0,0 +1,56 @@
+//! Heap used by a store of short values, with and without `compact-values`
+//!
+//! Run with `cargo bench --bench memory`, then again with
+//! `--features compact-values` to compare; `GVS_BENCH_ENTRIES` sets the size.
+use gvs_showcase::{perf, Database, MemoryDatabase};
+
+#[global_allocator]
+static ALLOCATOR: perf::CountingAlloc<std::alloc::System> = perf::CountingAlloc(std::alloc::System);
+
+/// Allocations and bytes allocated inserting `value(i)` under each key
+fn measure(keys: &[String], value: impl Fn(usize) -> String) -> (u64, u64) {
+    let values: Vec<String> = (0..keys.len()).map(value).collect();
+    let mut db = MemoryDatabase::new();
+    db.reserve(keys.len());
+    perf::reset();
+    perf::enable();
+    for (key, value) in keys.iter().zip(&values) {
+        db.insert(key.as_str(), value.as_str());
+    }
+    perf::disable();
+    let report = perf::report();
+    assert_eq!(db.len(), keys.len());
+    (report.allocations, report.allocated_bytes)
+}
+
+fn main() {
+    let entries: usize = std::env::var("GVS_BENCH_ENTRIES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(200_000);
+    let keys: Vec<String> = (0..entries).map(|i| format!("user:{:08}", i)).collect();
+    let compact = if cfg!(feature = "compact-values") {
+        "on"
+    } else {
+        "off"
+    };
+    println!("{} entries, compact-values {}", entries, compact);
+
+    for (name, value) in [
+        (
+            "short values",
+            (|i| format!("v{}", i)) as fn(usize) -> String,
+        ),
+        ("long values", |i| {
+            format!("{{\"score\":{},\"padding\":\"....\"}}", i)
+        }),
+    ] {
+        let (allocations, bytes) = measure(&keys, value);
+        println!(
+            "{:<14} {:>6.2} allocations/entry {:>8.1} bytes/entry",
+            name,
+            allocations as f64 / entries as f64,
+            bytes as f64 / entries as f64
+        );
+    }
+}
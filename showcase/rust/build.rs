@@ -0,0 +1,38 @@
+//! Bakes `data/defaults.db` and build metadata into `$OUT_DIR/defaults.rs`
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const DEFAULTS: &str = "data/defaults.db";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", DEFAULTS);
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let source = fs::read_to_string(DEFAULTS).expect("read data/defaults.db");
+    let mut out = String::new();
+
+    let version = env::var("CARGO_PKG_VERSION").unwrap_or_else(|_| "0.0.0".to_string());
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "debug".to_string());
+    writeln!(out, "/// Crate version at build time").unwrap();
+    writeln!(out, "pub const VERSION: &str = {:?};", version).unwrap();
+    writeln!(out, "/// Cargo profile the crate was built with").unwrap();
+    writeln!(out, "pub const PROFILE: &str = {:?};", profile).unwrap();
+
+    writeln!(out, "/// Entries from `{}`, in file order", DEFAULTS).unwrap();
+    writeln!(out, "pub const DEFAULT_ENTRIES: &[(&str, &str)] = &[").unwrap();
+    for (number, line) in source.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            panic!("{}:{}: expected `key:value`", DEFAULTS, number + 1);
+        };
+        writeln!(out, "    ({:?}, {:?}),", key, value).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("defaults.rs"), out).expect("write defaults.rs");
+}
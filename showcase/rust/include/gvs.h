@@ -0,0 +1,146 @@
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#ifndef GVS_H
+#define GVS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Longest key, in bytes, accepted by the C API
+ */
+#define GVS_MAX_KEY_LEN 1024
+
+/**
+ * Status codes returned by every `gvs_*` function
+ */
+typedef enum GvsStatus {
+  GVS_STATUS_OK = 0,
+  GVS_STATUS_NULL_POINTER = 1,
+  GVS_STATUS_INVALID_UTF8 = 2,
+  GVS_STATUS_NOT_FOUND = 3,
+  GVS_STATUS_IO = 4,
+  GVS_STATUS_KEY_TOO_LONG = 5,
+  GVS_STATUS_INTERIOR_NUL = 6,
+  GVS_STATUS_REFUSED = 7,
+} GvsStatus;
+
+/**
+ * Opaque database handle handed out to C callers
+ */
+typedef struct GvsDb GvsDb;
+
+/**
+ * Size summary filled in by `gvs_db_stats`
+ */
+typedef struct GvsStats {
+  size_t entries;
+  size_t key_bytes;
+  size_t value_bytes;
+} GvsStats;
+
+/**
+ * Called once per entry by `gvs_db_foreach`; returning non-zero stops the walk
+ */
+typedef int (*GvsVisitor)(const char *key, const char *value, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Opens a database, loading `path` when it is not NULL
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a valid NUL-terminated string and `out` must be a
+ * valid pointer. The handle written to `out` must be released with
+ * `gvs_db_close`.
+ */
+enum GvsStatus gvs_db_open(const char *path, struct GvsDb **out);
+
+/**
+ * Persists the database to `path`
+ *
+ * # Safety
+ *
+ * `db` must come from `gvs_db_open` and `path` must be a valid
+ * NUL-terminated string.
+ */
+enum GvsStatus gvs_db_save(const struct GvsDb *db, const char *path);
+
+/**
+ * Looks up `key`, writing a newly allocated copy of the value to `out`
+ *
+ * Values holding a NUL byte can't be returned as C strings and report
+ * `InteriorNul`.
+ *
+ * # Safety
+ *
+ * `db` must come from `gvs_db_open`, `key` must be a valid NUL-terminated
+ * string and `out` a valid pointer. The returned string must be released
+ * with `gvs_string_free`.
+ */
+enum GvsStatus gvs_db_get(const struct GvsDb *db, const char *key, char **out);
+
+/**
+ * Inserts or replaces `key` with `value`
+ *
+ * Returns `Refused` and changes nothing when a quota, key policy or hook
+ * turns the write down.
+ *
+ * # Safety
+ *
+ * `db` must come from `gvs_db_open` and both strings must be valid and
+ * NUL-terminated.
+ */
+enum GvsStatus gvs_db_set(struct GvsDb *db, const char *key, const char *value);
+
+/**
+ * Writes entry and byte counts for the database to `out`
+ *
+ * # Safety
+ *
+ * `db` must come from `gvs_db_open` and `out` must be a valid pointer.
+ */
+enum GvsStatus gvs_db_stats(const struct GvsDb *db, struct GvsStats *out);
+
+/**
+ * Calls `visitor` with every entry, in unspecified order
+ *
+ * The strings passed to `visitor` are only valid for the duration of the
+ * call. Entries containing NUL bytes are skipped.
+ *
+ * # Safety
+ *
+ * `db` must come from `gvs_db_open`; `user_data` is passed through untouched.
+ */
+enum GvsStatus gvs_db_foreach(const struct GvsDb *db, GvsVisitor visitor, void *user_data);
+
+/**
+ * Releases a handle returned by `gvs_db_open`
+ *
+ * # Safety
+ *
+ * `db` must be NULL or a handle that has not been closed yet.
+ */
+void gvs_db_close(struct GvsDb *db);
+
+/**
+ * Releases a string returned by `gvs_db_get`
+ *
+ * # Safety
+ *
+ * `value` must be NULL or a string returned by `gvs_db_get` that has not
+ * been freed yet.
+ */
+void gvs_string_free(char *value);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GVS_H */
@@ -0,0 +1,172 @@
+//! Minimal key-value map for targets without `std`
+//!
+//! Only `core` and `alloc` are used, so the map runs on embedded and wasm
+//! targets. Entries are kept sorted in a `Vec`, which needs no hasher and
+//! iterates in key order. The `std` feature (on by default) adds
+//! `std::error::Error` for `KvError`.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Longest key accepted by `KvMap::insert`
+pub const MAX_KEY_LEN: usize = 255;
+
+/// Errors raised by `KvMap` and `parse_line`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KvError {
+    EmptyKey,
+    KeyTooLong { len: usize },
+    Full { capacity: usize },
+    MissingSeparator,
+}
+
+impl fmt::Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KvError::EmptyKey => f.write_str("key must not be empty"),
+            KvError::KeyTooLong { len } => {
+                write!(f, "key is {} bytes, limit is {}", len, MAX_KEY_LEN)
+            }
+            KvError::Full { capacity } => write!(f, "map is full ({} entries)", capacity),
+            KvError::MissingSeparator => f.write_str("expected `key:value`"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KvError {}
+
+/// Sorted map from string keys to `V`, optionally bounded in size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvMap<V> {
+    entries: Vec<(String, V)>,
+    capacity: Option<usize>,
+}
+
+impl<V> KvMap<V> {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity: None,
+        }
+    }
+
+    /// Map that rejects new keys once it holds `capacity` entries
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            capacity: Some(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, key: &str) -> Result<usize, usize> {
+        self.entries.binary_search_by(|(k, _)| k.as_str().cmp(key))
+    }
+
+    /// Inserts or replaces `key`, returning the previous value
+    pub fn insert(&mut self, key: &str, value: V) -> Result<Option<V>, KvError> {
+        if key.is_empty() {
+            return Err(KvError::EmptyKey);
+        }
+        if key.len() > MAX_KEY_LEN {
+            return Err(KvError::KeyTooLong { len: key.len() });
+        }
+        match self.position(key) {
+            Ok(index) => Ok(Some(core::mem::replace(&mut self.entries[index].1, value))),
+            Err(index) => {
+                if let Some(capacity) = self.capacity.filter(|&c| self.entries.len() >= c) {
+                    return Err(KvError::Full { capacity });
+                }
+                self.entries.insert(index, (String::from(key), value));
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        let index = self.position(key).ok()?;
+        Some(&self.entries[index].1)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let index = self.position(key).ok()?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Entries in ascending key order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl<V> Default for KvMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits one `key:value` line of the showcase save format
+pub fn parse_line(line: &str) -> Result<(&str, &str), KvError> {
+    let (key, value) = line.split_once(':').ok_or(KvError::MissingSeparator)?;
+    if key.is_empty() {
+        return Err(KvError::EmptyKey);
+    }
+    Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_insert_get_remove() {
+        let mut map = KvMap::new();
+        assert_eq!(map.insert("year", 2010), Ok(None));
+        assert_eq!(map.insert("edition", 2021), Ok(None));
+        assert_eq!(map.insert("year", 2015), Ok(Some(2010)));
+
+        assert_eq!(map.get("year"), Some(&2015));
+        assert_eq!(
+            map.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["edition", "year"]
+        );
+        assert_eq!(map.remove("edition"), Some(2021));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_limits_and_parse_errors() {
+        let mut map = KvMap::bounded(1);
+        map.insert("a", ()).unwrap();
+        assert_eq!(map.insert("b", ()), Err(KvError::Full { capacity: 1 }));
+        assert_eq!(map.insert("a", ()), Ok(Some(())));
+        assert_eq!(
+            map.insert(&"k".repeat(MAX_KEY_LEN + 1), ()),
+            Err(KvError::KeyTooLong {
+                len: MAX_KEY_LEN + 1
+            })
+        );
+
+        assert_eq!(parse_line("language:Rust"), Ok(("language", "Rust")));
+        assert_eq!(parse_line("url:http://x"), Ok(("url", "http://x")));
+        assert_eq!(parse_line(":x"), Err(KvError::EmptyKey));
+        assert_eq!(
+            parse_line("nothing").unwrap_err().to_string(),
+            "expected `key:value`"
+        );
+    }
+}
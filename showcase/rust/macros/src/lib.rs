@@ -0,0 +1,195 @@
+//! Procedural macros for the showcase database
+//!
+//! - `#[derive(DbRecord)]` flattens a struct into database entries
+//! - `#[timed]` reports slow calls in debug builds
+//! - `db_ops!` builds a batch of `DbOperation`s from a compact syntax
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Expr, Fields, Ident, ItemFn, LitInt, LitStr,
+    Token,
+};
+
+/// Derives `DB_FIELDS` and `db_entries(&self) -> Vec<(String, String)>`
+///
+/// Keys are `prefix.field`. The prefix defaults to the lowercased type name
+/// and is set with `#[db(prefix = "...")]`; fields take `#[db(rename = "...")]`
+/// and `#[db(skip)]`. Every stored field must implement `Display`.
+#[proc_macro_derive(DbRecord, attributes(db))]
+pub fn derive_db_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_db_record(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_db_record(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut prefix = input.ident.to_string().to_lowercase();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("db")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                prefix = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `prefix = \"...\"`"))
+            }
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "DbRecord needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "DbRecord can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut keys = Vec::new();
+    let mut idents = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut name = ident.to_string();
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("db")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip` or `rename = \"...\"`"))
+                }
+            })?;
+        }
+        if !skip {
+            keys.push(format!("{}.{}", prefix, name));
+            idents.push(ident);
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Database keys this record is stored under, in field order
+            pub const DB_FIELDS: &'static [&'static str] = &[#(#keys),*];
+
+            /// `(key, value)` pairs ready for `Database::insert`
+            pub fn db_entries(&self) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                ::std::vec![#((
+                    ::std::string::String::from(#keys),
+                    ::std::string::ToString::to_string(&self.#idents),
+                )),*]
+            }
+        }
+    })
+}
+
+/// Logs calls that take at least `threshold_ms` (default 0) in debug builds
+///
+/// The timer is a drop guard, so early returns, `?` and panics are all
+/// measured and the function body is left untouched.
+#[proc_macro_attribute]
+pub fn timed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut threshold_ms: u64 = 0;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("threshold_ms") {
+            threshold_ms = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            Ok(())
+        } else {
+            Err(meta.error("expected `threshold_ms = <integer>`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+
+    let mut func = parse_macro_input!(item as ItemFn);
+    let name = func.sig.ident.to_string();
+    let body = &func.block;
+    func.block = parse_quote!({
+        struct TimedGuard(&'static str, ::std::time::Instant, u128);
+
+        impl ::std::ops::Drop for TimedGuard {
+            fn drop(&mut self) {
+                let elapsed = self.1.elapsed();
+                if cfg!(debug_assertions) && elapsed.as_millis() >= self.2 {
+                    ::std::eprintln!("[timed] {} took {:?}", self.0, elapsed);
+                }
+            }
+        }
+
+        let _timed = TimedGuard(#name, ::std::time::Instant::now(), #threshold_ms as u128);
+        #body
+    });
+    quote!(#func).into()
+}
+
+struct Op {
+    kind: Ident,
+    key: Expr,
+    value: Option<Expr>,
+}
+
+impl Parse for Op {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        let key: Expr = input.parse()?;
+        let value = match kind.to_string().as_str() {
+            "insert" | "update" => {
+                input.parse::<Token![=>]>()?;
+                Some(input.parse()?)
+            }
+            "retrieve" | "delete" => None,
+            _ => {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    "expected `insert`, `retrieve`, `update` or `delete`",
+                ))
+            }
+        };
+        Ok(Op { kind, key, value })
+    }
+}
+
+/// Builds a `Vec<DbOperation>`; `DbOperation` must be in scope
+///
+/// ```ignore
+/// let ops = db_ops! {
+///     insert "language" => "Rust";
+///     retrieve "language";
+///     update "language" => "Rust 2021";
+///     delete "language";
+/// };
+/// ```
+#[proc_macro]
+pub fn db_ops(input: TokenStream) -> TokenStream {
+    let ops = parse_macro_input!(input with Punctuated::<Op, Token![;]>::parse_terminated);
+    let ops = ops.into_iter().map(|Op { kind, key, value }| {
+        let key = quote!(::std::convert::Into::into(#key));
+        match (kind.to_string().as_str(), value) {
+            ("insert", Some(value)) => quote! {
+                DbOperation::Insert { key: #key, value: ::std::convert::Into::into(#value) }
+            },
+            ("update", Some(value)) => quote! {
+                DbOperation::Update { key: #key, value: ::std::convert::Into::into(#value) }
+            },
+            ("retrieve", _) => quote!(DbOperation::Retrieve { key: #key }),
+            _ => quote!(DbOperation::Delete { key: #key }),
+        }
+    });
+    quote!(::std::vec![#(#ops),*]).into()
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use gvs_macros::{db_ops, timed, DbRecord};
+
+#[derive(DbRecord)]
+#[db(prefix = "user")]
+struct User {
+    name: String,
+    #[db(rename = "years")]
+    age: u32,
+    #[db(skip)]
+    #[allow(dead_code)]
+    password: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum DbOperation {
+    Insert { key: Arc<str>, value: Arc<str> },
+    Retrieve { key: Arc<str> },
+    Delete { key: Arc<str> },
+    Update { key: Arc<str>, value: Arc<str> },
+}
+
+#[timed(threshold_ms = 1000)]
+fn parse_port(input: &str) -> Result<u16, std::num::ParseIntError> {
+    let port = input.trim().parse()?;
+    Ok(port)
+}
+
+#[test]
+fn test_derive_db_record() {
+    let user = User {
+        name: "ferris".to_string(),
+        age: 8,
+        password: "hunter2".to_string(),
+    };
+
+    assert_eq!(User::DB_FIELDS, &["user.name", "user.years"]);
+    assert_eq!(
+        user.db_entries(),
+        vec![
+            ("user.name".to_string(), "ferris".to_string()),
+            ("user.years".to_string(), "8".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_timed_keeps_control_flow() {
+    assert_eq!(parse_port(" 8080 "), Ok(8080));
+    assert!(parse_port("http").is_err());
+}
+
+#[test]
+fn test_db_ops() {
+    let key = String::from("language");
+    let ops = db_ops! {
+        insert "language" => "Rust";
+        retrieve key.as_str();
+        update "language" => format!("Rust {}", 2021);
+        delete "language";
+    };
+
+    assert_eq!(
+        ops,
+        vec![
+            DbOperation::Insert {
+                key: "language".into(),
+                value: "Rust".into()
+            },
+            DbOperation::Retrieve {
+                key: "language".into()
+            },
+            DbOperation::Update {
+                key: "language".into(),
+                value: "Rust 2021".into()
+            },
+            DbOperation::Delete {
+                key: "language".into()
+            },
+        ]
+    );
+}
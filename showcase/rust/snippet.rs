@@ -26,6 +26,14 @@ pub enum Status {
     Active,
     Inactive,
     Pending(u32),
+    Draining { remaining: u32 },
+    Failed(String),
+}
+
+impl Status {
+    pub fn is_available(&self) -> bool {
+        matches!(self, Status::Active | Status::Draining { .. })
+    }
 }
 
 pub trait Handler {
@@ -45,11 +53,13 @@ pub fn process_items<T: Handler>(handler: &T, configs: Vec<Config>) -> HashMap<S
     let mut results = HashMap::new();
 
     for config in configs {
-        if let Ok(_) = handler.handle(&config) {
-            results.insert(config.name.clone(), Status::Active);
-        } else {
-            results.insert(config.name.clone(), Status::Inactive);
-        }
+        match handler.handle(&config) {
+            Ok(()) => results.insert(config.name.clone(), Status::Active),
+            Err(e) if config.timeout == 0 => {
+                results.insert(config.name.clone(), Status::Failed(e.to_string()))
+            }
+            Err(_) => results.insert(config.name.clone(), Status::Inactive),
+        };
     }
 
     results
@@ -85,6 +95,8 @@ fn main() {
         Some(Status::Active) => println!("Primary is active"),
         Some(Status::Inactive) => println!("Primary is inactive"),
         Some(Status::Pending(id)) => println!("Primary pending: {}", id),
+        Some(Status::Draining { remaining }) => println!("Primary draining: {} left", remaining),
+        Some(Status::Failed(reason)) => println!("Primary failed: {}", reason),
         None => println!("Primary not found"),
     }
 }
@@ -0,0 +1,207 @@
+//! Bump arena for short-lived, allocation-heavy exports
+//!
+//! Memory is carved out of large chunks and released all at once when the
+//! arena is dropped, so copying thousands of entries costs a handful of heap
+//! allocations. Only `Copy` data is accepted, since no destructors ever run.
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::hash::BuildHasher;
+use std::mem::MaybeUninit;
+use std::ptr::{self, NonNull};
+
+use crate::StrDatabase;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+const CHUNK_ALIGN: usize = 16;
+
+/// Types for which the all-zero byte pattern is a valid value
+///
+/// # Safety
+///
+/// Implementors must be valid when every byte is zero, which rules out
+/// references, `NonNull`, most enums and anything with a niche.
+pub unsafe trait Zeroable: Copy {}
+
+macro_rules! impl_zeroable {
+    ($($ty:ty),*) => {
+        $(unsafe impl Zeroable for $ty {})*
+    };
+}
+
+impl_zeroable!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    used: usize,
+}
+
+impl Chunk {
+    fn new(layout: Layout) -> Self {
+        // SAFETY: the arena never asks for a zero-sized chunk
+        let raw = unsafe { alloc::alloc(layout) };
+        let ptr = NonNull::new(raw).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        Self {
+            ptr,
+            layout,
+            used: 0,
+        }
+    }
+
+    fn bump(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let base = self.ptr.as_ptr() as usize;
+        let start = (base + self.used).checked_next_multiple_of(layout.align())? - base;
+        let end = start.checked_add(layout.size())?;
+        if end > self.layout.size() {
+            return None;
+        }
+        self.used = end;
+        // SAFETY: `start <= end <= layout.size()`, so the pointer stays inside the chunk
+        Some(unsafe { NonNull::new_unchecked(self.ptr.as_ptr().add(start)) })
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` came from `alloc::alloc` with this exact layout
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/// Chunked bump allocator handing out references tied to its own lifetime
+pub struct Arena {
+    chunks: RefCell<Vec<Chunk>>,
+    chunk_size: usize,
+}
+
+// SAFETY: the arena owns its chunks outright; moving it between threads is
+// fine because nothing can borrow from it across the move.
+unsafe impl Send for Arena {}
+
+// every allocation is a fresh region, so `&mut` from `&self` never aliases
+#[allow(clippy::mut_from_ref)]
+impl Arena {
+    pub fn new() -> Self {
+        Self::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    /// Number of chunks allocated so far
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    /// Bytes handed out, including alignment padding
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.used).sum()
+    }
+
+    fn alloc_raw(&self, layout: Layout) -> NonNull<u8> {
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(ptr) = chunks.last_mut().and_then(|chunk| chunk.bump(layout)) {
+            return ptr;
+        }
+        let size = layout.size().max(self.chunk_size);
+        let align = layout.align().max(CHUNK_ALIGN);
+        let mut chunk = Chunk::new(Layout::from_size_align(size, align).expect("chunk layout"));
+        let ptr = chunk.bump(layout).expect("fresh chunk fits the request");
+        chunks.push(chunk);
+        ptr
+    }
+
+    fn alloc_uninit<T>(&self) -> &mut MaybeUninit<T> {
+        let ptr = self.alloc_raw(Layout::new::<T>()).cast::<MaybeUninit<T>>();
+        // SAFETY: the slot is aligned, sized for `T` and handed out exactly once
+        unsafe { &mut *ptr.as_ptr() }
+    }
+
+    pub fn alloc<T: Copy>(&self, value: T) -> &mut T {
+        self.alloc_uninit().write(value)
+    }
+
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        let layout = Layout::array::<T>(src.len()).expect("slice layout");
+        let dst = self.alloc_raw(layout).cast::<T>().as_ptr();
+        // SAFETY: `dst` is fresh, aligned and large enough for `src.len()` values
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+            std::slice::from_raw_parts_mut(dst, src.len())
+        }
+    }
+
+    pub fn alloc_str(&self, src: &str) -> &str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        // SAFETY: the bytes were copied verbatim from a `&str`
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Allocates `len` zeroed values without writing them one by one
+    pub fn alloc_zeroed<T: Zeroable>(&self, len: usize) -> &mut [T] {
+        let layout = Layout::array::<T>(len).expect("slice layout");
+        let dst = self.alloc_raw(layout).cast::<T>().as_ptr();
+        // SAFETY: all-zero bytes are a valid `T` by the `Zeroable` contract
+        unsafe {
+            ptr::write_bytes(dst, 0, len);
+            std::slice::from_raw_parts_mut(dst, len)
+        }
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Copies every entry into `arena`; the pairs stay valid after the database changes
+    pub fn export_into<'a>(&self, arena: &'a Arena) -> Vec<(&'a str, &'a str)> {
+        self.iter()
+            .map(|(key, value)| (arena.alloc_str(key), arena.alloc_str(value)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_alloc_alignment_and_chunks() {
+        let arena = Arena::with_chunk_size(64);
+        let byte = arena.alloc(7u8);
+        let word = arena.alloc(0xDEAD_BEEF_u64);
+        let zeros = arena.alloc_zeroed::<u32>(4);
+
+        assert_eq!(*byte, 7);
+        assert_eq!(*word, 0xDEAD_BEEF);
+        assert_eq!(word as *const u64 as usize % std::mem::align_of::<u64>(), 0);
+        assert_eq!(zeros, &[0, 0, 0, 0]);
+
+        let big = arena.alloc_slice_copy(&[1u8; 100]);
+        assert_eq!(big.len(), 100);
+        assert_eq!(arena.chunk_count(), 2);
+    }
+
+    #[test]
+    fn test_export_outlives_database() {
+        let arena = Arena::new();
+        let mut exported = {
+            let mut db = MemoryDatabase::new();
+            db.insert("language", "Rust");
+            db.insert("year", "2010");
+            db.export_into(&arena)
+        };
+
+        exported.sort();
+        assert_eq!(exported, vec![("language", "Rust"), ("year", "2010")]);
+        assert_eq!(arena.chunk_count(), 1);
+    }
+}
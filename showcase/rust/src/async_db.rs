@@ -0,0 +1,120 @@
+//! Awaitable storage for async handlers (used with the `async` feature)
+//!
+//! `AsyncDatabase` futures are `Send`, so handlers can await storage from
+//! tasks spawned on any Tokio runtime. The in-memory adapter holds a Tokio
+//! `RwLock`, which parks waiting tasks instead of blocking their threads.
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::{shared, Database, DbOperation, MemoryDatabase};
+
+/// Storage whose operations may wait, e.g. on a lock or the network
+pub trait AsyncDatabase: Send + Sync {
+    fn insert(&self, key: Arc<str>, value: Arc<str>) -> impl Future<Output = ()> + Send;
+    fn retrieve(&self, key: &str) -> impl Future<Output = Option<Arc<str>>> + Send;
+    /// Removes `key`, resolving to true if it was present
+    fn delete(&self, key: &str) -> impl Future<Output = bool> + Send;
+}
+
+/// A `MemoryDatabase` behind a Tokio `RwLock`
+#[derive(Debug, Default)]
+pub struct AsyncMemoryDatabase {
+    inner: RwLock<MemoryDatabase>,
+}
+
+impl AsyncMemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `op`, sharing the lock for reads as `ConcurrentDatabase` does
+    pub async fn execute(&self, op: &DbOperation) -> Result<String, String> {
+        if let Some(result) = op.execute_read(&*self.inner.read().await) {
+            return result;
+        }
+        op.execute(&mut *self.inner.write().await)
+    }
+
+    pub fn into_inner(self) -> MemoryDatabase {
+        self.inner.into_inner()
+    }
+}
+
+impl From<MemoryDatabase> for AsyncMemoryDatabase {
+    fn from(db: MemoryDatabase) -> Self {
+        Self {
+            inner: RwLock::new(db),
+        }
+    }
+}
+
+impl AsyncDatabase for AsyncMemoryDatabase {
+    async fn insert(&self, key: Arc<str>, value: Arc<str>) {
+        self.inner.write().await.insert(key, value);
+    }
+
+    async fn retrieve(&self, key: &str) -> Option<Arc<str>> {
+        self.inner.read().await.get(key).cloned().map(shared)
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        self.inner.write().await.remove(key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::block_on;
+
+    #[test]
+    fn test_adapter_round_trip() {
+        let db = AsyncMemoryDatabase::new();
+        block_on(async {
+            db.insert("language".into(), "Rust".into()).await;
+            assert_eq!(db.retrieve("language").await.as_deref(), Some("Rust"));
+            assert!(db.delete("language").await);
+            assert!(!db.delete("language").await);
+
+            let op = DbOperation::Retrieve {
+                key: "language".into(),
+            };
+            assert_eq!(
+                db.execute(&op).await,
+                Err("Key not found: language".to_string())
+            );
+        });
+        assert!(db.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_tasks_share_the_database() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let db = Arc::new(AsyncMemoryDatabase::new());
+        let total = runtime.block_on(async {
+            let tasks: Vec<_> = (0..8)
+                .map(|i| {
+                    let db = Arc::clone(&db);
+                    tokio::spawn(async move {
+                        db.insert(format!("task:{}", i).into(), i.to_string().into())
+                            .await;
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap();
+            }
+            let mut total = 0;
+            for i in 0..8 {
+                let value = db.retrieve(&format!("task:{}", i)).await.unwrap();
+                total += value.parse::<u32>().unwrap();
+            }
+            total
+        });
+        assert_eq!(total, 28);
+    }
+}
@@ -0,0 +1,405 @@
+//! User accounts persisted inside the database itself
+//!
+//! Accounts live under the reserved `__auth:user:` key prefix as
+//! `v2$<failed attempts>$<disabled>$<roles>$<hash>` records, where the hash
+//! is an Argon2id string in PHC format (used with the `auth` feature).
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+use crate::{Database, DbOperation};
+
+pub mod roles;
+
+pub use roles::{AdminCommand, Role};
+
+/// Keys under this prefix hold accounts and are reserved for admins
+pub const RESERVED_PREFIX: &str = "__auth:";
+const USER_PREFIX: &str = "__auth:user:";
+
+/// Failed attempts after which an account is locked until `unlock`
+pub const MAX_FAILED_ATTEMPTS: u32 = 5;
+
+/// Errors returned by account operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    UserExists(String),
+    UnknownUser(String),
+    InvalidPassword,
+    Locked(String),
+    Disabled(String),
+    CorruptRecord(String),
+    Forbidden(String),
+    InvalidCommand(String),
+    Hashing(String),
+    Refused(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::UserExists(name) => write!(f, "User already exists: {}", name),
+            AuthError::UnknownUser(name) => write!(f, "Unknown user: {}", name),
+            AuthError::InvalidPassword => write!(f, "Invalid password"),
+            AuthError::Locked(name) => write!(f, "Account locked: {}", name),
+            AuthError::Disabled(name) => write!(f, "Account disabled: {}", name),
+            AuthError::CorruptRecord(name) => write!(f, "Corrupt account record: {}", name),
+            AuthError::Forbidden(name) => write!(f, "Permission denied for: {}", name),
+            AuthError::InvalidCommand(reason) => write!(f, "{}", reason),
+            AuthError::Hashing(reason) => write!(f, "Password hashing failed: {}", reason),
+            AuthError::Refused(reason) => write!(f, "Account not saved: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+#[derive(Debug, Clone)]
+struct Account {
+    hash: String,
+    failed_attempts: u32,
+    disabled: bool,
+    roles: Vec<Role>,
+}
+
+impl Account {
+    fn encode(&self) -> String {
+        let roles: Vec<&str> = self.roles.iter().map(|role| role.as_str()).collect();
+        format!(
+            "v2${}${}${}${}",
+            self.failed_attempts,
+            u8::from(self.disabled),
+            roles.join(","),
+            self.hash
+        )
+    }
+
+    fn decode(record: &str) -> Option<Self> {
+        let mut fields = record.splitn(5, '$');
+        if fields.next()? != "v2" {
+            return None;
+        }
+        let failed_attempts = fields.next()?.parse().ok()?;
+        let disabled = fields.next()? == "1";
+        let roles = match fields.next()? {
+            "" => Vec::new(),
+            roles => roles
+                .split(',')
+                .map(|role| role.parse().ok())
+                .collect::<Option<_>>()?,
+        };
+        let hash = fields.next()?.to_string();
+        Some(Self {
+            hash,
+            failed_attempts,
+            disabled,
+            roles,
+        })
+    }
+}
+
+/// Account store layered over any `Database`
+pub struct Users<'a, D: Database> {
+    db: &'a mut D,
+}
+
+impl<'a, D: Database> Users<'a, D> {
+    pub fn new(db: &'a mut D) -> Self {
+        Self { db }
+    }
+
+    /// Creates an account with a freshly salted password hash
+    pub fn create(&mut self, name: &str, password: &str) -> Result<(), AuthError> {
+        if self.db.retrieve(&user_key(name)).is_some() {
+            return Err(AuthError::UserExists(name.to_string()));
+        }
+        let account = Account {
+            hash: hash_password(password)?,
+            failed_attempts: 0,
+            disabled: false,
+            roles: Vec::new(),
+        };
+        self.store(name, &account)
+    }
+
+    /// Checks a password, counting failures towards the lockout threshold
+    pub fn verify(&mut self, name: &str, password: &str) -> Result<(), AuthError> {
+        let mut account = self.load(name)?;
+        if account.disabled {
+            return Err(AuthError::Disabled(name.to_string()));
+        }
+        if account.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            return Err(AuthError::Locked(name.to_string()));
+        }
+
+        let hash = PasswordHash::new(&account.hash)
+            .map_err(|_| AuthError::CorruptRecord(name.to_string()))?;
+        let matches = Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok();
+        if matches {
+            if account.failed_attempts > 0 {
+                account.failed_attempts = 0;
+                self.store(name, &account)?;
+            }
+            Ok(())
+        } else {
+            account.failed_attempts += 1;
+            self.store(name, &account)?;
+            if account.failed_attempts >= MAX_FAILED_ATTEMPTS {
+                Err(AuthError::Locked(name.to_string()))
+            } else {
+                Err(AuthError::InvalidPassword)
+            }
+        }
+    }
+
+    /// Disables an account; `verify` rejects it until it is recreated
+    pub fn disable(&mut self, name: &str) -> Result<(), AuthError> {
+        let mut account = self.load(name)?;
+        account.disabled = true;
+        self.store(name, &account)
+    }
+
+    /// Clears the failed-attempt counter of a locked account
+    pub fn unlock(&mut self, name: &str) -> Result<(), AuthError> {
+        let mut account = self.load(name)?;
+        account.failed_attempts = 0;
+        self.store(name, &account)
+    }
+
+    /// Returns the roles granted to a user
+    pub fn roles(&self, name: &str) -> Result<Vec<Role>, AuthError> {
+        Ok(self.load(name)?.roles)
+    }
+
+    /// Grants `role` to a user; granting twice is a no-op
+    pub fn grant(&mut self, name: &str, role: Role) -> Result<(), AuthError> {
+        let mut account = self.load(name)?;
+        if !account.roles.contains(&role) {
+            account.roles.push(role);
+            account.roles.sort();
+            self.store(name, &account)?;
+        }
+        Ok(())
+    }
+
+    /// Revokes `role` from a user
+    pub fn revoke(&mut self, name: &str, role: Role) -> Result<(), AuthError> {
+        let mut account = self.load(name)?;
+        account.roles.retain(|granted| *granted != role);
+        self.store(name, &account)
+    }
+
+    /// Checks that one of the user's roles permits `op`
+    pub fn authorize(&self, name: &str, op: &DbOperation) -> Result<(), AuthError> {
+        authorize(&*self.db, name, op)
+    }
+
+    /// Runs an `AdminCommand` line on behalf of `actor`, who must be an admin
+    pub fn execute_admin(&mut self, actor: &str, line: &str) -> Result<String, AuthError> {
+        if !self.roles(actor)?.contains(&Role::Admin) {
+            return Err(AuthError::Forbidden(actor.to_string()));
+        }
+        match AdminCommand::parse(line).map_err(AuthError::InvalidCommand)? {
+            AdminCommand::Grant { role, user } => {
+                self.grant(&user, role)?;
+                Ok(format!("Granted: {} to {}", role, user))
+            }
+            AdminCommand::Revoke { role, user } => {
+                self.revoke(&user, role)?;
+                Ok(format!("Revoked: {} from {}", role, user))
+            }
+            AdminCommand::CreateUser { user, password } => {
+                self.create(&user, &password)?;
+                Ok(format!("Created: {}", user))
+            }
+        }
+    }
+
+    fn load(&self, name: &str) -> Result<Account, AuthError> {
+        load(&*self.db, name)
+    }
+
+    fn store(&mut self, name: &str, account: &Account) -> Result<(), AuthError> {
+        self.db
+            .try_insert(user_key(name), account.encode())
+            .map_err(|e| AuthError::Refused(e.to_string()))
+    }
+}
+
+/// Checks that one of the user's roles permits `op`, reading `db` only
+///
+/// Lets a server check each command under a shared lock; `Users` needs
+/// the database exclusively.
+pub fn authorize<D: Database>(db: &D, name: &str, op: &DbOperation) -> Result<(), AuthError> {
+    let account = load(db, name)?;
+    if account.disabled {
+        return Err(AuthError::Disabled(name.to_string()));
+    }
+    if account.roles.iter().any(|role| role.allows(op)) {
+        Ok(())
+    } else {
+        Err(AuthError::Forbidden(name.to_string()))
+    }
+}
+
+fn load<D: Database>(db: &D, name: &str) -> Result<Account, AuthError> {
+    let record = db
+        .retrieve(&user_key(name))
+        .ok_or_else(|| AuthError::UnknownUser(name.to_string()))?;
+    Account::decode(record).ok_or_else(|| AuthError::CorruptRecord(name.to_string()))
+}
+
+fn user_key(name: &str) -> String {
+    format!("{}{}", USER_PREFIX, name)
+}
+
+fn new_salt() -> Result<SaltString, AuthError> {
+    let mut salt = Vec::with_capacity(16);
+    for half in 0..2u8 {
+        // std seeds RandomState from the OS once per thread and bumps the
+        // keys for every new(), so salts are unique but not independent
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u8(half);
+        salt.extend_from_slice(&hasher.finish().to_le_bytes());
+    }
+    SaltString::encode_b64(&salt).map_err(|e| AuthError::Hashing(e.to_string()))
+}
+
+/// Hashes `password` with Argon2id under a fresh salt
+fn hash_password(password: &str) -> Result<String, AuthError> {
+    let salt = new_salt()?;
+    hasher()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AuthError::Hashing(e.to_string()))
+}
+
+#[cfg(not(test))]
+fn hasher() -> Argon2<'static> {
+    Argon2::default()
+}
+
+/// The cheapest parameters, keeping tests quick; every hash records its
+/// own cost, so `verify` needs no matching switch
+#[cfg(test)]
+fn hasher() -> Argon2<'static> {
+    use argon2::{Algorithm, Params, Version};
+    let params = Params::new(Params::MIN_M_COST, Params::MIN_T_COST, 1, None)
+        .expect("minimum Argon2 parameters are valid");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quota::QuotaPolicy;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_create_and_verify() {
+        let mut db = MemoryDatabase::new();
+        let mut users = Users::new(&mut db);
+
+        users.create("alice", "hunter2").unwrap();
+        assert_eq!(
+            users.create("alice", "other"),
+            Err(AuthError::UserExists("alice".to_string()))
+        );
+        assert!(users.verify("alice", "hunter2").is_ok());
+        assert_eq!(
+            users.verify("alice", "wrong"),
+            Err(AuthError::InvalidPassword)
+        );
+
+        users.disable("alice").unwrap();
+        assert_eq!(
+            users.verify("alice", "hunter2"),
+            Err(AuthError::Disabled("alice".to_string()))
+        );
+        let record = db.retrieve("__auth:user:alice").unwrap();
+        assert!(record.contains("$argon2id$"));
+        assert!(!record.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_refused_writes_surface() {
+        let mut db = MemoryDatabase::with_max_bytes(64, QuotaPolicy::Refuse);
+        let mut users = Users::new(&mut db);
+        assert!(matches!(
+            users.create("dave", "pw"),
+            Err(AuthError::Refused(_))
+        ));
+        assert_eq!(
+            users.verify("dave", "pw"),
+            Err(AuthError::UnknownUser("dave".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_lockout_after_failures() {
+        let mut db = MemoryDatabase::new();
+        let mut users = Users::new(&mut db);
+        users.create("bob", "secret").unwrap();
+
+        for _ in 1..MAX_FAILED_ATTEMPTS {
+            assert_eq!(
+                users.verify("bob", "guess"),
+                Err(AuthError::InvalidPassword)
+            );
+        }
+        assert_eq!(
+            users.verify("bob", "guess"),
+            Err(AuthError::Locked("bob".to_string()))
+        );
+        assert_eq!(
+            users.verify("bob", "secret"),
+            Err(AuthError::Locked("bob".to_string()))
+        );
+
+        users.unlock("bob").unwrap();
+        assert!(users.verify("bob", "secret").is_ok());
+    }
+
+    #[test]
+    fn test_roles_gate_operations() {
+        let mut db = MemoryDatabase::new();
+        let mut users = Users::new(&mut db);
+        users.create("root", "pw").unwrap();
+        users.create("carol", "pw").unwrap();
+        users.grant("root", Role::Admin).unwrap();
+
+        let write = DbOperation::Insert {
+            key: "k".into(),
+            value: "v".into(),
+        };
+        assert_eq!(
+            users.authorize("carol", &write),
+            Err(AuthError::Forbidden("carol".to_string()))
+        );
+
+        assert_eq!(
+            users.execute_admin("root", "GRANT writer TO carol"),
+            Ok("Granted: writer to carol".to_string())
+        );
+        assert!(users.authorize("carol", &write).is_ok());
+        assert_eq!(
+            users.execute_admin("carol", "GRANT admin TO carol"),
+            Err(AuthError::Forbidden("carol".to_string()))
+        );
+
+        users
+            .execute_admin("root", "REVOKE writer FROM carol")
+            .unwrap();
+        assert_eq!(users.roles("carol"), Ok(Vec::new()));
+        users.execute_admin("root", "CREATE USER erin pw").unwrap();
+        assert_eq!(
+            authorize(&db, "erin", &write),
+            Err(AuthError::Forbidden("erin".to_string()))
+        );
+    }
+}
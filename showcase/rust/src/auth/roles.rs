@@ -0,0 +1,179 @@
+//! Role-based permissions for database operations
+use std::fmt;
+use std::str::FromStr;
+
+use super::RESERVED_PREFIX;
+use crate::DbOperation;
+
+/// Roles ordered by privilege; each one includes everything below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Reader,
+    Writer,
+    Admin,
+}
+
+impl Role {
+    /// Returns true when this role may run `op`
+    ///
+    /// Keys under `RESERVED_PREFIX`, in any case, need `Admin` whatever
+    /// the operation, so account records can't be read or rewritten.
+    pub fn allows(self, op: &DbOperation) -> bool {
+        let key = op.key().as_bytes();
+        let reserved = key.len() >= RESERVED_PREFIX.len()
+            && key[..RESERVED_PREFIX.len()].eq_ignore_ascii_case(RESERVED_PREFIX.as_bytes());
+        if reserved {
+            return self >= Role::Admin;
+        }
+        match op {
+            DbOperation::Retrieve { .. } => self >= Role::Reader,
+            DbOperation::Insert { .. }
+            | DbOperation::Update { .. }
+            | DbOperation::Append { .. }
+            | DbOperation::Increment { .. }
+            | DbOperation::Delete { .. } => self >= Role::Writer,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Writer => "writer",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "reader" => Ok(Role::Reader),
+            "writer" => Ok(Role::Writer),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role: {}", s)),
+        }
+    }
+}
+
+/// Role management command accepted by the admin interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Grant { role: Role, user: String },
+    Revoke { role: Role, user: String },
+    CreateUser { user: String, password: String },
+}
+
+impl AdminCommand {
+    /// Whether `line` starts with an admin verb, so a malformed one is
+    /// reported by `parse` rather than run as a database command
+    pub fn recognizes(line: &str) -> bool {
+        line.split_whitespace().next().is_some_and(|verb| {
+            ["grant", "revoke", "create"]
+                .iter()
+                .any(|admin| verb.eq_ignore_ascii_case(admin))
+        })
+    }
+
+    /// Parses `GRANT <role> TO <user>`, `REVOKE <role> FROM <user>` or
+    /// `CREATE USER <user> <password>`
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [verb, noun, user, password]
+                if verb.eq_ignore_ascii_case("create") && noun.eq_ignore_ascii_case("user") =>
+            {
+                Ok(AdminCommand::CreateUser {
+                    user: user.to_string(),
+                    password: password.to_string(),
+                })
+            }
+            [verb, role, to, user]
+                if verb.eq_ignore_ascii_case("grant") && to.eq_ignore_ascii_case("to") =>
+            {
+                Ok(AdminCommand::Grant {
+                    role: role.parse()?,
+                    user: user.to_string(),
+                })
+            }
+            [verb, role, from, user]
+                if verb.eq_ignore_ascii_case("revoke") && from.eq_ignore_ascii_case("from") =>
+            {
+                Ok(AdminCommand::Revoke {
+                    role: role.parse()?,
+                    user: user.to_string(),
+                })
+            }
+            _ => Err(format!("Invalid admin command: {}", line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        let read = DbOperation::Retrieve { key: "k".into() };
+        let write = DbOperation::Delete { key: "k".into() };
+
+        assert!(Role::Reader.allows(&read));
+        assert!(!Role::Reader.allows(&write));
+        assert!(Role::Writer.allows(&write));
+        assert!(Role::Admin.allows(&write));
+    }
+
+    #[test]
+    fn test_account_records_need_admin() {
+        let overwrite = DbOperation::Insert {
+            key: "__auth:user:alice".into(),
+            value: "v1$$$0$false$admin".into(),
+        };
+        let read = DbOperation::Retrieve {
+            key: "__AUTH:user:alice".into(),
+        };
+
+        assert!(!Role::Writer.allows(&overwrite));
+        assert!(!Role::Reader.allows(&read));
+        assert!(!Role::Writer.allows(&read));
+        assert!(Role::Admin.allows(&overwrite));
+        assert!(Role::Admin.allows(&read));
+    }
+
+    #[test]
+    fn test_parse_admin_commands() {
+        assert_eq!(
+            AdminCommand::parse("GRANT writer TO alice"),
+            Ok(AdminCommand::Grant {
+                role: Role::Writer,
+                user: "alice".to_string(),
+            })
+        );
+        assert_eq!(
+            AdminCommand::parse("revoke ADMIN from bob"),
+            Ok(AdminCommand::Revoke {
+                role: Role::Admin,
+                user: "bob".to_string(),
+            })
+        );
+        assert_eq!(
+            AdminCommand::parse("create user carol pw"),
+            Ok(AdminCommand::CreateUser {
+                user: "carol".to_string(),
+                password: "pw".to_string(),
+            })
+        );
+        assert!(AdminCommand::parse("GRANT owner TO alice").is_err());
+        assert!(AdminCommand::parse("DROP alice").is_err());
+        assert!(AdminCommand::recognizes("Grant"));
+        assert!(!AdminCommand::recognizes("get grant"));
+    }
+}
@@ -0,0 +1,359 @@
+//! Periodic, checksummed database backups
+use std::fs;
+use std::hash::BuildHasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{perf, Error, MemoryDatabase, Service, StrDatabase};
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+const SNAPSHOT_EXTENSION: &str = "db";
+const CHECKSUM_EXTENSION: &str = "crc32";
+
+/// Where, how often and how many backups to keep
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub staging_dir: PathBuf,
+    pub destination: PathBuf,
+    pub interval: Duration,
+    pub retain: usize,
+}
+
+/// Which backups to keep after each new one is written
+///
+/// The newest backup is always kept, whatever its age.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Retention {
+    pub max_count: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+/// Takes snapshots of a shared database and keeps the newest `retain` copies
+pub struct BackupService {
+    db: Arc<Mutex<MemoryDatabase>>,
+    config: BackupConfig,
+    worker: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl BackupService {
+    pub fn new(db: Arc<Mutex<MemoryDatabase>>, config: BackupConfig) -> Self {
+        Self {
+            db,
+            config,
+            worker: None,
+        }
+    }
+
+    /// Takes one backup immediately, returning the verified copy's path
+    pub fn run_once(&self) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.config.staging_dir)?;
+        fs::create_dir_all(&self.config.destination)?;
+        run_backup(&self.db, &self.config)
+    }
+
+    /// Lists backups in the destination, oldest first
+    pub fn backups(&self) -> io::Result<Vec<PathBuf>> {
+        list_snapshots(&self.config.destination)
+    }
+}
+
+impl Service for BackupService {
+    /// Starts taking a backup every `interval` on a background thread
+    fn start(&mut self) -> Result<(), Error> {
+        if self.worker.is_some() {
+            return Err(Error::AlreadyRunning("Backup service"));
+        }
+        fs::create_dir_all(&self.config.staging_dir)?;
+        fs::create_dir_all(&self.config.destination)?;
+
+        let (stop, stopped) = mpsc::channel();
+        let db = Arc::clone(&self.db);
+        let config = self.config.clone();
+        // a stop message or a dropped sender ends the loop
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(config.interval) {
+                if let Err(e) = run_backup(&db, &config) {
+                    eprintln!("Backup failed: {}", e);
+                }
+            }
+        });
+        self.worker = Some((stop, handle));
+        Ok(())
+    }
+
+    /// Signals the background thread and waits for it to finish
+    fn stop(&mut self) -> Result<(), Error> {
+        if let Some((stop, handle)) = self.worker.take() {
+            let _ = stop.send(());
+            handle.join().map_err(|_| Error::WorkerPanicked("Backup"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BackupService {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// A snapshot file name embedding the current time, sorting chronologically
+fn snapshot_name() -> io::Result<String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(io::Error::other)?
+        .as_nanos();
+    Ok(format!(
+        "{}{:039}.{}",
+        SNAPSHOT_PREFIX, stamp, SNAPSHOT_EXTENSION
+    ))
+}
+
+/// When the snapshot at `path` was taken, from the stamp in its name
+fn taken_at(path: &Path) -> Option<SystemTime> {
+    let name = path.file_stem()?.to_str()?;
+    let nanos: u64 = name.strip_prefix(SNAPSHOT_PREFIX)?.parse().ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_nanos(nanos))
+}
+
+fn write_checksum(path: &Path, crc: u32) -> io::Result<()> {
+    fs::write(
+        path.with_extension(CHECKSUM_EXTENSION),
+        format!("{:08x}\n", crc),
+    )
+}
+
+fn run_backup(db: &Mutex<MemoryDatabase>, config: &BackupConfig) -> io::Result<PathBuf> {
+    let name = snapshot_name()?;
+
+    let staged = config.staging_dir.join(&name);
+    let waiting = perf::start();
+    let guard = db
+        .lock()
+        .map_err(|_| io::Error::other("Database lock poisoned"))?;
+    perf::record_lock_wait(waiting);
+    guard.save_to_file(path_str(&staged)?)?;
+    drop(guard);
+    let expected = crc32(&fs::read(&staged)?);
+
+    let target = config.destination.join(&name);
+    fs::copy(&staged, &target)?;
+    fs::remove_file(&staged)?;
+    if crc32(&fs::read(&target)?) != expected {
+        fs::remove_file(&target)?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Checksum mismatch for {}", target.display()),
+        ));
+    }
+    write_checksum(&target, expected)?;
+
+    let retention = Retention {
+        max_count: Some(config.retain),
+        max_age: None,
+    };
+    prune(&config.destination, &retention)?;
+    Ok(target)
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Saves a timestamped, checksummed snapshot into `dir`, then prunes it
+    ///
+    /// `dir` is created if missing. Returns the new snapshot's path.
+    pub fn backup_to_dir(&self, dir: &Path, retention: &Retention) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let target = dir.join(snapshot_name()?);
+        self.save_to_file(path_str(&target)?)?;
+        write_checksum(&target, crc32(&fs::read(&target)?))?;
+        prune(dir, retention)?;
+        Ok(target)
+    }
+}
+
+impl MemoryDatabase {
+    /// Loads the newest snapshot in `dir` that passes verification
+    ///
+    /// Snapshots whose checksum doesn't match are skipped in favour of older
+    /// ones; fails with `NotFound` when none is usable.
+    pub fn restore_latest(dir: &Path) -> io::Result<Self> {
+        for snapshot in list_snapshots(dir)?.iter().rev() {
+            let intact = !snapshot.with_extension(CHECKSUM_EXTENSION).exists() || verify(snapshot)?;
+            if intact {
+                return Self::load_from_file(path_str(snapshot)?);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No usable backup in {}", dir.display()),
+        ))
+    }
+}
+
+/// Checks a backup against the checksum written next to it
+pub fn verify(path: &Path) -> io::Result<bool> {
+    let recorded = fs::read_to_string(path.with_extension(CHECKSUM_EXTENSION))?;
+    let recorded = u32::from_str_radix(recorded.trim(), 16)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(crc32(&fs::read(path)?) == recorded)
+}
+
+fn prune(dir: &Path, retention: &Retention) -> io::Result<()> {
+    let mut snapshots = list_snapshots(dir)?;
+    // the newest is never pruned, so it counts against `max_count` up front
+    snapshots.pop();
+    let retain = retention.max_count.map_or(usize::MAX, |count| count.max(1));
+    let excess = snapshots.len().saturating_sub(retain - 1);
+    let now = SystemTime::now();
+    let expired = |path: &Path| {
+        let age = taken_at(path).and_then(|taken| now.duration_since(taken).ok());
+        age.zip(retention.max_age)
+            .is_some_and(|(age, max_age)| age > max_age)
+    };
+    for (i, old) in snapshots.iter().enumerate() {
+        if i >= excess && !expired(old) {
+            continue;
+        }
+        fs::remove_file(old)?;
+        let checksum = old.with_extension(CHECKSUM_EXTENSION);
+        if checksum.exists() {
+            fs::remove_file(checksum)?;
+        }
+    }
+    Ok(())
+}
+
+fn list_snapshots(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_snapshot = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| {
+                name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_EXTENSION)
+            });
+        if is_snapshot {
+            snapshots.push(path);
+        }
+    }
+    // zero-padded timestamps sort chronologically
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+fn path_str(path: &Path) -> io::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Non UTF-8 path"))
+}
+
+/// Bitwise CRC-32 (IEEE), fast enough for occasional backups
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    fn config(name: &str) -> BackupConfig {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        BackupConfig {
+            staging_dir: root.join("staging"),
+            destination: root.join("backups"),
+            interval: Duration::from_millis(10),
+            retain: 2,
+        }
+    }
+
+    #[test]
+    fn test_crc32_vector() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_run_once_verifies_and_prunes() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let config = config("gvs_backup_run_once");
+        let service = BackupService::new(Arc::clone(&db), config.clone());
+
+        for i in 0..3 {
+            db.lock()
+                .unwrap()
+                .insert("round".to_string(), i.to_string());
+            let path = service.run_once().unwrap();
+            assert!(verify(&path).unwrap());
+        }
+
+        let backups = service.backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        let latest = MemoryDatabase::load_from_file(backups[1].to_str().unwrap()).unwrap();
+        assert_eq!(latest.retrieve("round"), Some("2"));
+
+        fs::remove_dir_all(config.destination.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_backup_to_dir_rotates_and_restores() {
+        let dir = std::env::temp_dir().join("gvs_backup_to_dir");
+        let _ = fs::remove_dir_all(&dir);
+        let mut db = MemoryDatabase::new();
+        let keep_two = Retention {
+            max_count: Some(2),
+            max_age: None,
+        };
+        let mut written = Vec::new();
+        for i in 0..3 {
+            db.insert("round", i.to_string());
+            written.push(db.backup_to_dir(&dir, &keep_two).unwrap());
+        }
+        assert_eq!(list_snapshots(&dir).unwrap(), &written[1..]);
+        assert_eq!(
+            MemoryDatabase::restore_latest(&dir)
+                .unwrap()
+                .retrieve("round"),
+            Some("2")
+        );
+
+        fs::write(&written[2], "round:tampered\n").unwrap();
+        let restored = MemoryDatabase::restore_latest(&dir).unwrap();
+        assert_eq!(restored.retrieve("round"), Some("1"));
+
+        let by_age = Retention {
+            max_count: None,
+            max_age: Some(Duration::ZERO),
+        };
+        let newest = db.backup_to_dir(&dir, &by_age).unwrap();
+        assert_eq!(list_snapshots(&dir).unwrap(), [newest]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(MemoryDatabase::restore_latest(&dir).is_err());
+    }
+
+    #[test]
+    fn test_start_and_stop() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let config = config("gvs_backup_start_stop");
+        let mut service = BackupService::new(db, config.clone());
+
+        service.start().unwrap();
+        assert!(service.start().is_err());
+        thread::sleep(Duration::from_millis(50));
+        service.stop().unwrap();
+
+        assert!(!service.backups().unwrap().is_empty());
+        fs::remove_dir_all(config.destination.parent().unwrap()).unwrap();
+    }
+}
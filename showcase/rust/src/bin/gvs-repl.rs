@@ -0,0 +1,124 @@
+//! Interactive shell over an in-memory database
+//!
+//! Reads one command per line from stdin, e.g. `set lang rust` or `get lang`.
+//!
+//! `gvs-repl --at <point> <wal> [snapshot]` instead opens a read-only session
+//! on the database as it was at `point`: the snapshot, if any, with the log's
+//! records replayed up to `#<sequence>`, a unix time in seconds, or a time
+//! relative to now such as `-10m` (units `s`, `m`, `h` and `d`).
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gvs_showcase::readonly::ReadOnlyDatabase;
+use gvs_showcase::{literals, patterns, MemoryDatabase};
+
+const USAGE: &str =
+    "usage: gvs-repl [--version | --at <#seq|unix secs|-<n>s|m|h|d> <wal> [snapshot]]";
+
+/// Where in the log a time-travel session stops
+enum Point {
+    Sequence(u64),
+    Time(SystemTime),
+}
+
+fn parse_point(text: &str) -> Result<Point, String> {
+    let invalid = || format!("Invalid point in time: {}", text);
+    if let Some(sequence) = text.strip_prefix('#') {
+        return sequence.parse().map(Point::Sequence).map_err(|_| invalid());
+    }
+    if let Some(ago) = text.strip_prefix('-') {
+        let split = ago.len().checked_sub(1).ok_or_else(invalid)?;
+        let (count, unit) = ago.split_at_checked(split).ok_or_else(invalid)?;
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        let seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let ago = Duration::from_secs(count.saturating_mul(seconds));
+        let at = SystemTime::now().checked_sub(ago).ok_or_else(invalid)?;
+        return Ok(Point::Time(at));
+    }
+    let seconds = text.parse().map_err(|_| invalid())?;
+    Ok(Point::Time(UNIX_EPOCH + Duration::from_secs(seconds)))
+}
+
+fn recover(point: &str, wal: &str, snapshot: Option<&str>) -> Result<ReadOnlyDatabase, String> {
+    let point = parse_point(point)?;
+    let db = match snapshot {
+        Some(path) => {
+            MemoryDatabase::load_from_file(path).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => MemoryDatabase::new(),
+    };
+    let db = match point {
+        Point::Sequence(sequence) => MemoryDatabase::recover_to_op(db, wal, sequence),
+        Point::Time(at) => MemoryDatabase::recover_to(db, wal, at),
+    };
+    db.map(ReadOnlyDatabase::from)
+        .map_err(|e| format!("{}: {}", wal, e))
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut past = None;
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["--version"] => {
+            println!("{}", literals::BANNER);
+            return Ok(());
+        }
+        ["--at", point, wal, snapshot @ ..] if snapshot.len() <= 1 => {
+            match recover(point, wal, snapshot.first().copied()) {
+                Ok(db) => past = Some(db),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+    let mut db = MemoryDatabase::with_defaults();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let prompt = if past.is_some() {
+        "gvs (past)> "
+    } else {
+        "gvs> "
+    };
+
+    loop {
+        write!(stdout, "{}", prompt)?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match line.trim() {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            command => {
+                let result = patterns::parse_command(command).and_then(|op| match &past {
+                    Some(past) => past.execute(&op),
+                    None => op.execute(&mut db),
+                });
+                match result {
+                    Ok(output) => writeln!(stdout, "{}", output)?,
+                    Err(e) => writeln!(stdout, "error: {}", e)?,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,456 @@
+//! Line-based TCP server over a shared in-memory database
+//!
+//! Every connection gets its own thread, and lookups from different
+//! connections run in parallel; commands use the REPL syntax and
+//! each reply is a single line starting with `ok` or `error`.
+//!
+//! With the `auth` feature and `GVS_ADMIN_PASSWORD` set, the server creates
+//! an `admin` account at startup and every connection has to log in with
+//! `AUTH <user> <password>` before its other commands run. Each command is
+//! then checked against the user's roles, and admins manage accounts with
+//! `CREATE USER`, `GRANT` and `REVOKE`.
+//!
+//! With `GVS_PEERS` listing the other servers' addresses, the servers elect
+//! a leader among themselves. The leader takes writes and streams them to
+//! the others, which serve reads and answer writes with `MOVED <leader>`;
+//! if the leader goes silent, a replica is elected in its place. Each
+//! server must listen on the address its peers know it by. With logins
+//! required, peers log in as `admin` and only admins may run `ELECTION`
+//! and `REPLICATE`. `ROLE` reports what the server currently is.
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "auth")]
+use gvs_showcase::auth::{self, AdminCommand, Role, Users};
+use gvs_showcase::client::{Client, ClientError};
+use gvs_showcase::cluster::election::{Message, Reply};
+use gvs_showcase::cluster::{self, Election};
+use gvs_showcase::concurrent::ConcurrentDatabase;
+use gvs_showcase::enums::OpCode;
+use gvs_showcase::replication::Replicator;
+use gvs_showcase::wal::LogRecord;
+use gvs_showcase::{literals, patterns, DbOperation, MemoryDatabase, ServerConfig};
+
+/// Silence after which a replica stands for election, before jitter
+const ELECTION_TIMEOUT: Duration = Duration::from_millis(1500);
+/// How often the leader sends heartbeats and replication keepalives
+const HEARTBEAT: Duration = Duration::from_millis(300);
+/// How long a peer may take to connect or answer
+const PEER_TIMEOUT: Duration = Duration::from_millis(250);
+/// How long a peer may take to check this server's login
+#[cfg(feature = "auth")]
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5);
+const TICK: Duration = Duration::from_millis(50);
+
+/// This server's place among the servers named in `GVS_PEERS`
+#[derive(Debug)]
+struct Node {
+    peers: Vec<String>,
+    election: Mutex<Election>,
+    /// Set while this server leads
+    replicator: Mutex<Option<Replicator>>,
+    #[cfg(feature = "auth")]
+    password: Option<String>,
+}
+
+impl Node {
+    fn from_env(local: &str) -> Option<Self> {
+        let peers: Vec<String> = std::env::var("GVS_PEERS")
+            .ok()?
+            .split(',')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty() && *peer != local)
+            .map(String::from)
+            .collect();
+        if peers.is_empty() {
+            return None;
+        }
+        // random per process, so two replicas rarely stand at once
+        let jitter = RandomState::new().hash_one(local) % 1000;
+        let timeout = ELECTION_TIMEOUT + Duration::from_millis(jitter);
+        let election = Election::new(local, peers.len(), timeout, Instant::now());
+        Some(Self {
+            peers,
+            election: Mutex::new(election),
+            replicator: Mutex::new(None),
+            #[cfg(feature = "auth")]
+            password: std::env::var("GVS_ADMIN_PASSWORD").ok(),
+        })
+    }
+
+    fn election(&self) -> MutexGuard<'_, Election> {
+        self.election.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn replicator(&self) -> MutexGuard<'_, Option<Replicator>> {
+        self.replicator.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The leader to replicate from, unless this server leads
+    fn following(&self) -> Option<String> {
+        let election = self.election();
+        let leader = election.leader()?;
+        (election.role() != cluster::Role::Leader).then(|| leader.to_string())
+    }
+
+    fn connect(&self, addr: &str, timeout: Duration) -> Result<Client, ClientError> {
+        #[allow(unused_mut)]
+        let mut client = Client::connect_timeout(addr, timeout)?;
+        #[cfg(feature = "auth")]
+        if let Some(password) = &self.password {
+            // hashing the password can take longer than an election message
+            client.set_timeout(Some(LOGIN_TIMEOUT))?;
+            client.auth("admin", password)?;
+            client.set_timeout(Some(timeout))?;
+        }
+        Ok(client)
+    }
+
+    /// Sends `message` to every peer, returning the replies that came back
+    fn broadcast(&self, message: &Message, peers: &mut HashMap<String, Client>) -> Vec<Reply> {
+        let line = message.encode();
+        let mut replies = Vec::new();
+        for peer in &self.peers {
+            let client = match peers.remove(peer) {
+                Some(client) => Ok(client),
+                None => self.connect(peer, PEER_TIMEOUT),
+            };
+            let Ok(mut client) = client else { continue };
+            match client.command(&line).map(|text| Reply::decode(&text)) {
+                Ok(Ok(reply)) => {
+                    replies.push(reply);
+                    peers.insert(peer.clone(), client);
+                }
+                Ok(Err(_)) | Err(ClientError::Server(_)) => {
+                    peers.insert(peer.clone(), client);
+                }
+                Err(_) => {}
+            }
+        }
+        replies
+    }
+
+    /// Starts or stops forwarding writes as leadership changes
+    fn sync_role(&self, db: &ConcurrentDatabase) {
+        let (leading, term) = {
+            let election = self.election();
+            (election.role() == cluster::Role::Leader, election.term())
+        };
+        let mut replicator = self.replicator();
+        if leading && replicator.is_none() {
+            *replicator = Some(db.write().replicate());
+            eprintln!("Leading in term {}", term);
+        } else if !leading && replicator.take().is_some() {
+            eprintln!("Stepped down in term {}", term);
+        }
+    }
+
+    /// Holds elections and sends heartbeats, forever
+    fn run_elections(&self, db: &ConcurrentDatabase) {
+        let mut peers = HashMap::new();
+        let mut last_heartbeat = Instant::now();
+        loop {
+            thread::sleep(TICK);
+            let now = Instant::now();
+            let message = {
+                let mut election = self.election();
+                let request = election.tick(now);
+                let beat_due = now.duration_since(last_heartbeat) >= HEARTBEAT;
+                request.or_else(|| election.heartbeat().filter(|_| beat_due))
+            };
+            if let Some(message) = message {
+                if matches!(message, Message::Heartbeat { .. }) {
+                    last_heartbeat = now;
+                }
+                let replies = self.broadcast(&message, &mut peers);
+                self.election().on_replies(&replies, Instant::now());
+            }
+            self.sync_role(db);
+        }
+    }
+
+    /// Mirrors the current leader into `db`, forever
+    fn follow_leader(&self, db: &ConcurrentDatabase) {
+        let mut failing = None;
+        loop {
+            thread::sleep(TICK);
+            let Some(leader) = self.following() else {
+                continue;
+            };
+            match self.replicate_from(&leader, db) {
+                Ok(()) => failing = None,
+                // retried every tick, so only the first failure is reported
+                Err(e) if failing.as_ref() != Some(&leader) => {
+                    eprintln!("Replication from {} stopped: {}", leader, e);
+                    failing = Some(leader);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Copies `leader`'s data, then applies its writes until it changes
+    fn replicate_from(&self, leader: &str, db: &ConcurrentDatabase) -> Result<(), ClientError> {
+        // a leader sends keepalives, so a silent stream means it is gone
+        let lines = self
+            .connect(leader, ELECTION_TIMEOUT)?
+            .stream("REPLICATE")?;
+        db.write().clear();
+        for line in lines {
+            let line = line?;
+            if !line.is_empty() {
+                let record = LogRecord::decode(&line).map_err(ClientError::InvalidArgument)?;
+                db.write().apply_record(record);
+            }
+            if self.following().as_deref() != Some(leader) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes `records` to a follower as they come, with keepalives in between
+fn stream_records(writer: &mut impl Write, records: Receiver<LogRecord>) -> io::Result<()> {
+    loop {
+        match records.recv_timeout(HEARTBEAT) {
+            Ok(record) => writeln!(writer, "{}", record.encode())?,
+            Err(RecvTimeoutError::Timeout) => writeln!(writer)?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// What one connection has proven about itself
+#[derive(Debug)]
+struct Session {
+    node: Option<Arc<Node>>,
+    #[cfg(feature = "auth")]
+    required: bool,
+    #[cfg(feature = "auth")]
+    user: Option<String>,
+}
+
+impl Session {
+    fn new(node: Option<Arc<Node>>, #[cfg(feature = "auth")] required: bool) -> Self {
+        Self {
+            node,
+            #[cfg(feature = "auth")]
+            required,
+            #[cfg(feature = "auth")]
+            user: None,
+        }
+    }
+
+    fn run(&mut self, line: &str, db: &ConcurrentDatabase) -> Result<String, String> {
+        #[cfg(feature = "auth")]
+        if self.required {
+            return self.run_as_user(line, db);
+        }
+        if let Some(reply) = self.run_cluster(line) {
+            return reply;
+        }
+        patterns::parse_command(line).and_then(|op| self.execute(&op, db))
+    }
+
+    /// Runs `ROLE` and `ELECTION` lines, leaving other lines alone
+    fn run_cluster(&self, line: &str) -> Option<Result<String, String>> {
+        let node = self.node.as_ref()?;
+        if line.trim().eq_ignore_ascii_case("role") {
+            let election = node.election();
+            let role = match (election.role(), election.leader()) {
+                (cluster::Role::Leader, _) => "leader".to_string(),
+                (cluster::Role::Candidate, _) => "candidate".to_string(),
+                (cluster::Role::Follower, Some(leader)) => format!("replica of {}", leader),
+                (cluster::Role::Follower, None) => "replica".to_string(),
+            };
+            return Some(Ok(format!("{} in term {}", role, election.term())));
+        }
+        if !Message::recognizes(line) {
+            return None;
+        }
+        Some(Message::decode(line).map(|message| {
+            let reply = node.election().receive(&message, Instant::now());
+            reply.encode()
+        }))
+    }
+
+    /// Runs `op`, redirecting writes to the leader when this server isn't it
+    fn execute(&self, op: &DbOperation, db: &ConcurrentDatabase) -> Result<String, String> {
+        let Some(node) = &self.node else {
+            return db.execute(op);
+        };
+        if OpCode::from(op).is_write() {
+            node.election().redirect()?;
+        }
+        let output = db.execute(op)?;
+        if let Some(replicator) = node.replicator().as_mut() {
+            replicator.forward(&db.read());
+        }
+        Ok(output)
+    }
+
+    /// Registers this connection as a follower of the leader's writes
+    fn replicate(&self, db: &ConcurrentDatabase) -> Result<Receiver<LogRecord>, String> {
+        let node = self.node.as_ref().ok_or("Not part of a cluster")?;
+        #[cfg(feature = "auth")]
+        if self.required {
+            self.require_admin(db)?;
+        }
+        node.election().redirect()?;
+        let mut replicator = node.replicator();
+        let replicator = replicator.as_mut().ok_or("No leader elected yet")?;
+        let leader = db.read();
+        replicator.forward(&leader);
+        Ok(replicator.add_follower(&leader))
+    }
+
+    #[cfg(feature = "auth")]
+    fn require_admin(&self, db: &ConcurrentDatabase) -> Result<(), String> {
+        let user = self.user.as_deref().ok_or("Authentication required")?;
+        let roles = Users::new(&mut *db.write())
+            .roles(user)
+            .map_err(|e| e.to_string())?;
+        if roles.contains(&Role::Admin) {
+            Ok(())
+        } else {
+            Err(auth::AuthError::Forbidden(user.to_string()).to_string())
+        }
+    }
+
+    /// Runs `line` for the logged-in user, if their roles allow it
+    #[cfg(feature = "auth")]
+    fn run_as_user(&mut self, line: &str, db: &ConcurrentDatabase) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        if words
+            .next()
+            .is_some_and(|verb| verb.eq_ignore_ascii_case("auth"))
+        {
+            let (Some(name), Some(password), None) = (words.next(), words.next(), words.next())
+            else {
+                return Err("Usage: AUTH <user> <password>".to_string());
+            };
+            Users::new(&mut *db.write())
+                .verify(name, password)
+                .map_err(|e| e.to_string())?;
+            self.user = Some(name.to_string());
+            return Ok(format!("Authenticated: {}", name));
+        }
+        let Some(user) = &self.user else {
+            return Err("Authentication required".to_string());
+        };
+        if self.node.is_some()
+            && (Message::recognizes(line) || line.trim().eq_ignore_ascii_case("role"))
+        {
+            self.require_admin(db)?;
+            if let Some(reply) = self.run_cluster(line) {
+                return reply;
+            }
+        }
+        if AdminCommand::recognizes(line) {
+            if let Some(node) = &self.node {
+                node.election().redirect()?;
+            }
+            return Users::new(&mut *db.write())
+                .execute_admin(user, line)
+                .map_err(|e| e.to_string());
+        }
+        let op = patterns::parse_command(line)?;
+        auth::authorize(&*db.read(), user, &op).map_err(|e| e.to_string())?;
+        self.execute(&op, db)
+    }
+}
+
+/// Creates `admin` from `GVS_ADMIN_PASSWORD`, returning whether logins are required
+#[cfg(feature = "auth")]
+fn create_admin(db: &ConcurrentDatabase) -> io::Result<bool> {
+    let Ok(password) = std::env::var("GVS_ADMIN_PASSWORD") else {
+        return Ok(false);
+    };
+    let mut guard = db.write();
+    let mut users = Users::new(&mut *guard);
+    users
+        .create("admin", &password)
+        .and_then(|()| users.grant("admin", Role::Admin))
+        .map_err(io::Error::other)?;
+    Ok(true)
+}
+
+fn handle(stream: TcpStream, db: &ConcurrentDatabase, mut session: Session) -> io::Result<()> {
+    // One write per reply; piecemeal writes stall on Nagle and delayed ACKs
+    let mut writer = LineWriter::new(stream.try_clone()?);
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.trim().eq_ignore_ascii_case("replicate") {
+            match session.replicate(db) {
+                Ok(records) => {
+                    writeln!(writer, "ok Replicating")?;
+                    return stream_records(&mut writer, records);
+                }
+                Err(e) => writeln!(writer, "error {}", e)?,
+            }
+            continue;
+        }
+        match session.run(&line, db) {
+            Ok(output) => writeln!(writer, "ok {}", output)?,
+            Err(e) => writeln!(writer, "error {}", e)?,
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let config = ServerConfig::default();
+    let arg = std::env::args().nth(1);
+    if arg.as_deref() == Some("--version") {
+        println!("{}", literals::BANNER);
+        return Ok(());
+    }
+    let addr = arg.unwrap_or_else(|| format!("{}:{}", config.host, config.port));
+
+    let listener = TcpListener::bind(&addr)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+
+    let db = Arc::new(ConcurrentDatabase::from(MemoryDatabase::with_defaults()));
+    #[cfg(feature = "auth")]
+    let required = create_admin(&db)?;
+    let node = Node::from_env(&addr).map(Arc::new);
+    if let Some(node) = &node {
+        eprintln!("Replicating with {}", node.peers.join(", "));
+        for follow in [false, true] {
+            let (node, db) = (Arc::clone(node), Arc::clone(&db));
+            thread::spawn(move || {
+                if follow {
+                    node.follow_leader(&db)
+                } else {
+                    node.run_elections(&db)
+                }
+            });
+        }
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let db = Arc::clone(&db);
+        let session = Session::new(
+            node.clone(),
+            #[cfg(feature = "auth")]
+            required,
+        );
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &db, session) {
+                eprintln!("Connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
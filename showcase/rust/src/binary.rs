@@ -0,0 +1,114 @@
+//! Compact binary persistence, for stores too large for the text format
+//!
+//! A file is `MAGIC`, one format version byte, then the entries encoded with
+//! bincode in key order. Files from another format version are rejected on
+//! load rather than misread.
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use bincode::config;
+
+use crate::literals::{has_magic, MAGIC};
+use crate::{persist, MemoryDatabase, StrDatabase};
+
+/// Version of the layout after `MAGIC`, bumped on incompatible changes
+pub const FORMAT_VERSION: u8 = 1;
+
+fn invalid(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Writes the binary format to `writer`
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut entries: Vec<(&str, &str)> = self.scan().collect();
+        entries.sort_unstable();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        bincode::encode_into_std_write(&entries, &mut writer, config::standard())
+            .map_err(invalid)?;
+        writer.flush()
+    }
+
+    /// Saves data to `path` in the binary format
+    pub fn save_binary(&self, path: &str) -> io::Result<()> {
+        persist::write_atomically(path, |file| self.write_binary(BufWriter::new(file)))
+    }
+}
+
+impl MemoryDatabase {
+    /// Reads the binary format from `reader`
+    ///
+    /// Fails with `InvalidData` on a missing magic header, an unknown format
+    /// version or a truncated body.
+    pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; MAGIC.len() + 1];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| invalid("Not a binary database"))?;
+        if !has_magic(&header) {
+            return Err(invalid("Not a binary database"));
+        }
+        match header[MAGIC.len()] {
+            FORMAT_VERSION => {}
+            version => return Err(invalid(format!("Unsupported format version: {}", version))),
+        }
+
+        let entries: Vec<(String, String)> =
+            bincode::decode_from_std_read(&mut reader, config::standard()).map_err(invalid)?;
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Loads data saved by `save_binary`
+    pub fn load_binary(path: &str) -> io::Result<Self> {
+        Self::read_binary(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let path = std::env::temp_dir().join("gvs_binary_round_trip.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("key:with:colons", "line one\nline two");
+        db.insert("crab", "🦀");
+
+        db.save_binary(path).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        assert!(has_magic(&bytes));
+        assert_eq!(bytes[MAGIC.len()], FORMAT_VERSION);
+
+        let loaded = MemoryDatabase::load_binary(path).unwrap();
+        assert_eq!(
+            loaded.retrieve("key:with:colons"),
+            Some("line one\nline two")
+        );
+        assert_eq!(loaded.retrieve("crab"), Some("🦀"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_binary_rejects_bad_headers() {
+        let mut db = MemoryDatabase::new();
+        db.insert("a", "1");
+        let mut bytes = Vec::new();
+        db.write_binary(&mut bytes).unwrap();
+
+        let mut future = bytes.clone();
+        future[MAGIC.len()] = FORMAT_VERSION + 1;
+        let err = MemoryDatabase::read_binary(&future[..]).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported format version: 2");
+
+        let err = MemoryDatabase::read_binary(&b"a:1\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(MemoryDatabase::read_binary(truncated).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! Logical tables sharing one database
+//!
+//! A [`Bucket`] is a [`Keyspace`] bound to a database: its methods take
+//! names inside the bucket, and iteration, clearing and saving see only the
+//! bucket's own entries. Saving the whole database still writes every
+//! bucket to one file, since each entry is stored under its prefixed key.
+//!
+//! ```
+//! use gvs_showcase::{Database, MemoryDatabase};
+//!
+//! let mut db = MemoryDatabase::new();
+//! db.bucket("sessions").insert("abc", "alice");
+//! db.bucket("users").insert("alice", "admin");
+//!
+//! assert_eq!(db.retrieve("sessions/abc"), Some("alice"));
+//! assert_eq!(db.bucket("sessions").len(), 1);
+//! ```
+use std::hash::BuildHasher;
+use std::io;
+use std::sync::Arc;
+
+use crate::keyspace::Keyspace;
+use crate::{Database, Error, MemoryDatabase, StrDatabase};
+
+/// One namespace of a database, borrowed for reads and writes
+///
+/// Entries of nested keyspaces, such as `sessions/old/abc` inside
+/// `sessions`, belong to the bucket too, under names like `old/abc`.
+pub struct Bucket<'a, S> {
+    pub(crate) db: &'a mut StrDatabase<S>,
+    pub(crate) space: Keyspace,
+}
+
+/// One namespace of a database, borrowed for reads only
+///
+/// Unlike `Bucket`, any number can be held at once.
+pub struct BucketRef<'a, S> {
+    pub(crate) db: &'a StrDatabase<S>,
+    pub(crate) space: Keyspace,
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Borrows the bucket called `name`; it exists once it holds an entry
+    ///
+    /// A case-insensitive database lowercases `name`, like any key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` would be rejected by [`Keyspace::new`]; bucket names
+    /// are expected to be literals chosen by the program, not user input.
+    pub fn bucket(&mut self, name: &str) -> Bucket<'_, S> {
+        let space = self.bucket_space(name);
+        Bucket { db: self, space }
+    }
+
+    /// Like `bucket`, borrowing the database only for reads
+    pub fn bucket_ref(&self, name: &str) -> BucketRef<'_, S> {
+        BucketRef {
+            space: self.bucket_space(name),
+            db: self,
+        }
+    }
+
+    fn bucket_space(&self, name: &str) -> Keyspace {
+        Keyspace::new(&self.fold_key(name))
+            .unwrap_or_else(|e| panic!("invalid bucket name {:?}: {}", name, e))
+    }
+}
+
+fn retrieve<'a, S: BuildHasher + Clone>(
+    db: &'a StrDatabase<S>,
+    space: &Keyspace,
+    name: &str,
+) -> Option<&'a str> {
+    db.retrieve(&space.key(name))
+}
+
+fn entries<'a, S: BuildHasher + Clone>(
+    db: &'a StrDatabase<S>,
+    space: &'a Keyspace,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    db.iter()
+        .filter_map(|(key, value)| Some((space.strip(key)?, value)))
+}
+
+fn save<S: BuildHasher + Clone>(
+    db: &StrDatabase<S>,
+    space: &Keyspace,
+    path: &str,
+) -> io::Result<()> {
+    let mut saved = MemoryDatabase::new();
+    saved.insert_many(entries(db, space));
+    saved.save_to_file(path)
+}
+
+impl<S: BuildHasher + Clone> BucketRef<'_, S> {
+    /// The keyspace the bucket's entries are stored under
+    pub fn keyspace(&self) -> &Keyspace {
+        &self.space
+    }
+
+    pub fn retrieve(&self, name: &str) -> Option<&str> {
+        retrieve(self.db, &self.space, name)
+    }
+
+    /// Live entries in the bucket, keyed by their names inside it
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        entries(self.db, &self.space)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Saves only this bucket, with names in place of the prefixed keys
+    pub fn save_to_file(&self, path: &str) -> io::Result<()> {
+        save(self.db, &self.space, path)
+    }
+}
+
+impl<S: BuildHasher + Clone> Bucket<'_, S> {
+    /// The keyspace the bucket's entries are stored under
+    pub fn keyspace(&self) -> &Keyspace {
+        &self.space
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl Into<Arc<str>> + AsRef<str>) {
+        self.db.insert(self.space.key(name), value);
+    }
+
+    /// Like `insert`, but fails when the write would break a quota
+    pub fn try_insert(
+        &mut self,
+        name: &str,
+        value: impl Into<Arc<str>> + AsRef<str>,
+    ) -> Result<(), Error> {
+        self.db.try_insert(self.space.key(name), value)
+    }
+
+    pub fn retrieve(&self, name: &str) -> Option<&str> {
+        retrieve(self.db, &self.space, name)
+    }
+
+    /// Removes `name`, returning true if it was present
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.db.remove(self.space.key(name).as_str()).is_some()
+    }
+
+    /// Live entries in the bucket, keyed by their names inside it
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        entries(self.db, &self.space)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Removes every entry in the bucket, returning how many were stored
+    pub fn clear(&mut self) -> usize {
+        let keys: Vec<String> = self.iter().map(|(name, _)| self.space.key(name)).collect();
+        for key in &keys {
+            self.db.remove(key.as_str());
+        }
+        keys.len()
+    }
+
+    /// Saves only this bucket, with names in place of the prefixed keys
+    pub fn save_to_file(&self, path: &str) -> io::Result<()> {
+        save(self.db, &self.space, path)
+    }
+
+    /// Inserts every entry from a file written by `save_to_file` into the bucket
+    ///
+    /// Returns how many entries were read; existing names are overwritten.
+    pub fn load_from_file(&mut self, path: &str) -> io::Result<usize> {
+        let loaded = MemoryDatabase::load_from_file(path)?;
+        for (name, value) in &loaded {
+            self.insert(name, value);
+        }
+        Ok(loaded.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_are_isolated() {
+        let mut db = MemoryDatabase::new();
+        db.insert("sessionsx/stray", "outside");
+        let mut sessions = db.bucket("sessions");
+        sessions.insert("a", "1");
+        sessions.insert("b", "2");
+        assert!(sessions.remove("b"));
+        db.bucket("users").insert("a", "alice");
+
+        assert_eq!(db.bucket("sessions").retrieve("a"), Some("1"));
+        assert_eq!(db.bucket("users").retrieve("a"), Some("alice"));
+        let users: Vec<_> = db
+            .bucket("users")
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(users, [("a".to_string(), "alice".to_string())]);
+
+        let (sessions, users) = (db.bucket_ref("sessions"), db.bucket_ref("users"));
+        assert_eq!(sessions.retrieve("a"), Some("1"));
+        assert_eq!(users.retrieve("a"), Some("alice"));
+        assert_eq!(sessions.len() + users.len(), 2);
+
+        assert_eq!(db.bucket("sessions").clear(), 1);
+        assert!(db.bucket("sessions").is_empty());
+        assert_eq!(db.len(), 2);
+    }
+
+    #[test]
+    fn test_bucket_save_and_load() {
+        let path = std::env::temp_dir().join("gvs_bucket.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.bucket("cache").insert("page:1", "<html>");
+        db.bucket("config").insert("port", "8080");
+        db.bucket("cache").save_to_file(path).unwrap();
+
+        let mut restored = MemoryDatabase::new();
+        assert_eq!(restored.bucket("archive").load_from_file(path).unwrap(), 1);
+        assert_eq!(restored.retrieve("archive/page:1"), Some("<html>"));
+        assert_eq!(restored.len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+//! Entry and byte limits on single buckets
+//!
+//! A bucket quota keeps one tenant from filling a shared database: writes
+//! that would take a bucket past a limit fail with `Error::BucketFull` or
+//! `Error::BucketOverQuota` and change nothing. Limits cover nested
+//! keyspaces too, so `sessions/old/abc` counts towards `sessions`. Bytes
+//! are counted as for `with_max_bytes`, using the full prefixed key.
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::bucket::{Bucket, BucketRef};
+use crate::keyspace::Keyspace;
+use crate::quota::entry_size;
+use crate::{Error, MemoryDatabase, StoredValue, StrDatabase};
+
+/// Limits on one bucket; `None` leaves that measure unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BucketQuota {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+/// A limited bucket and what it currently holds
+#[derive(Debug, Clone)]
+struct Tracked {
+    space: Keyspace,
+    quota: BucketQuota,
+    entries: usize,
+    bytes: usize,
+}
+
+/// Every bucket quota of a database
+#[derive(Debug, Clone)]
+pub(crate) struct BucketQuotas<K, V> {
+    buckets: Vec<Tracked>,
+    name_of: fn(&K) -> &str,
+    size_of: fn(&K, &V) -> usize,
+}
+
+impl<K, V> BucketQuotas<K, V> {
+    fn containing<'a>(&'a self, key: &'a K) -> impl Iterator<Item = &'a Tracked> {
+        let name = (self.name_of)(key);
+        self.buckets
+            .iter()
+            .filter(move |bucket| bucket.space.strip(name).is_some())
+    }
+
+    /// Fails if replacing `old` with `value` under `key` breaks a limit
+    pub(crate) fn check(&self, key: &K, old: Option<&V>, value: &V) -> Result<(), Error> {
+        let freed = old.map_or(0, |old| (self.size_of)(key, old));
+        let size = (self.size_of)(key, value);
+        for bucket in self.containing(key) {
+            let entries = bucket.entries + usize::from(old.is_none());
+            if let Some(limit) = bucket.quota.max_entries.filter(|&limit| entries > limit) {
+                return Err(Error::BucketFull {
+                    bucket: bucket.space.prefix().to_string(),
+                    limit,
+                });
+            }
+            let needed = bucket.bytes - freed + size;
+            if let Some(limit) = bucket.quota.max_bytes.filter(|&limit| needed > limit) {
+                return Err(Error::BucketOverQuota {
+                    bucket: bucket.space.prefix().to_string(),
+                    needed,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that `key` went from holding `old` to holding `new`
+    pub(crate) fn update(&mut self, key: &K, old: Option<&V>, new: Option<&V>) {
+        let name = (self.name_of)(key);
+        let freed = old.map_or(0, |old| (self.size_of)(key, old));
+        let size = new.map_or(0, |new| (self.size_of)(key, new));
+        for bucket in &mut self.buckets {
+            if bucket.space.strip(name).is_some() {
+                bucket.entries =
+                    bucket.entries + usize::from(new.is_some()) - usize::from(old.is_some());
+                bucket.bytes = bucket.bytes - freed + size;
+            }
+        }
+    }
+}
+
+fn key_name(key: &Arc<str>) -> &str {
+    key
+}
+
+impl<S: BuildHasher + Clone> Bucket<'_, S> {
+    /// Limits what the bucket may hold, replacing any earlier quota
+    ///
+    /// Entries already stored are kept even if they exceed the new limits;
+    /// only writes that would grow the bucket further are refused. Expired
+    /// entries are purged first, so they never count towards a new quota.
+    pub fn set_quota(&mut self, quota: BucketQuota) {
+        let db = &mut *self.db;
+        let quotas = db.bucket_quotas.get_or_insert_with(|| BucketQuotas {
+            buckets: Vec::new(),
+            name_of: key_name,
+            size_of: entry_size::<Arc<str>, StoredValue>,
+        });
+        if let Some(bucket) = quotas.buckets.iter_mut().find(|b| b.space == self.space) {
+            bucket.quota = quota;
+            return;
+        }
+        let size_of = quotas.size_of;
+        let mut tracked = Tracked {
+            space: self.space.clone(),
+            quota,
+            entries: 0,
+            bytes: 0,
+        };
+        let now = Instant::now();
+        db.purge_expired_at(now);
+        for (key, value) in db.live_at(now) {
+            if tracked.space.strip(key).is_some() {
+                tracked.entries += 1;
+                tracked.bytes += size_of(key, value);
+            }
+        }
+        if let Some(quotas) = &mut db.bucket_quotas {
+            quotas.buckets.push(tracked);
+        }
+    }
+
+    /// The bucket's own quota, if one is set
+    pub fn quota(&self) -> Option<BucketQuota> {
+        tracked(self.db, &self.space).map(|bucket| bucket.quota)
+    }
+
+    /// Approximate bytes the bucket holds, or `None` without a quota
+    pub fn used_bytes(&self) -> Option<usize> {
+        tracked(self.db, &self.space).map(|bucket| bucket.bytes)
+    }
+
+    /// Lifts the bucket's quota
+    pub fn clear_quota(&mut self) {
+        if let Some(quotas) = &mut self.db.bucket_quotas {
+            quotas.buckets.retain(|bucket| bucket.space != self.space);
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> BucketRef<'_, S> {
+    /// The bucket's own quota, if one is set
+    pub fn quota(&self) -> Option<BucketQuota> {
+        tracked(self.db, &self.space).map(|bucket| bucket.quota)
+    }
+
+    /// Approximate bytes the bucket holds, or `None` without a quota
+    pub fn used_bytes(&self) -> Option<usize> {
+        tracked(self.db, &self.space).map(|bucket| bucket.bytes)
+    }
+}
+
+fn tracked<'a, S>(db: &'a StrDatabase<S>, space: &Keyspace) -> Option<&'a Tracked> {
+    let quotas = db.bucket_quotas.as_ref()?;
+    quotas.buckets.iter().find(|b| b.space == *space)
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    pub(crate) fn check_bucket_quotas(&self, key: &K, value: &V) -> Result<(), Error> {
+        match &self.bucket_quotas {
+            Some(quotas) => quotas.check(key, self.store.get(key), value),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, DbOperation};
+
+    #[test]
+    fn test_entry_quota_isolates_tenants() {
+        let mut db = MemoryDatabase::new();
+        db.insert("noisy/old/1", "x");
+        let mut noisy = db.bucket("noisy");
+        noisy.set_quota(BucketQuota {
+            max_entries: Some(2),
+            max_bytes: None,
+        });
+        noisy.try_insert("2", "x").unwrap();
+        let err = noisy.try_insert("3", "x").unwrap_err();
+        assert!(matches!(err, Error::BucketFull { limit: 2, .. }));
+        noisy.try_insert("2", "replaced").unwrap();
+        noisy.insert("dropped", "x");
+        assert_eq!(noisy.len(), 2);
+
+        let op = DbOperation::Insert {
+            key: "noisy/4".into(),
+            value: "x".into(),
+        };
+        assert_eq!(
+            op.execute(&mut db).unwrap_err(),
+            "Bucket \"noisy\" is full: limit is 2 entries"
+        );
+        db.bucket("quiet").insert("1", "x");
+        db.remove("noisy/old/1");
+        db.bucket("noisy").try_insert("3", "x").unwrap();
+    }
+
+    #[test]
+    fn test_byte_quota() {
+        let mut db = MemoryDatabase::new();
+        let mut logs = db.bucket("logs");
+        logs.set_quota(BucketQuota {
+            max_entries: None,
+            max_bytes: Some(20),
+        });
+        logs.try_insert("a", "0123456789").unwrap();
+        assert_eq!(logs.used_bytes(), Some(16));
+        let err = logs.try_insert("b", "0123").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::BucketOverQuota {
+                needed: 26,
+                limit: 20,
+                ..
+            }
+        ));
+        assert_eq!(db.bucket_ref("logs").used_bytes(), Some(16));
+        let mut logs = db.bucket("logs");
+        logs.clear_quota();
+        assert_eq!(logs.quota(), None);
+        logs.try_insert("b", "0123").unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+//! Case-insensitive keys
+//!
+//! A database made by `new_case_insensitive` lowercases every key it is
+//! given for a lookup or write, so `Host` and `host` name one entry and keys
+//! read back in lowercase. Only `put`, which takes an owned key of any type,
+//! stores keys exactly as given.
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use crate::{MemoryDatabase, StrDatabase};
+
+/// A lookup key that a case-insensitive database can lowercase
+///
+/// String keys fold; every other key type looks up as given.
+pub trait FoldCase: ToOwned {
+    fn fold_case(&self) -> Cow<'_, Self> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl FoldCase for str {
+    fn fold_case(&self) -> Cow<'_, str> {
+        if self.chars().any(char::is_uppercase) {
+            Cow::Owned(self.to_lowercase())
+        } else {
+            Cow::Borrowed(self)
+        }
+    }
+}
+
+impl FoldCase for String {
+    fn fold_case(&self) -> Cow<'_, String> {
+        match self.as_str().fold_case() {
+            Cow::Owned(folded) => Cow::Owned(folded),
+            Cow::Borrowed(_) => Cow::Borrowed(self),
+        }
+    }
+}
+
+impl FoldCase for Arc<str> {
+    fn fold_case(&self) -> Cow<'_, Arc<str>> {
+        match (**self).fold_case() {
+            Cow::Owned(folded) => Cow::Owned(folded.into()),
+            Cow::Borrowed(_) => Cow::Borrowed(self),
+        }
+    }
+}
+
+macro_rules! as_given {
+    ($($ty:ty),*) => {
+        $(impl FoldCase for $ty {})*
+    };
+}
+
+as_given!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, bool, char);
+as_given!([u8], Vec<u8>, &str);
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// `key` as it is stored, borrowed unless it had to be lowercased
+    pub(crate) fn fold_query<'a, Q: FoldCase + ?Sized>(&self, key: &'a Q) -> Cow<'a, Q> {
+        if self.fold_case {
+            key.fold_case()
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+impl MemoryDatabase {
+    /// Creates an empty database that ignores the case of keys
+    pub fn new_case_insensitive() -> Self {
+        Self::with_hasher_case_insensitive(RandomState::new())
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Like `new_case_insensitive`, hashing keys with `hasher`
+    pub fn with_hasher_case_insensitive(hasher: S) -> Self {
+        Self {
+            fold_case: true,
+            ..Self::with_hasher(hasher)
+        }
+    }
+
+    /// Returns true when keys are folded to lowercase
+    pub fn is_case_insensitive(&self) -> bool {
+        self.fold_case
+    }
+
+    pub(crate) fn fold_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        self.fold_query(key)
+    }
+
+    pub(crate) fn fold_owned_key(&self, key: Arc<str>) -> Arc<str> {
+        match self.fold_key(&key) {
+            Cow::Owned(folded) => folded.into(),
+            Cow::Borrowed(_) => key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_keys_differing_in_case_are_one_entry() {
+        let mut db = MemoryDatabase::new_case_insensitive();
+        db.insert("Server.Host", "localhost");
+        db.insert("server.HOST", "example.com");
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.retrieve("SERVER.host"), Some("example.com"));
+        assert!(Database::contains_key(&db, "Server.Host"));
+        assert!(db.contains_key("Server.Host"));
+        assert_eq!(db.scan_prefix("Server.").count(), 1);
+        assert_eq!(db.keys().collect::<Vec<_>>(), ["server.host"]);
+        *db.get_mut("SERVER.HOST").unwrap() = crate::stored("edited");
+        assert_eq!(db.retrieve("server.host"), Some("edited"));
+        assert!(db.remove("Server.Host").is_some());
+
+        db.insert_with_ttl("Session", "abc", Duration::from_secs(60));
+        assert!(db.ttl("SESSION").is_some());
+        assert!(db.delete("SESSION"));
+        assert!(!db.delete("session"));
+    }
+
+    #[test]
+    fn test_default_database_is_case_sensitive() {
+        let mut db = MemoryDatabase::new();
+        assert!(!db.is_case_insensitive());
+        db.insert("Key", "1");
+        db.insert("key", "2");
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.retrieve("KEY"), None);
+        assert!(MemoryDatabase::new_case_insensitive().is_case_insensitive());
+    }
+
+    #[test]
+    fn test_buckets_fold_their_names() {
+        use crate::bucket_quota::BucketQuota;
+
+        let mut db = MemoryDatabase::new_case_insensitive();
+        db.bucket("Sessions").insert("ABC", "alice");
+        assert_eq!(db.retrieve("sessions/abc"), Some("alice"));
+        assert_eq!(db.bucket("SESSIONS").len(), 1);
+        assert_eq!(db.bucket("sessions").retrieve("Abc"), Some("alice"));
+        assert!(db.bucket("Sessions").remove("abc"));
+
+        let mut sessions = db.bucket("Sessions");
+        sessions.set_quota(BucketQuota {
+            max_entries: Some(1),
+            max_bytes: None,
+        });
+        sessions.try_insert("a", "1").unwrap();
+        assert!(db.bucket("SESSIONS").try_insert("b", "2").is_err());
+        assert_eq!(db.bucket("sessions").clear(), 1);
+    }
+}
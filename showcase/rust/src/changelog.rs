@@ -0,0 +1,151 @@
+//! Sequence-numbered log of recent mutations
+//!
+//! Once `enable_changelog` is called, every write and removal is numbered
+//! from 1 and the newest `max_entries` are kept with the value written, so
+//! external systems can poll `changelog_since` with the last number they
+//! saw instead of re-reading the whole store. Writes inside a transaction
+//! are numbered when made and kept only if it commits.
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hash};
+
+use crate::MemoryDatabase;
+
+/// One logged mutation; `value` is `None` for a removal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<'a, K, V> {
+    pub sequence: u64,
+    pub key: &'a K,
+    pub value: Option<&'a V>,
+}
+
+/// The newest mutations, oldest first
+#[derive(Debug, Clone)]
+pub(crate) struct Changelog<K, V> {
+    max_entries: usize,
+    last: u64,
+    kept: VecDeque<(u64, K, Option<V>)>,
+}
+
+impl<K, V> Changelog<K, V> {
+    pub(crate) fn record(&mut self, key: K, value: Option<V>) {
+        if self.kept.len() == self.max_entries {
+            self.kept.pop_front();
+        }
+        self.last += 1;
+        self.kept.push_back((self.last, key, value));
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Logs every later mutation, keeping the newest `max_entries`
+    ///
+    /// Calling this again changes the bound and keeps the numbering.
+    pub fn enable_changelog(&mut self, max_entries: usize) {
+        let max_entries = max_entries.max(1);
+        let changelog = self.changelog.get_or_insert_with(|| Changelog {
+            max_entries,
+            last: 0,
+            kept: VecDeque::new(),
+        });
+        changelog.max_entries = max_entries;
+        let excess = changelog.kept.len().saturating_sub(max_entries);
+        changelog.kept.drain(..excess);
+    }
+
+    /// Number of the latest logged mutation, or `None` without a changelog
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.changelog.as_ref().map(|changelog| changelog.last)
+    }
+
+    /// Mutations numbered after `sequence`, oldest first
+    ///
+    /// Returns `None` without a changelog, or when some of those mutations
+    /// were already dropped; the caller has to re-read the store then.
+    pub fn changelog_since(&self, sequence: u64) -> Option<Vec<Change<'_, K, V>>> {
+        let changelog = self.changelog.as_ref()?;
+        let oldest = changelog.kept.front().map_or(changelog.last + 1, |e| e.0);
+        if sequence.saturating_add(1) < oldest {
+            return None;
+        }
+        let changes = changelog
+            .kept
+            .iter()
+            .filter(|(number, _, _)| *number > sequence)
+            .map(|(number, key, value)| Change {
+                sequence: *number,
+                key,
+                value: value.as_ref(),
+            })
+            .collect();
+        Some(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eviction::EvictionPolicy;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_changes_since_a_sequence() {
+        let mut db: MemoryDatabase<&str, u32> =
+            MemoryDatabase::with_capacity_policy(2, EvictionPolicy::Lru);
+        assert_eq!(db.changelog_since(0), None);
+        db.put("before", 0);
+        db.enable_changelog(10);
+        assert_eq!(db.last_sequence(), Some(0));
+        assert_eq!(db.changelog_since(0), Some(vec![]));
+
+        db.put("a", 1);
+        db.put("b", 2);
+        db.remove("a");
+        let seen = db.last_sequence().unwrap();
+        db.put("c", 3);
+
+        let log: Vec<_> = db
+            .changelog_since(0)
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.sequence, *change.key, change.value.copied()))
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (1, "a", Some(1)),
+                (2, "b", Some(2)),
+                (3, "before", None),
+                (4, "a", None),
+                (5, "c", Some(3)),
+            ]
+        );
+        assert_eq!(db.changelog_since(seen).unwrap().len(), 1);
+        assert!(db.changelog_since(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trimmed_or_rolled_back_changes() {
+        let mut db = MemoryDatabase::new();
+        db.enable_changelog(2);
+        for i in 0..3 {
+            db.insert("k", i.to_string());
+        }
+        assert_eq!(db.changelog_since(0), None);
+        assert_eq!(db.changelog_since(1).unwrap().len(), 2);
+
+        let mut tx = db.begin();
+        tx.insert("draft", "x");
+        tx.rollback();
+        assert_eq!(db.last_sequence(), Some(3));
+        let mut tx = db.begin();
+        tx.insert("kept", "y");
+        tx.commit();
+        let changes = db.changelog_since(3).unwrap();
+        assert_eq!(&**changes[0].key, "kept");
+        assert_eq!(db.last_sequence(), Some(4));
+    }
+}
@@ -0,0 +1,149 @@
+//! Change notifications for subscribers
+//!
+//! Subscribers are tracked only once `subscribe` is first called, so
+//! databases nobody watches pay one `Option` check per write. Clones,
+//! snapshots included, start without subscribers, and a transaction holds
+//! its events back until `commit`.
+use std::hash::{BuildHasher, Hash};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::MemoryDatabase;
+
+/// What a mutation did to its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Inserted,
+    Updated,
+    /// Removed, evicted or purged after expiring
+    Deleted,
+}
+
+/// One mutation, as seen by a subscriber
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent<K = Arc<str>> {
+    pub key: K,
+    pub kind: ChangeKind,
+}
+
+/// Senders for every live subscriber, or events held for a transaction
+#[derive(Debug)]
+pub(crate) struct Changes<K> {
+    senders: Vec<Sender<ChangeEvent<K>>>,
+    deferred: Option<Vec<ChangeEvent<K>>>,
+}
+
+impl<K> Default for Changes<K> {
+    fn default() -> Self {
+        Self {
+            senders: Vec::new(),
+            deferred: None,
+        }
+    }
+}
+
+impl<K> Clone for Changes<K> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Clone> Changes<K> {
+    /// Collects events instead of sending them, for `take_deferred`
+    pub(crate) fn deferred() -> Self {
+        Self {
+            senders: Vec::new(),
+            deferred: Some(Vec::new()),
+        }
+    }
+
+    pub(crate) fn take_deferred(&mut self) -> Vec<ChangeEvent<K>> {
+        self.deferred.take().unwrap_or_default()
+    }
+
+    pub(crate) fn notify(&mut self, key: K, kind: ChangeKind) {
+        let event = ChangeEvent { key, kind };
+        match &mut self.deferred {
+            Some(events) => events.push(event),
+            // a dropped receiver unsubscribes
+            None => self
+                .senders
+                .retain(|sender| sender.send(event.clone()).is_ok()),
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Returns a channel receiving an event for every later mutation
+    ///
+    /// Events are sent as each write happens, in order. Dropping the
+    /// receiver unsubscribes it on the next write.
+    pub fn subscribe(&mut self) -> Receiver<ChangeEvent<K>> {
+        let (sender, receiver) = mpsc::channel();
+        self.changes
+            .get_or_insert_with(Changes::default)
+            .senders
+            .push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eviction::EvictionPolicy;
+    use crate::Database;
+
+    #[test]
+    fn test_subscriber_sees_each_mutation() {
+        let mut db = MemoryDatabase::with_capacity_policy(2, EvictionPolicy::Lru);
+        db.insert("ignored", "before subscribing");
+        let events = db.subscribe();
+        db.insert("a", "1");
+        db.insert("a", "2");
+        db.insert("b", "3");
+        db.remove("b");
+        db.remove("missing");
+
+        let kinds: Vec<_> = events.try_iter().map(|e| (e.key, e.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a".into(), ChangeKind::Inserted),
+                ("a".into(), ChangeKind::Updated),
+                ("b".into(), ChangeKind::Inserted),
+                ("ignored".into(), ChangeKind::Deleted),
+                ("b".into(), ChangeKind::Deleted),
+            ]
+        );
+
+        drop(events);
+        db.insert("c", "4");
+        assert!(db.changes.as_ref().unwrap().senders.is_empty());
+    }
+
+    #[test]
+    fn test_transaction_events_wait_for_commit() {
+        let mut db = MemoryDatabase::new();
+        let events = db.subscribe();
+
+        let mut tx = db.begin();
+        tx.insert("draft", "x");
+        tx.rollback();
+        assert_eq!(events.try_iter().count(), 0);
+
+        let mut tx = db.begin();
+        tx.insert("kept", "y");
+        assert_eq!(events.try_iter().count(), 0);
+        tx.commit();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.key.as_ref(), "kept");
+        assert_eq!(event.kind, ChangeKind::Inserted);
+        assert_eq!(db.snapshot().len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+//! Blocking client for `gvs-server`
+//!
+//! Sends one command per line and reads its one-line reply: `ok` replies
+//! return their text and `error` replies come back as `ClientError::Server`.
+//! Keys and values travel as REPL words, so keys can't hold whitespace,
+//! values can't hold line breaks, and runs of spaces in a value collapse.
+//!
+//! A replica answers writes with `MOVED <leader>`; the client then connects
+//! to the leader, logs in again if it had, and retries the command there.
+use std::fmt;
+use std::io::{self, BufRead, BufReader, LineWriter, Lines, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Redirects followed for one command before giving up
+const MAX_REDIRECTS: usize = 3;
+
+/// Why a request failed
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection failed or the server hung up
+    Io(io::Error),
+    /// The server answered `error` with this message
+    Server(String),
+    /// The key or value can't be written as a command
+    InvalidArgument(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "I/O error: {}", e),
+            ClientError::Server(message) => f.write_str(message),
+            ClientError::InvalidArgument(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+/// One connection to a `gvs-server`
+#[derive(Debug)]
+pub struct Client {
+    reader: BufReader<TcpStream>,
+    writer: LineWriter<TcpStream>,
+    timeout: Option<Duration>,
+    credentials: Option<(String, String)>,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(addr)?, None)
+    }
+
+    /// Connects within `timeout` and fails any read or write that takes longer
+    pub fn connect_timeout(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<Self> {
+        let mut last = io::Error::new(io::ErrorKind::InvalidInput, "No address to connect to");
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Self::from_stream(stream, Some(timeout)),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// Changes how long reads and writes may take, `None` for no limit
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        let stream = self.reader.get_ref();
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn from_stream(stream: TcpStream, timeout: Option<Duration>) -> io::Result<Self> {
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        // Whole lines in one write, or Nagle holds each command back
+        let writer = LineWriter::new(stream);
+        Ok(Self {
+            reader,
+            writer,
+            timeout,
+            credentials: None,
+        })
+    }
+
+    /// Sends one command line and returns the text of an `ok` reply
+    ///
+    /// Follows up to three `MOVED` redirects.
+    pub fn command(&mut self, line: &str) -> Result<String, ClientError> {
+        if line.contains(['\n', '\r']) {
+            return Err(ClientError::InvalidArgument(
+                "Commands can't span lines".to_string(),
+            ));
+        }
+        let mut redirects = 0;
+        loop {
+            match self.send(line) {
+                Err(ClientError::Server(message))
+                    if redirects < MAX_REDIRECTS && message.starts_with("MOVED ") =>
+                {
+                    self.reconnect(&message["MOVED ".len()..])?;
+                    redirects += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Moves the connection to `addr`, logging in again if needed
+    fn reconnect(&mut self, addr: &str) -> Result<(), ClientError> {
+        let stream = match self.timeout {
+            Some(timeout) => Self::connect_timeout(addr, timeout)?,
+            None => Self::connect(addr)?,
+        };
+        let credentials = self.credentials.take();
+        *self = stream;
+        if let Some((user, password)) = credentials {
+            self.send(&format!("AUTH {} {}", user, password))?;
+            self.credentials = Some((user, password));
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, line: &str) -> Result<String, ClientError> {
+        writeln!(self.writer, "{}", line)?;
+        let mut reply = String::new();
+        if self.reader.read_line(&mut reply)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let reply = reply.trim_end_matches(['\n', '\r']);
+        if let Some(text) = reply.strip_prefix("ok ") {
+            Ok(text.to_string())
+        } else if let Some(message) = reply.strip_prefix("error ") {
+            Err(ClientError::Server(message.to_string()))
+        } else {
+            let reason = format!("Unexpected reply: {}", reply);
+            Err(io::Error::new(io::ErrorKind::InvalidData, reason).into())
+        }
+    }
+
+    /// Logs in on a server started with accounts
+    pub fn auth(&mut self, user: &str, password: &str) -> Result<(), ClientError> {
+        self.command(&format!("AUTH {} {}", word(user)?, word(password)?))?;
+        self.credentials = Some((user.to_string(), password.to_string()));
+        Ok(())
+    }
+
+    /// Sends a command that streams, returning the lines after its `ok` reply
+    pub fn stream(mut self, line: &str) -> Result<Lines<BufReader<TcpStream>>, ClientError> {
+        self.command(line)?;
+        Ok(self.reader.lines())
+    }
+
+    /// Reads `key`, or `None` when the server doesn't hold it
+    pub fn get(&mut self, key: &str) -> Result<Option<String>, ClientError> {
+        let prefix = format!("Retrieved: {} = ", word(key)?);
+        match self.command(&format!("get {}", key)) {
+            Ok(reply) => Ok(Some(
+                reply.strip_prefix(&prefix).unwrap_or(&reply).to_string(),
+            )),
+            Err(ClientError::Server(message)) if is_not_found(&message) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ClientError> {
+        if value.trim().is_empty() {
+            return Err(ClientError::InvalidArgument(
+                "Values can't be blank".to_string(),
+            ));
+        }
+        self.command(&format!("set {} {}", word(key)?, value))
+            .map(|_| ())
+    }
+
+    /// Removes `key`, returning true if the server held it
+    pub fn delete(&mut self, key: &str) -> Result<bool, ClientError> {
+        match self.command(&format!("del {}", word(key)?)) {
+            Ok(_) => Ok(true),
+            Err(ClientError::Server(message)) if is_not_found(&message) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Checks that `text` travels as a single command word
+fn word(text: &str) -> Result<&str, ClientError> {
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return Err(ClientError::InvalidArgument(format!(
+            "Not a single word: {:?}",
+            text
+        )));
+    }
+    Ok(text)
+}
+
+fn is_not_found(message: &str) -> bool {
+    message.starts_with("Key not found: ")
+}
+
+/// Answers connections on a local port the way `gvs-server` does
+#[cfg(test)]
+pub(crate) fn serve(db: std::sync::Arc<crate::concurrent::ConcurrentDatabase>) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let db = std::sync::Arc::clone(&db);
+            std::thread::spawn(move || {
+                let mut writer = LineWriter::new(stream.try_clone().unwrap());
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else { return };
+                    let reply =
+                        crate::patterns::parse_command(&line).and_then(|op| db.execute(&op));
+                    let sent = match reply {
+                        Ok(output) => writeln!(writer, "ok {}", output),
+                        Err(e) => writeln!(writer, "error {}", e),
+                    };
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serve_one() -> String {
+        serve(std::sync::Arc::new(
+            crate::concurrent::ConcurrentDatabase::new(),
+        ))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut client = Client::connect(serve_one()).unwrap();
+        client.set("motto", "#1 crab = best").unwrap();
+        assert_eq!(
+            client.get("motto").unwrap().as_deref(),
+            Some("#1 crab = best")
+        );
+        assert_eq!(client.get("missing").unwrap(), None);
+        assert!(client.delete("motto").unwrap());
+        assert!(!client.delete("motto").unwrap());
+        assert!(matches!(
+            client.command("incr"),
+            Err(ClientError::Server(message)) if message.starts_with("usage")
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unsendable_arguments() {
+        let mut client = Client::connect(serve_one()).unwrap();
+        for result in [
+            client.set("two words", "x").map(|_| ()),
+            client.set("k", "line\nbreak").map(|_| ()),
+            client.get("").map(|_| ()),
+        ] {
+            assert!(matches!(result, Err(ClientError::InvalidArgument(_))));
+        }
+        client.set("k", "still connected").unwrap();
+    }
+
+    /// Answers every command with `MOVED <target>`, itself by default
+    fn redirect(target: Option<String>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let target = target.unwrap_or_else(|| addr.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let mut writer = LineWriter::new(stream.try_clone().unwrap());
+                for _ in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if writeln!(writer, "error MOVED {}", target).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_follows_moved_to_the_leader() {
+        let replica = redirect(Some(serve_one()));
+        let mut client = Client::connect_timeout(&*replica, Duration::from_secs(5)).unwrap();
+        client.set("k", "v").unwrap();
+        assert_eq!(client.get("k").unwrap().as_deref(), Some("v"));
+
+        let mut lost = Client::connect(redirect(None)).unwrap();
+        assert!(matches!(
+            lost.command("get k"),
+            Err(ClientError::Server(message)) if message.starts_with("MOVED ")
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+//! Request handlers built from closures, and combinators that wrap them
+use std::sync::{Arc, RwLock};
+
+use crate::{Database, MemoryDatabase, ServerConfig};
+
+/// Result every handler produces
+pub type Response = Result<String, String>;
+
+/// A handler stored behind a pointer, for tables of routes
+pub type BoxedHandler = Box<dyn Fn(&ServerConfig, &str) -> Response + Send + Sync>;
+
+/// Shared database handle captured by handlers
+pub type SharedDb = Arc<RwLock<MemoryDatabase>>;
+
+/// Handler that looks the request up as a key in `db`
+pub fn getter(db: SharedDb) -> impl Fn(&ServerConfig, &str) -> Response + Clone {
+    move |_, key| {
+        let db = db
+            .read()
+            .map_err(|_| "Database lock poisoned".to_string())?;
+        db.retrieve(key)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Key not found: {}", key))
+    }
+}
+
+/// Handler that stores `key=value` requests in `db`
+pub fn setter(db: SharedDb) -> impl Fn(&ServerConfig, &str) -> Response + Clone {
+    move |_, request| {
+        let (key, value) = request
+            .split_once('=')
+            .ok_or_else(|| format!("Expected key=value: {}", request))?;
+        let mut db = db
+            .write()
+            .map_err(|_| "Database lock poisoned".to_string())?;
+        db.insert(key, value);
+        Ok(format!("Stored: {}", key))
+    }
+}
+
+/// Rewrites successful responses of `handler` with `map`
+pub fn map_handler<H, F>(handler: H, map: F) -> impl Fn(&ServerConfig, &str) -> Response
+where
+    H: Fn(&ServerConfig, &str) -> Response,
+    F: Fn(&ServerConfig, String) -> String,
+{
+    move |cfg, request| handler(cfg, request).map(|response| map(cfg, response))
+}
+
+/// Rejects requests longer than the configured limit before `handler` sees them
+pub fn limit_request<H>(max_len: usize, handler: H) -> impl Fn(&ServerConfig, &str) -> Response
+where
+    H: Fn(&ServerConfig, &str) -> Response,
+{
+    move |cfg, request| {
+        if request.len() > max_len {
+            return Err(format!("Request exceeds {} bytes", max_len));
+        }
+        handler(cfg, request)
+    }
+}
+
+/// Tries `first`, falling back to `second` when it fails
+pub fn or_else<A, B>(first: A, second: B) -> impl Fn(&ServerConfig, &str) -> Response
+where
+    A: Fn(&ServerConfig, &str) -> Response,
+    B: Fn(&ServerConfig, &str) -> Response,
+{
+    move |cfg, request| first(cfg, request).or_else(|_| second(cfg, request))
+}
+
+/// Boxes a handler so differently typed handlers fit in one table
+pub fn boxed<H>(handler: H) -> BoxedHandler
+where
+    H: Fn(&ServerConfig, &str) -> Response + Send + Sync + 'static,
+{
+    Box::new(handler)
+}
+
+/// Counter that hands out 1, 2, 3, ... each time it is called
+pub fn counter() -> impl FnMut() -> u64 {
+    let mut count = 0;
+    move || {
+        count += 1;
+        count
+    }
+}
+
+/// Calls `attempt` up to `times` times, returning the first success or the last error
+pub fn retry<T, E>(times: usize, mut attempt: impl FnMut(usize) -> Result<T, E>) -> Result<T, E> {
+    let mut tried = 0;
+    loop {
+        match attempt(tried) {
+            Err(_) if tried + 1 < times => tried += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Runs `change` against `db`, restoring the previous contents if it fails
+pub fn transaction<R>(
+    db: &mut MemoryDatabase,
+    change: impl FnOnce(&mut MemoryDatabase) -> Result<R, String>,
+) -> Result<R, String> {
+    let before = db.clone();
+    change(db).inspect_err(|_| *db = before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handler_combinators() {
+        let db: SharedDb = Arc::new(RwLock::new(MemoryDatabase::new()));
+        let cfg = ServerConfig::default();
+        let set = setter(Arc::clone(&db));
+        let get = map_handler(getter(Arc::clone(&db)), |cfg, value| {
+            format!("{}:{} -> {}", cfg.host, cfg.port, value)
+        });
+
+        assert_eq!(set(&cfg, "lang=Rust"), Ok("Stored: lang".to_string()));
+        assert_eq!(get(&cfg, "lang"), Ok("127.0.0.1:8080 -> Rust".to_string()));
+        assert!(set(&cfg, "no separator").is_err());
+
+        let routes: Vec<BoxedHandler> = vec![
+            boxed(limit_request(4, getter(Arc::clone(&db)))),
+            boxed(or_else(
+                getter(Arc::clone(&db)),
+                |_: &ServerConfig, _: &str| Ok("default".to_string()),
+            )),
+        ];
+        assert_eq!(
+            routes[0](&cfg, "language"),
+            Err("Request exceeds 4 bytes".to_string())
+        );
+        assert_eq!(routes[1](&cfg, "missing"), Ok("default".to_string()));
+    }
+
+    #[test]
+    fn test_stateful_and_one_shot_closures() {
+        let mut next = counter();
+        assert_eq!((next(), next(), next()), (1, 2, 3));
+
+        let mut calls = 0;
+        let result: Result<usize, String> = retry(3, |attempt| {
+            calls += 1;
+            if attempt < 2 {
+                Err(format!("attempt {} failed", attempt))
+            } else {
+                Ok(attempt)
+            }
+        });
+        assert_eq!((result, calls), (Ok(2), 3));
+        assert_eq!(retry(2, Err::<(), _>), Err(1));
+
+        let mut db = MemoryDatabase::new();
+        db.insert("balance", "10");
+        let owned = String::from("20");
+        let failed = transaction(&mut db, move |db| {
+            db.insert("balance", owned);
+            Err::<(), _>("insufficient funds".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(db.retrieve("balance"), Some("10"));
+    }
+}
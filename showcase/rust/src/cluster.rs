@@ -0,0 +1,232 @@
+//! Key routing across multiple server endpoints
+//!
+//! `ClusterClient` fans batches out over the `Router`'s nodes and moves keys
+//! when membership changes; `Dashboard` summarises the ring for operators.
+//! `Election` picks which of a primary's replicas takes over when it fails.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub mod client;
+pub mod dashboard;
+pub mod election;
+pub mod membership;
+
+pub use client::ClusterClient;
+pub use dashboard::Dashboard;
+pub use election::{Election, Role};
+pub use membership::{Membership, NodeState};
+
+const DEFAULT_VIRTUAL_NODES: usize = 64;
+
+/// FNV-1a, used instead of `DefaultHasher` so every client agrees on the ring
+///
+/// FNV barely changes its high bits when only the last bytes differ, which
+/// would bunch up `node#0`, `node#1`, ... on the ring, so the result goes
+/// through MurmurHash3's finalizer.
+fn ring_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+/// Consistent hashing router mapping keys to endpoints through virtual nodes
+///
+/// The ring is rebuilt from the sorted endpoint list on every membership
+/// change, so routers that hold the same endpoints agree on every key
+/// whatever order the endpoints were added in. A virtual node whose point
+/// is already taken is rehashed until it lands on a free one.
+#[derive(Debug, Clone)]
+pub struct Router {
+    ring: BTreeMap<u64, String>,
+    endpoints: BTreeSet<String>,
+    virtual_nodes: usize,
+    hash: fn(&[u8]) -> u64,
+}
+
+impl Router {
+    /// Creates an empty router with the default number of virtual nodes
+    pub fn new() -> Self {
+        Self::with_virtual_nodes(DEFAULT_VIRTUAL_NODES)
+    }
+
+    /// Creates an empty router placing each endpoint `virtual_nodes` times on the ring
+    pub fn with_virtual_nodes(virtual_nodes: usize) -> Self {
+        Self {
+            ring: BTreeMap::new(),
+            endpoints: BTreeSet::new(),
+            virtual_nodes: virtual_nodes.max(1),
+            hash: ring_hash,
+        }
+    }
+
+    /// Number of ring points each endpoint gets
+    pub fn virtual_nodes(&self) -> usize {
+        self.virtual_nodes
+    }
+
+    /// Adds an endpoint to the ring
+    pub fn add_node(&mut self, endpoint: &str) {
+        if self.endpoints.insert(endpoint.to_string()) {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.ring.clear();
+        for endpoint in &self.endpoints {
+            for replica in 0..self.virtual_nodes {
+                let mut point = (self.hash)(format!("{}#{}", endpoint, replica).as_bytes());
+                let mut attempt = 0;
+                while self.ring.contains_key(&point) {
+                    attempt += 1;
+                    let label = format!("{}#{}#{}", endpoint, replica, attempt);
+                    point = (self.hash)(label.as_bytes());
+                }
+                self.ring.insert(point, endpoint.clone());
+            }
+        }
+    }
+
+    /// Removes an endpoint, handing its keys to the next nodes on the ring
+    pub fn remove_node(&mut self, endpoint: &str) {
+        if self.endpoints.remove(endpoint) {
+            self.rebuild();
+        }
+    }
+
+    /// Returns the distinct endpoints currently on the ring
+    pub fn nodes(&self) -> Vec<&str> {
+        self.endpoints.iter().map(String::as_str).collect()
+    }
+
+    /// Fraction of the hash space each endpoint owns, sorted by endpoint
+    pub fn shares(&self) -> Vec<(&str, f64)> {
+        let mut owned: BTreeMap<&str, u64> = self.nodes().into_iter().map(|n| (n, 0)).collect();
+        let mut previous = self.ring.keys().next_back().copied();
+        for (&point, node) in &self.ring {
+            let start = previous.unwrap_or(point);
+            let span = owned.entry(node).or_default();
+            *span = span.wrapping_add(point.wrapping_sub(start));
+            previous = Some(point);
+        }
+        if self.endpoints.len() == 1 {
+            return owned.into_keys().map(|node| (node, 1.0)).collect();
+        }
+        owned
+            .into_iter()
+            .map(|(node, span)| (node, span as f64 / u64::MAX as f64))
+            .collect()
+    }
+
+    /// Returns the endpoint owning `key`
+    pub fn route(&self, key: &str) -> Option<&str> {
+        let point = ring_hash(key.as_bytes());
+        self.ring
+            .range(point..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, node)| node.as_str())
+    }
+
+    /// Groups keys by owning endpoint so a batch can be fanned out per node
+    pub fn partition<'a, I>(&self, keys: I) -> HashMap<&str, Vec<&'a str>>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut batches: HashMap<&str, Vec<&'a str>> = HashMap::new();
+        for key in keys {
+            if let Some(node) = self.route(key) {
+                batches.entry(node).or_default().push(key);
+            }
+        }
+        batches
+    }
+
+    /// Lists the keys whose owner differs between `self` and `next`
+    ///
+    /// Used after a membership change to find what has to be moved.
+    pub fn moved_keys<'a, I>(&self, next: &Router, keys: I) -> Vec<(&'a str, String)>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        keys.into_iter()
+            .filter_map(|key| match (self.route(key), next.route(key)) {
+                (old, Some(new)) if old != Some(new) => Some((key, new.to_string())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_is_stable() {
+        let mut router = Router::new();
+        router.add_node("10.0.0.1:8080");
+        router.add_node("10.0.0.2:8080");
+        router.add_node("10.0.0.3:8080");
+
+        let owner = router.route("user:42").unwrap();
+        assert_eq!(router.route("user:42"), Some(owner));
+        assert_eq!(router.nodes().len(), 3);
+        assert!(Router::new().route("user:42").is_none());
+
+        let mut reversed = Router::new();
+        for node in ["10.0.0.3:8080", "10.0.0.1:8080", "10.0.0.2:8080"] {
+            reversed.add_node(node);
+        }
+        assert_eq!(reversed.route("user:42"), Some(owner));
+        let total: f64 = router.shares().iter().map(|(_, share)| share).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_colliding_points_are_rehashed() {
+        let mut router = Router::with_virtual_nodes(4);
+        router.hash = |bytes| bytes.len() as u64;
+        router.add_node("a");
+        router.add_node("b");
+        assert_eq!(router.ring.len(), 8);
+        assert_eq!(router.ring.values().filter(|node| *node == "b").count(), 4);
+
+        router.remove_node("a");
+        assert_eq!(router.ring.len(), 4);
+        assert_eq!(router.route("anything"), Some("b"));
+    }
+
+    #[test]
+    fn test_membership_change_moves_few_keys() {
+        let mut router = Router::new();
+        router.add_node("a");
+        router.add_node("b");
+        router.add_node("c");
+
+        let mut next = router.clone();
+        next.add_node("d");
+
+        let keys: Vec<String> = (0..1000).map(|i| format!("key:{}", i)).collect();
+        let moved = router.moved_keys(&next, keys.iter().map(String::as_str));
+
+        assert!(!moved.is_empty());
+        assert!(moved.len() < 500);
+        assert!(moved.iter().all(|(_, node)| node == "d"));
+
+        let batches = next.partition(keys.iter().map(String::as_str));
+        assert_eq!(batches.values().map(Vec::len).sum::<usize>(), 1000);
+    }
+}
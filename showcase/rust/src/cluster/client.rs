@@ -0,0 +1,290 @@
+//! Client spreading keys over several `gvs-server` nodes
+//!
+//! Single-key calls go to the key's owner on the `Router`. Batch calls are
+//! split per owner and every node's share is sent from its own thread, so a
+//! batch takes about as long as the slowest node's part of it. Connections
+//! open on first use and are dropped after an I/O error, so the next call
+//! reconnects.
+use std::collections::HashMap;
+use std::thread;
+
+use super::{Dashboard, NodeState, Router};
+use crate::client::{Client, ClientError};
+
+/// A `Client` per node, picked by consistent hashing
+#[derive(Debug)]
+pub struct ClusterClient {
+    router: Router,
+    clients: HashMap<String, Client>,
+}
+
+impl ClusterClient {
+    /// Routes over `router`'s nodes without connecting yet
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            clients: HashMap::new(),
+        }
+    }
+
+    pub fn router(&self) -> &Router {
+        &self.router
+    }
+
+    fn owner(&self, key: &str) -> Result<String, ClientError> {
+        match self.router.route(key) {
+            Some(node) => Ok(node.to_string()),
+            None => Err(ClientError::InvalidArgument(
+                "The cluster has no nodes".to_string(),
+            )),
+        }
+    }
+
+    /// Takes the connection to `node` out of the pool, opening one if needed
+    fn take(&mut self, node: &str) -> Result<Client, ClientError> {
+        match self.clients.remove(node) {
+            Some(client) => Ok(client),
+            None => Ok(Client::connect(node)?),
+        }
+    }
+
+    /// Returns a connection to the pool unless its last call broke it
+    fn put_back<T>(&mut self, node: String, client: Client, result: &Result<T, ClientError>) {
+        if !matches!(result, Err(ClientError::Io(_))) {
+            self.clients.insert(node, client);
+        }
+    }
+
+    fn on_owner<T>(
+        &mut self,
+        key: &str,
+        f: impl FnOnce(&mut Client) -> Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        let node = self.owner(key)?;
+        let mut client = self.take(&node)?;
+        let result = f(&mut client);
+        self.put_back(node, client, &result);
+        result
+    }
+
+    pub fn get(&mut self, key: &str) -> Result<Option<String>, ClientError> {
+        self.on_owner(key, |client| client.get(key))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ClientError> {
+        self.on_owner(key, |client| client.set(key, value))
+    }
+
+    /// Removes `key`, returning true if its owner held it
+    pub fn delete(&mut self, key: &str) -> Result<bool, ClientError> {
+        self.on_owner(key, |client| client.delete(key))
+    }
+
+    /// Reads every key, answering in the order asked
+    pub fn get_many(&mut self, keys: &[&str]) -> Result<Vec<Option<String>>, ClientError> {
+        self.fan_out(
+            keys.iter().map(|key| (*key, ())).collect(),
+            |client, key, ()| client.get(key),
+        )
+    }
+
+    /// Writes every pair, stopping each node's share at its first failure
+    pub fn set_many(&mut self, pairs: &[(&str, &str)]) -> Result<(), ClientError> {
+        self.fan_out(pairs.to_vec(), |client, key, value| client.set(key, value))
+            .map(|_| ())
+    }
+
+    /// Runs `run` on each item's owner, one thread per node
+    fn fan_out<'a, T, R>(
+        &mut self,
+        items: Vec<(&'a str, T)>,
+        run: impl Fn(&mut Client, &'a str, T) -> Result<R, ClientError> + Sync,
+    ) -> Result<Vec<R>, ClientError>
+    where
+        T: Send,
+        R: Send,
+    {
+        let count = items.len();
+        let mut batches: HashMap<String, Vec<(usize, &'a str, T)>> = HashMap::new();
+        for (index, (key, item)) in items.into_iter().enumerate() {
+            let node = self.owner(key)?;
+            batches.entry(node).or_default().push((index, key, item));
+        }
+        let mut work = Vec::with_capacity(batches.len());
+        for (node, batch) in batches {
+            let client = self.take(&node)?;
+            work.push((node, client, batch));
+        }
+
+        let run = &run;
+        let finished: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = work
+                .into_iter()
+                .map(|(node, mut client, batch)| {
+                    scope.spawn(move || {
+                        let mut replies = Vec::with_capacity(batch.len());
+                        for (index, key, item) in batch {
+                            match run(&mut client, key, item) {
+                                Ok(reply) => replies.push((index, reply)),
+                                Err(e) => return (node, client, Err(e)),
+                            }
+                        }
+                        (node, client, Ok(replies))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("fan-out thread panicked"))
+                .collect()
+        });
+
+        let mut replies: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        let mut failure = None;
+        for (node, client, result) in finished {
+            self.put_back(node, client, &result);
+            match result {
+                Ok(done) => {
+                    for (index, reply) in done {
+                        replies[index] = Some(reply);
+                    }
+                }
+                Err(e) => failure = failure.or(Some(e)),
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(replies.into_iter().flatten().collect()),
+        }
+    }
+
+    /// Switches to `next`, first copying every key that changes owner
+    ///
+    /// The server can't list its keys, so the caller names the keys to
+    /// check. Each moved key is written to its new owner before it is
+    /// deleted from the old one, and the router only switches once all of
+    /// them moved; on failure it can be retried with the same arguments.
+    /// Returns how many keys were moved.
+    pub fn rebalance<'a, I>(&mut self, next: Router, keys: I) -> Result<usize, ClientError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut moved = 0;
+        for (key, new_owner) in self.router.moved_keys(&next, keys) {
+            let Some(value) = self.get(key)? else {
+                continue;
+            };
+            let mut client = self.take(&new_owner)?;
+            let result = client.set(key, &value);
+            self.put_back(new_owner, client, &result);
+            result?;
+            self.delete(key)?;
+            moved += 1;
+        }
+        let nodes = next.nodes();
+        self.clients
+            .retain(|node, _| nodes.contains(&node.as_str()));
+        self.router = next;
+        Ok(moved)
+    }
+
+    /// Probes every node and reports which ones answer
+    ///
+    /// A node counts as alive when it answers a command, even with an
+    /// error such as a login request.
+    pub fn dashboard(&mut self) -> Dashboard {
+        let nodes: Vec<String> = self.router.nodes().into_iter().map(String::from).collect();
+        let mut states = HashMap::new();
+        for node in nodes {
+            let state = match self.take(&node) {
+                Ok(mut client) => {
+                    let result = client.command("get __ping");
+                    let reachable = !matches!(result, Err(ClientError::Io(_)));
+                    self.put_back(node.clone(), client, &result);
+                    if reachable {
+                        NodeState::Alive
+                    } else {
+                        NodeState::Dead
+                    }
+                }
+                Err(_) => NodeState::Dead,
+            };
+            states.insert(node, state);
+        }
+        Dashboard::new(&self.router, |node| states.get(node).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::serve;
+    use crate::concurrent::ConcurrentDatabase;
+    use std::sync::Arc;
+
+    fn cluster(nodes: usize) -> (Vec<Arc<ConcurrentDatabase>>, Router) {
+        let mut router = Router::new();
+        let dbs: Vec<_> = (0..nodes)
+            .map(|_| {
+                let db = Arc::new(ConcurrentDatabase::new());
+                router.add_node(&serve(Arc::clone(&db)));
+                db
+            })
+            .collect();
+        (dbs, router)
+    }
+
+    #[test]
+    fn test_batches_fan_out_per_node() {
+        let (dbs, router) = cluster(3);
+        let mut client = ClusterClient::new(router);
+        let keys: Vec<String> = (0..60).map(|i| format!("key:{}", i)).collect();
+        let pairs: Vec<(&str, &str)> = keys.iter().map(|key| (key.as_str(), "v")).collect();
+        client.set_many(&pairs).unwrap();
+
+        assert!(dbs.iter().all(|db| !db.read().is_empty()));
+        assert_eq!(dbs.iter().map(|db| db.read().len()).sum::<usize>(), 60);
+        let asked = ["key:7", "missing", "key:0"];
+        assert_eq!(
+            client.get_many(&asked).unwrap(),
+            [Some("v".to_string()), None, Some("v".to_string())]
+        );
+        assert!(client.delete("key:7").unwrap());
+        assert_eq!(client.get("key:7").unwrap(), None);
+    }
+
+    #[test]
+    fn test_rebalance_moves_keys_to_new_owners() {
+        let (dbs, router) = cluster(2);
+        let mut client = ClusterClient::new(router.clone());
+        let keys: Vec<String> = (0..100).map(|i| format!("key:{}", i)).collect();
+        for key in &keys {
+            client.set(key, key).unwrap();
+        }
+
+        let joined = Arc::new(ConcurrentDatabase::new());
+        let mut next = router.clone();
+        next.add_node(&serve(Arc::clone(&joined)));
+        let expected = router
+            .moved_keys(&next, keys.iter().map(String::as_str))
+            .len();
+        let moved = client
+            .rebalance(next, keys.iter().map(String::as_str))
+            .unwrap();
+
+        assert_eq!(moved, expected);
+        assert_eq!(joined.read().len(), moved);
+        assert_eq!(
+            dbs.iter().map(|db| db.read().len()).sum::<usize>(),
+            100 - moved
+        );
+        let values = client.get_many(&["key:1", "key:99"]).unwrap();
+        assert_eq!(values, [Some("key:1".into()), Some("key:99".into())]);
+
+        let dashboard = client.dashboard();
+        assert!(dashboard
+            .rows
+            .iter()
+            .all(|row| row.state == Some(NodeState::Alive)));
+    }
+}
@@ -0,0 +1,160 @@
+//! Operator view of a cluster: who is on the ring, who answers, who owns what
+//!
+//! `Display` renders an aligned table for terminals and `to_json` a single
+//! line for scripts and monitoring.
+use std::fmt;
+
+use super::{Membership, NodeState, Router};
+
+/// One endpoint as the dashboard shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRow {
+    pub endpoint: String,
+    /// `None` when nothing is known about the endpoint's health
+    pub state: Option<NodeState>,
+    /// Points the endpoint holds on the ring, 0 when it isn't routed to
+    pub virtual_nodes: usize,
+    /// Fraction of the keyspace routed to the endpoint
+    pub share: f64,
+}
+
+/// Rows for every endpoint, sorted by endpoint
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dashboard {
+    pub rows: Vec<NodeRow>,
+}
+
+fn state_name(state: Option<NodeState>) -> &'static str {
+    match state {
+        Some(NodeState::Alive) => "alive",
+        Some(NodeState::Suspect) => "suspect",
+        Some(NodeState::Dead) => "dead",
+        None => "unknown",
+    }
+}
+
+impl Dashboard {
+    /// Shows the ring's endpoints, asking `state_of` about their health
+    pub fn new(router: &Router, state_of: impl Fn(&str) -> Option<NodeState>) -> Self {
+        let rows = router
+            .shares()
+            .into_iter()
+            .map(|(endpoint, share)| NodeRow {
+                endpoint: endpoint.to_string(),
+                state: state_of(endpoint),
+                virtual_nodes: router.virtual_nodes(),
+                share,
+            })
+            .collect();
+        Self { rows }
+    }
+
+    /// Shows every gossiped member, including those off the ring
+    pub fn with_membership(membership: &Membership, router: &Router) -> Self {
+        let mut dashboard = Self::new(router, |endpoint| membership.state(endpoint));
+        for (endpoint, state) in membership.members() {
+            if !dashboard.rows.iter().any(|row| row.endpoint == endpoint) {
+                dashboard.rows.push(NodeRow {
+                    endpoint: endpoint.to_string(),
+                    state: Some(state),
+                    virtual_nodes: 0,
+                    share: 0.0,
+                });
+            }
+        }
+        dashboard.rows.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        dashboard
+    }
+
+    /// Renders the rows as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                format!(
+                    "{{\"endpoint\":{:?},\"state\":\"{}\",\"virtual_nodes\":{},\"share\":{:.4}}}",
+                    row.endpoint,
+                    state_name(row.state),
+                    row.virtual_nodes,
+                    row.share
+                )
+            })
+            .collect();
+        format!("{{\"nodes\":[{}]}}", rows.join(","))
+    }
+}
+
+impl fmt::Display for Dashboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.endpoint.len())
+            .chain(["endpoint".len()])
+            .max()
+            .unwrap_or(0);
+        writeln!(f, "{:<width$}  state    vnodes  share", "endpoint")?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:<width$}  {:<7}  {:>6}  {:>5.1}%",
+                row.endpoint,
+                state_name(row.state),
+                row.virtual_nodes,
+                row.share * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_membership_view() {
+        let now = Instant::now();
+        let mut membership = Membership::new("a", Duration::from_secs(2), Duration::from_secs(5));
+        membership.join("b", now);
+        membership.join("c", now);
+        let mut router = Router::with_virtual_nodes(8);
+        router.add_node("a");
+        router.add_node("b");
+        membership.detect_failures(now + Duration::from_secs(3));
+
+        let dashboard = Dashboard::with_membership(&membership, &router);
+        let states: Vec<_> = dashboard
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    row.endpoint.as_str(),
+                    state_name(row.state),
+                    row.virtual_nodes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            states,
+            [("a", "alive", 8), ("b", "suspect", 8), ("c", "suspect", 0)]
+        );
+        let table = dashboard.to_string();
+        assert!(table.starts_with("endpoint  state    vnodes  share\n"));
+        assert!(table.ends_with("c         suspect       0    0.0%\n"));
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let mut router = Router::new();
+        router.add_node("10.0.0.1:7000");
+        let json = Dashboard::new(&router, |_| None).to_json();
+        assert_eq!(
+            json,
+            "{\"nodes\":[{\"endpoint\":\"10.0.0.1:7000\",\"state\":\"unknown\",\
+             \"virtual_nodes\":64,\"share\":1.0000}]}"
+        );
+    }
+}
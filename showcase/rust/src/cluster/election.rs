@@ -0,0 +1,367 @@
+//! Leader election between a primary and its replicas
+//!
+//! A Raft-style vote over numbered terms. A node that hears no heartbeat
+//! for its timeout starts a new term and asks every peer for its vote; each
+//! node gives one vote per term, and a candidate holding a majority becomes
+//! the leader. A leader that can't reach a majority for a timeout steps
+//! down, so a partitioned primary stops taking writes. Give each node a
+//! slightly different timeout so candidates rarely split the vote.
+//!
+//! Transport is left to the caller: send `tick` and `heartbeat` messages to
+//! every peer, answer received ones with `receive`, and feed each round's
+//! answers back into `on_replies`. Replication is asynchronous, so writes
+//! the old primary had not forwarded yet are lost on failover.
+use std::time::{Duration, Instant};
+
+/// What the local node currently is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Leader,
+    Follower,
+    Candidate,
+}
+
+/// A message between nodes, sent as one `ELECTION` command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Heartbeat { term: u64, leader: String },
+    RequestVote { term: u64, candidate: String },
+}
+
+impl Message {
+    pub fn encode(&self) -> String {
+        match self {
+            Message::Heartbeat { term, leader } => {
+                format!("ELECTION HEARTBEAT {} {}", term, leader)
+            }
+            Message::RequestVote { term, candidate } => {
+                format!("ELECTION VOTE {} {}", term, candidate)
+            }
+        }
+    }
+
+    /// Parses a line written by `encode`
+    pub fn decode(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (kind, term, node) = match words.as_slice() {
+            [verb, kind, term, node] if verb.eq_ignore_ascii_case("election") => {
+                let term = term
+                    .parse()
+                    .map_err(|_| format!("Invalid term: {}", term))?;
+                (kind.to_ascii_uppercase(), term, node.to_string())
+            }
+            _ => return Err("usage: ELECTION HEARTBEAT|VOTE <term> <node>".to_string()),
+        };
+        match kind.as_str() {
+            "HEARTBEAT" => Ok(Message::Heartbeat { term, leader: node }),
+            "VOTE" => Ok(Message::RequestVote {
+                term,
+                candidate: node,
+            }),
+            kind => Err(format!("Unknown election message: {}", kind)),
+        }
+    }
+
+    /// Returns true for lines `decode` should be given
+    pub fn recognizes(line: &str) -> bool {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|verb| verb.eq_ignore_ascii_case("election"))
+    }
+}
+
+/// A peer's answer to a `Message`, sent back as the reply text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reply {
+    /// The peer's term, which may be newer than the sender's
+    pub term: u64,
+    /// Whether the vote was given or the heartbeat accepted
+    pub granted: bool,
+}
+
+impl Reply {
+    pub fn encode(&self) -> String {
+        let answer = if self.granted { "granted" } else { "denied" };
+        format!("{} {}", answer, self.term)
+    }
+
+    /// Parses a reply written by `encode`
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid election reply: {}", text);
+        let (answer, term) = text.split_once(' ').ok_or_else(invalid)?;
+        let term = term.parse().map_err(|_| invalid())?;
+        match answer {
+            "granted" => Ok(Reply {
+                term,
+                granted: true,
+            }),
+            "denied" => Ok(Reply {
+                term,
+                granted: false,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// One node's view of who leads the cluster
+#[derive(Debug, Clone)]
+pub struct Election {
+    local: String,
+    peers: usize,
+    timeout: Duration,
+    term: u64,
+    role: Role,
+    leader: Option<String>,
+    voted_for: Option<String>,
+    votes: usize,
+    deadline: Instant,
+}
+
+impl Election {
+    /// Starts as a follower of nobody among `peers` other nodes
+    pub fn new(local: &str, peers: usize, timeout: Duration, now: Instant) -> Self {
+        Self {
+            local: local.to_string(),
+            peers,
+            timeout,
+            term: 0,
+            role: Role::Follower,
+            leader: None,
+            voted_for: None,
+            votes: 0,
+            deadline: now + timeout,
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    /// The endpoint of the current leader, the local one included
+    pub fn leader(&self) -> Option<&str> {
+        self.leader.as_deref()
+    }
+
+    /// Fails with a `MOVED <leader>` message unless this node leads
+    pub fn redirect(&self) -> Result<(), String> {
+        match (self.role, &self.leader) {
+            (Role::Leader, _) => Ok(()),
+            (_, Some(leader)) => Err(format!("MOVED {}", leader)),
+            (_, None) => Err("No leader elected yet".to_string()),
+        }
+    }
+
+    /// Votes needed to lead: a majority of the peers and this node
+    fn quorum(&self) -> usize {
+        let nodes = self.peers + 1;
+        nodes / 2 + 1
+    }
+
+    /// Moves to a newer term as a follower of nobody yet
+    fn adopt(&mut self, term: u64) {
+        if term > self.term {
+            self.term = term;
+            self.role = Role::Follower;
+            self.leader = None;
+            self.voted_for = None;
+        }
+    }
+
+    fn lead(&mut self, now: Instant) {
+        self.role = Role::Leader;
+        self.leader = Some(self.local.clone());
+        self.deadline = now + self.timeout;
+    }
+
+    /// Acts on a timeout passing, returning a vote request to send to every peer
+    ///
+    /// Followers and candidates that timed out start a new term; a leader
+    /// that timed out steps down.
+    pub fn tick(&mut self, now: Instant) -> Option<Message> {
+        if now < self.deadline {
+            return None;
+        }
+        if self.role == Role::Leader {
+            self.role = Role::Follower;
+            self.leader = None;
+            self.deadline = now + self.timeout;
+            return None;
+        }
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.voted_for = Some(self.local.clone());
+        self.votes = 1;
+        self.deadline = now + self.timeout;
+        if self.votes >= self.quorum() {
+            self.lead(now);
+            return None;
+        }
+        Some(Message::RequestVote {
+            term: self.term,
+            candidate: self.local.clone(),
+        })
+    }
+
+    /// The heartbeat a leader sends every peer, well within their timeout
+    pub fn heartbeat(&self) -> Option<Message> {
+        (self.role == Role::Leader).then(|| Message::Heartbeat {
+            term: self.term,
+            leader: self.local.clone(),
+        })
+    }
+
+    /// Answers a message from another node
+    pub fn receive(&mut self, message: &Message, now: Instant) -> Reply {
+        let granted = match message {
+            Message::Heartbeat { term, leader } if *term >= self.term => {
+                self.adopt(*term);
+                self.role = Role::Follower;
+                self.leader = Some(leader.clone());
+                self.deadline = now + self.timeout;
+                true
+            }
+            Message::RequestVote { term, candidate } if *term >= self.term => {
+                self.adopt(*term);
+                let free = self.voted_for.as_ref().is_none_or(|vote| vote == candidate);
+                let granted = self.role == Role::Follower && free;
+                if granted {
+                    self.voted_for = Some(candidate.clone());
+                    self.deadline = now + self.timeout;
+                }
+                granted
+            }
+            _ => false,
+        };
+        Reply {
+            term: self.term,
+            granted,
+        }
+    }
+
+    /// Counts the replies to this round's vote requests or heartbeats
+    ///
+    /// A reply from a newer term makes this node a follower again.
+    pub fn on_replies(&mut self, replies: &[Reply], now: Instant) {
+        if let Some(newer) = replies
+            .iter()
+            .map(|r| r.term)
+            .filter(|&t| t > self.term)
+            .max()
+        {
+            self.adopt(newer);
+            self.deadline = now + self.timeout;
+            return;
+        }
+        let granted = replies
+            .iter()
+            .filter(|reply| reply.granted && reply.term == self.term)
+            .count();
+        match self.role {
+            Role::Candidate => {
+                self.votes += granted;
+                if self.votes >= self.quorum() {
+                    self.lead(now);
+                }
+            }
+            Role::Leader if granted + 1 >= self.quorum() => {
+                self.deadline = now + self.timeout;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Delivers `message` to every node in `peers`, collecting their replies
+    fn broadcast(message: &Message, peers: &mut [&mut Election], now: Instant) -> Vec<Reply> {
+        let line = message.encode();
+        let message = Message::decode(&line).unwrap();
+        peers
+            .iter_mut()
+            .map(|peer| Reply::decode(&peer.receive(&message, now).encode()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_first_to_time_out_leads() {
+        let start = Instant::now();
+        let mut a = Election::new("a", 2, TIMEOUT, start);
+        let mut b = Election::new("b", 2, TIMEOUT * 2, start);
+        let mut c = Election::new("c", 2, TIMEOUT * 2, start);
+        assert_eq!(b.redirect().unwrap_err(), "No leader elected yet");
+
+        let now = start + TIMEOUT;
+        let request = a.tick(now).unwrap();
+        assert_eq!(a.role(), Role::Candidate);
+        let replies = broadcast(&request, &mut [&mut b, &mut c], now);
+        a.on_replies(&replies, now);
+        assert_eq!(a.role(), Role::Leader);
+        assert_eq!(a.redirect(), Ok(()));
+
+        let heartbeat = a.heartbeat().unwrap();
+        let replies = broadcast(&heartbeat, &mut [&mut b, &mut c], now);
+        a.on_replies(&replies, now);
+        assert_eq!(b.leader(), Some("a"));
+        assert_eq!(c.redirect().unwrap_err(), "MOVED a");
+        assert_eq!(b.tick(now + TIMEOUT), None);
+
+        let mut late = Election::new("late", 2, TIMEOUT, start);
+        let rival = late.tick(now).unwrap();
+        let replies = broadcast(&rival, &mut [&mut b], now);
+        assert_eq!(
+            replies,
+            [Reply {
+                term: 1,
+                granted: false
+            }]
+        );
+    }
+
+    #[test]
+    fn test_replica_takes_over_from_silent_primary() {
+        let start = Instant::now();
+        let mut a = Election::new("a", 2, TIMEOUT, start);
+        let mut b = Election::new("b", 2, TIMEOUT * 2, start);
+        let mut c = Election::new("c", 2, TIMEOUT * 3, start);
+        let request = a.tick(start + TIMEOUT).unwrap();
+        let replies = broadcast(&request, &mut [&mut b, &mut c], start + TIMEOUT);
+        a.on_replies(&replies, start + TIMEOUT);
+
+        // a goes silent; b's timeout passes first
+        let now = start + TIMEOUT * 4;
+        let request = b.tick(now).unwrap();
+        let replies = broadcast(&request, &mut [&mut c], now);
+        b.on_replies(&replies, now);
+        assert_eq!((b.role(), b.term()), (Role::Leader, 2));
+
+        // a missed a round of acks, so it has stepped down by itself
+        assert_eq!(a.tick(now), None);
+        assert_eq!(a.role(), Role::Follower);
+        let heartbeat = b.heartbeat().unwrap();
+        broadcast(&heartbeat, &mut [&mut a, &mut c], now);
+        assert_eq!(a.redirect().unwrap_err(), "MOVED b");
+
+        // a stale leader learns of the new term from any reply
+        let mut stale = Election::new("a", 2, TIMEOUT, start);
+        stale.lead(now);
+        let old = stale.heartbeat().unwrap();
+        let replies = broadcast(&old, &mut [&mut b, &mut c], now);
+        stale.on_replies(&replies, now);
+        assert_eq!((stale.role(), stale.term()), (Role::Follower, 2));
+        assert_eq!(
+            Message::decode("ELECTION PING 1 a").unwrap_err(),
+            "Unknown election message: PING"
+        );
+    }
+}
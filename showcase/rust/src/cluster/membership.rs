@@ -0,0 +1,239 @@
+//! Heartbeat gossip keeping a live view of cluster members
+//!
+//! Transport is left to the caller: each round, send `digest()` to the peers
+//! from `gossip_targets()` and feed whatever comes back into `merge()`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::Router;
+
+/// Gossiped heartbeat counters, one per known endpoint
+pub type Digest = Vec<(String, u64)>;
+
+/// Liveness of a member as seen by the local node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    Alive,
+    Suspect,
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+struct Member {
+    heartbeat: u64,
+    updated_at: Instant,
+    state: NodeState,
+}
+
+/// Local view of the cluster built from gossiped heartbeats
+#[derive(Debug, Clone)]
+pub struct Membership {
+    local: String,
+    members: HashMap<String, Member>,
+    suspect_after: Duration,
+    dead_after: Duration,
+    round: usize,
+}
+
+impl Membership {
+    /// Creates a view containing only the local endpoint
+    pub fn new(local: &str, suspect_after: Duration, dead_after: Duration) -> Self {
+        let mut members = HashMap::new();
+        members.insert(
+            local.to_string(),
+            Member {
+                heartbeat: 0,
+                updated_at: Instant::now(),
+                state: NodeState::Alive,
+            },
+        );
+        Self {
+            local: local.to_string(),
+            members,
+            suspect_after,
+            dead_after,
+            round: 0,
+        }
+    }
+
+    /// Adds a seed peer that has not been heard from yet
+    pub fn join(&mut self, endpoint: &str, now: Instant) {
+        self.members.entry(endpoint.to_string()).or_insert(Member {
+            heartbeat: 0,
+            updated_at: now,
+            state: NodeState::Alive,
+        });
+    }
+
+    /// Bumps the local heartbeat and returns the digest to gossip this round
+    pub fn tick(&mut self, now: Instant) -> Digest {
+        if let Some(local) = self.members.get_mut(&self.local) {
+            local.heartbeat += 1;
+            local.updated_at = now;
+        }
+        self.round = self.round.wrapping_add(1);
+        self.digest()
+    }
+
+    /// Returns the heartbeat of every member that is not dead
+    pub fn digest(&self) -> Digest {
+        self.members
+            .iter()
+            .filter(|(_, member)| member.state != NodeState::Dead)
+            .map(|(endpoint, member)| (endpoint.clone(), member.heartbeat))
+            .collect()
+    }
+
+    /// Folds a digest received from a peer into the local view
+    pub fn merge(&mut self, digest: &[(String, u64)], now: Instant) {
+        for (endpoint, heartbeat) in digest {
+            match self.members.get_mut(endpoint) {
+                Some(member) if *heartbeat > member.heartbeat => {
+                    member.heartbeat = *heartbeat;
+                    member.updated_at = now;
+                    member.state = NodeState::Alive;
+                }
+                Some(_) => {}
+                None => {
+                    self.members.insert(
+                        endpoint.clone(),
+                        Member {
+                            heartbeat: *heartbeat,
+                            updated_at: now,
+                            state: NodeState::Alive,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Marks members whose heartbeat stalled as suspect, then dead
+    pub fn detect_failures(&mut self, now: Instant) {
+        for (endpoint, member) in self.members.iter_mut() {
+            if *endpoint == self.local {
+                continue;
+            }
+            let silent = now.saturating_duration_since(member.updated_at);
+            member.state = if silent >= self.dead_after {
+                NodeState::Dead
+            } else if silent >= self.suspect_after {
+                NodeState::Suspect
+            } else {
+                NodeState::Alive
+            };
+        }
+    }
+
+    /// Picks up to `fanout` peers to gossip with, rotating every round
+    pub fn gossip_targets(&self, fanout: usize) -> Vec<&str> {
+        let mut peers: Vec<&str> = self
+            .members
+            .iter()
+            .filter(|(endpoint, member)| {
+                **endpoint != self.local && member.state != NodeState::Dead
+            })
+            .map(|(endpoint, _)| endpoint.as_str())
+            .collect();
+        peers.sort_unstable();
+        if peers.is_empty() {
+            return peers;
+        }
+        let offset = self.round % peers.len();
+        peers.rotate_left(offset);
+        peers.truncate(fanout);
+        peers
+    }
+
+    /// Returns the state of `endpoint`, if it is known
+    pub fn state(&self, endpoint: &str) -> Option<NodeState> {
+        self.members.get(endpoint).map(|member| member.state)
+    }
+
+    /// Returns every known member with its state, sorted by endpoint
+    pub fn members(&self) -> Vec<(&str, NodeState)> {
+        let mut members: Vec<(&str, NodeState)> = self
+            .members
+            .iter()
+            .map(|(endpoint, member)| (endpoint.as_str(), member.state))
+            .collect();
+        members.sort_unstable_by_key(|(endpoint, _)| *endpoint);
+        members
+    }
+
+    /// Returns the members currently considered alive, sorted by endpoint
+    pub fn alive(&self) -> Vec<&str> {
+        let mut alive: Vec<&str> = self
+            .members
+            .iter()
+            .filter(|(_, member)| member.state == NodeState::Alive)
+            .map(|(endpoint, _)| endpoint.as_str())
+            .collect();
+        alive.sort_unstable();
+        alive
+    }
+
+    /// Rebuilds the router ring so it only contains alive members
+    pub fn sync_router(&self, router: &mut Router) {
+        let alive = self.alive();
+        let current: Vec<String> = router.nodes().into_iter().map(String::from).collect();
+        for node in current
+            .iter()
+            .filter(|node| !alive.contains(&node.as_str()))
+        {
+            router.remove_node(node);
+        }
+        for endpoint in alive {
+            if !current.iter().any(|node| node == endpoint) {
+                router.add_node(endpoint);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUSPECT: Duration = Duration::from_secs(2);
+    const DEAD: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_gossip_spreads_members() {
+        let now = Instant::now();
+        let mut a = Membership::new("a", SUSPECT, DEAD);
+        let mut b = Membership::new("b", SUSPECT, DEAD);
+        let mut c = Membership::new("c", SUSPECT, DEAD);
+
+        b.merge(&a.tick(now), now);
+        c.merge(&b.tick(now), now);
+
+        assert_eq!(c.alive(), vec!["a", "b", "c"]);
+        assert_eq!(c.gossip_targets(1).len(), 1);
+    }
+
+    #[test]
+    fn test_silent_member_is_dropped_from_router() {
+        let start = Instant::now();
+        let mut a = Membership::new("a", SUSPECT, DEAD);
+        a.join("b", start);
+        a.join("c", start);
+
+        let mut router = Router::new();
+        a.sync_router(&mut router);
+        assert_eq!(router.nodes(), vec!["a", "b", "c"]);
+
+        let later = start + Duration::from_secs(3);
+        a.merge(&[("c".to_string(), 7)], later);
+        a.detect_failures(later);
+        assert_eq!(a.state("b"), Some(NodeState::Suspect));
+
+        let much_later = start + Duration::from_secs(6);
+        a.detect_failures(much_later);
+        assert_eq!(a.state("b"), Some(NodeState::Dead));
+        assert_eq!(a.state("c"), Some(NodeState::Suspect));
+
+        a.sync_router(&mut router);
+        assert_eq!(router.nodes(), vec!["a"]);
+    }
+}
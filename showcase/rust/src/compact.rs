@@ -0,0 +1,169 @@
+//! Inline storage for short values (used with the `compact-values` feature)
+//!
+//! Values of up to `INLINE_CAPACITY` bytes live inside the map entry itself,
+//! so a store dominated by short values makes one heap allocation per key
+//! instead of two; `cargo bench --bench memory` measures the difference.
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Longest value kept inline; keeps `CompactValue` at 24 bytes
+pub const INLINE_CAPACITY: usize = 22;
+
+/// A string value stored inline when short and behind an `Arc` otherwise
+#[derive(Clone)]
+pub enum CompactValue {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Shared(Arc<str>),
+}
+
+impl CompactValue {
+    /// Returns true when the value did not need a heap allocation
+    pub fn is_inline(&self) -> bool {
+        matches!(self, CompactValue::Inline { .. })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            CompactValue::Inline { len, bytes } => {
+                // SAFETY: `bytes[..len]` is always copied from a `&str`
+                unsafe { std::str::from_utf8_unchecked(&bytes[..usize::from(*len)]) }
+            }
+            CompactValue::Shared(value) => value,
+        }
+    }
+
+    pub(crate) fn inline(value: &str) -> Option<Self> {
+        if value.len() > INLINE_CAPACITY {
+            return None;
+        }
+        let mut bytes = [0u8; INLINE_CAPACITY];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        Some(CompactValue::Inline {
+            len: value.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl From<&str> for CompactValue {
+    fn from(value: &str) -> Self {
+        Self::inline(value).unwrap_or_else(|| CompactValue::Shared(value.into()))
+    }
+}
+
+impl From<Arc<str>> for CompactValue {
+    fn from(value: Arc<str>) -> Self {
+        Self::inline(&value).unwrap_or(CompactValue::Shared(value))
+    }
+}
+
+impl From<CompactValue> for Arc<str> {
+    fn from(value: CompactValue) -> Self {
+        match value {
+            CompactValue::Shared(value) => value,
+            inline => inline.as_str().into(),
+        }
+    }
+}
+
+impl Deref for CompactValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for CompactValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for CompactValue {}
+
+impl fmt::Debug for CompactValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for CompactValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perf;
+    use std::mem::size_of;
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(size_of::<CompactValue>(), 24);
+        assert_eq!(size_of::<CompactValue>(), size_of::<String>());
+    }
+
+    #[test]
+    fn test_inline_threshold() {
+        let short = CompactValue::from("admin");
+        let exact = CompactValue::from("x".repeat(INLINE_CAPACITY).as_str());
+        let long = CompactValue::from(Arc::<str>::from("y".repeat(INLINE_CAPACITY + 1)));
+
+        assert!(short.is_inline());
+        assert!(exact.is_inline());
+        assert!(!long.is_inline());
+        assert_eq!(&*short, "admin");
+        assert_eq!(long.len(), INLINE_CAPACITY + 1);
+        assert_eq!(CompactValue::from("🦀 Rust"), CompactValue::from("🦀 Rust"));
+    }
+
+    #[test]
+    fn test_short_values_skip_the_heap() {
+        // a typical config-style workload: most values are a handful of bytes
+        let values: Vec<String> = (0..1000).map(|i| format!("value-{}", i)).collect();
+        let (compact, allocations) = perf::count_allocations(|| {
+            let mut compact = Vec::with_capacity(values.len());
+            compact.extend(values.iter().map(|v| CompactValue::from(v.as_str())));
+            compact
+        });
+        assert_eq!(allocations, 1);
+        assert!(compact.iter().all(CompactValue::is_inline));
+
+        let (_, allocations) = perf::count_allocations(|| {
+            let mut shared: Vec<Arc<str>> = Vec::with_capacity(values.len());
+            shared.extend(values.iter().map(|v| Arc::from(v.as_str())));
+            shared
+        });
+        assert_eq!(allocations, 1001);
+    }
+
+    #[cfg(feature = "compact-values")]
+    #[test]
+    fn test_database_keeps_short_values_inline() {
+        use crate::{Database, StrDatabase};
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let keys: Vec<String> = (0..100).map(|i| format!("key-{}", i)).collect();
+        let long = "v".repeat(INLINE_CAPACITY + 1);
+        let [long, short] = [&*long, "short"].map(|value| {
+            // a fixed hasher spreads both runs over the shards alike
+            let mut db = StrDatabase::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+            perf::count_allocations(|| {
+                for key in &keys {
+                    db.insert(key.as_str(), value);
+                }
+            })
+            .1
+        });
+        // both store one `Arc<str>` per key; only long values need another
+        assert_eq!(long - short, 100);
+    }
+}
@@ -0,0 +1,220 @@
+//! A database shared across threads behind one `RwLock`
+//!
+//! Reads take the lock shared and writes take it exclusively. Bounded
+//! databases still record reads, since their recency order has its own lock.
+//! Every write lock bumps a counter that `wait_for` sleeps on, so waiters
+//! wake once the writer is done rather than polling.
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{
+    Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+use std::time::{Duration, Instant};
+
+use crate::lending::{LendingDatabase, ValueGuard};
+use crate::{shared, Database, DbOperation, MemoryDatabase, StrDatabase};
+
+/// `Database` for stores shared between threads, writing through `&self`
+///
+/// Values come back owned because no borrow may outlive the lock; use
+/// `LendingDatabase::lend` to read one in place instead.
+pub trait SharedDatabase: Send + Sync {
+    fn insert(&self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>);
+    fn retrieve(&self, key: &str) -> Option<Arc<str>>;
+    /// Removes `key`, returning true if it was present
+    fn remove(&self, key: &str) -> bool;
+}
+
+/// A `MemoryDatabase` behind an `RwLock`, ready to share through an `Arc`
+#[derive(Debug)]
+pub struct ConcurrentDatabase<S = RandomState> {
+    inner: RwLock<StrDatabase<S>>,
+    writes: Mutex<u64>,
+    written: Condvar,
+}
+
+impl ConcurrentDatabase {
+    pub fn new() -> Self {
+        Self::from(MemoryDatabase::new())
+    }
+}
+
+impl<S: Default + Clone> Default for ConcurrentDatabase<S> {
+    fn default() -> Self {
+        Self::from(StrDatabase::default())
+    }
+}
+
+impl<S> From<StrDatabase<S>> for ConcurrentDatabase<S> {
+    fn from(db: StrDatabase<S>) -> Self {
+        Self {
+            inner: RwLock::new(db),
+            writes: Mutex::new(0),
+            written: Condvar::new(),
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> ConcurrentDatabase<S> {
+    /// Locks the database for reading, recovering it if a writer panicked
+    pub fn read(&self) -> RwLockReadGuard<'_, StrDatabase<S>> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the database for a multi-step write
+    pub fn write(&self) -> RwLockWriteGuard<'_, StrDatabase<S>> {
+        let guard = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        // waiters woken now block on the read lock until this write is done
+        *self.writes() += 1;
+        self.written.notify_all();
+        guard
+    }
+
+    /// Blocks until `key` is present or `timeout` passes
+    ///
+    /// Returns the value, or `None` on timeout. Wakes on every write, so a
+    /// busy database costs waiters one lookup per write.
+    pub fn wait_for(&self, key: &str, timeout: Duration) -> Option<Arc<str>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut seen = *self.writes();
+        loop {
+            if let Some(value) = self.read().get(key).cloned().map(shared) {
+                return Some(value);
+            }
+            let mut writes = self.writes();
+            while *writes == seen {
+                writes = match deadline {
+                    Some(deadline) => {
+                        let left = deadline
+                            .checked_duration_since(Instant::now())
+                            .filter(|left| !left.is_zero())?;
+                        self.written
+                            .wait_timeout(writes, left)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0
+                    }
+                    // a deadline past the clock's range never arrives
+                    None => self
+                        .written
+                        .wait(writes)
+                        .unwrap_or_else(PoisonError::into_inner),
+                };
+            }
+            seen = *writes;
+        }
+    }
+
+    /// Runs `op`, holding the lock shared for reads and exclusively for writes
+    pub fn execute(&self, op: &DbOperation) -> Result<String, String> {
+        if let Some(result) = op.execute_read(&*self.read()) {
+            return result;
+        }
+        op.execute(&mut *self.write())
+    }
+
+    pub fn into_inner(self) -> StrDatabase<S> {
+        self.inner.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn writes(&self) -> MutexGuard<'_, u64> {
+        self.writes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<S: BuildHasher + Clone + Send + Sync> SharedDatabase for ConcurrentDatabase<S> {
+    fn insert(&self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        self.write().insert(key, value);
+    }
+
+    fn retrieve(&self, key: &str) -> Option<Arc<str>> {
+        self.read().get(key).cloned().map(shared)
+    }
+
+    fn remove(&self, key: &str) -> bool {
+        self.write().remove(key).is_some()
+    }
+}
+
+impl<S: BuildHasher + Clone> LendingDatabase for ConcurrentDatabase<S> {
+    type Guard<'a>
+        = ValueGuard<'a, S>
+    where
+        S: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<ValueGuard<'a, S>> {
+        self.inner.lend(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_shared_across_threads() {
+        let db = Arc::new(ConcurrentDatabase::new());
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        db.insert(format!("{}:{}", worker, i), i.to_string());
+                    }
+                    db.remove(&format!("{}:0", worker))
+                })
+            })
+            .collect();
+        for worker in workers {
+            assert!(worker.join().unwrap());
+        }
+
+        assert_eq!(db.read().len(), 4 * 99);
+        assert_eq!(db.retrieve("3:42").as_deref(), Some("42"));
+        assert!(!db.remove("3:0"));
+    }
+
+    #[test]
+    fn test_reads_share_the_lock() {
+        let db = ConcurrentDatabase::new();
+        db.insert("language", "Rust");
+        let op = DbOperation::Retrieve {
+            key: "language".into(),
+        };
+
+        let held = db.read();
+        assert_eq!(
+            db.execute(&op),
+            Ok("Retrieved: language = Rust".to_string())
+        );
+        assert_eq!(&*db.lend("language").unwrap(), "Rust");
+        drop(held);
+
+        let insert = DbOperation::Insert {
+            key: "year".into(),
+            value: "2010".into(),
+        };
+        db.execute(&insert).unwrap();
+        assert_eq!(db.into_inner().retrieve("year"), Some("2010"));
+    }
+
+    #[test]
+    fn test_wait_for_blocks_until_written() {
+        let db = Arc::new(ConcurrentDatabase::new());
+        assert_eq!(db.wait_for("job", Duration::from_millis(20)), None);
+
+        let producer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                db.insert("noise", "1");
+                thread::sleep(Duration::from_millis(20));
+                db.write().insert("job", "done");
+            })
+        };
+        let value = db.wait_for("job", Duration::from_secs(10));
+        assert_eq!(value.as_deref(), Some("done"));
+        producer.join().unwrap();
+        assert_eq!(db.wait_for("job", Duration::ZERO).as_deref(), Some("done"));
+    }
+}
@@ -0,0 +1,175 @@
+//! CSV exchange with spreadsheets and other tools
+//!
+//! Files follow RFC 4180: comma-separated, with fields holding commas,
+//! quotes, line breaks or edge whitespace wrapped in double quotes and
+//! inner quotes doubled. Exports start with a `key,value` header; imports
+//! find those columns by name, so extra columns are ignored.
+use std::hash::BuildHasher;
+use std::io::{self, Read, Write};
+
+use crate::{persist, Database, StrDatabase};
+
+fn write_field(out: &mut impl Write, field: &str) -> io::Result<()> {
+    let needs_quotes = field.contains([',', '"', '\n', '\r']) || field.trim() != field;
+    if needs_quotes {
+        write!(out, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        out.write_all(field.as_bytes())
+    }
+}
+
+/// Fields of one record, after the line it starts on
+type Record = (usize, Vec<String>);
+
+/// Splits `input` into records, skipping blank lines
+fn parse_records(input: &str) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            match chars.next() {
+                Some('"') if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                Some('"') if field.is_empty() => quoted = true,
+                Some(c) if quoted => {
+                    line += usize::from(c == '\n');
+                    field.push(c);
+                }
+                Some(',') => fields.push(std::mem::take(&mut field)),
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') => {
+                    line += 1;
+                    break;
+                }
+                Some(c) => field.push(c),
+                None if quoted => {
+                    let e = "Unterminated quoted field".to_string();
+                    return Err(persist::invalid_line(start, e));
+                }
+                None => break,
+            }
+        }
+        fields.push(field);
+        // skip blank lines, which spreadsheets often leave at the end
+        if fields.len() > 1 || !fields[0].is_empty() {
+            records.push((start, fields));
+        }
+    }
+    Ok(records)
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Writes a `key,value` header and then every live entry in key order
+    ///
+    /// Returns how many entries were written.
+    pub fn export_csv<W: Write>(&self, mut out: W) -> io::Result<usize> {
+        let mut entries: Vec<(&str, &str)> = self.iter().collect();
+        entries.sort_unstable();
+        out.write_all(b"key,value\r\n")?;
+        for (key, value) in &entries {
+            write_field(&mut out, key)?;
+            out.write_all(b",")?;
+            write_field(&mut out, value)?;
+            out.write_all(b"\r\n")?;
+        }
+        out.flush()?;
+        Ok(entries.len())
+    }
+
+    /// Inserts every row below a header naming `key` and `value` columns
+    ///
+    /// Header names are matched ignoring case. Returns how many rows were
+    /// read; fails with `InvalidData` naming the line of a row that is
+    /// missing a column, before anything is inserted.
+    pub fn import_csv<R: Read>(&mut self, mut input: R) -> io::Result<usize> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let records = parse_records(&text)?;
+        let Some(((_, header), rows)) = records.split_first() else {
+            return Ok(0);
+        };
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|field| field.trim().eq_ignore_ascii_case(name))
+                .ok_or_else(|| persist::invalid_line(1, format!("No {} column", name)))
+        };
+        let (key, value) = (column("key")?, column("value")?);
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for (number, row) in rows {
+            match (row.get(key), row.get(value)) {
+                (Some(key), Some(value)) => entries.push((key.as_str(), value.as_str())),
+                _ => {
+                    return Err(persist::invalid_line(
+                        *number,
+                        "Missing key or value".to_string(),
+                    ))
+                }
+            }
+        }
+        self.insert_many(entries.iter().copied());
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_csv_round_trip_quotes_fields() {
+        let mut db = MemoryDatabase::new();
+        db.insert("plain", "value");
+        db.insert("a,b", "say \"hi\"");
+        db.insert("multi", "line one\nline two");
+        db.insert("padded", " x ");
+
+        let mut out = Vec::new();
+        assert_eq!(db.export_csv(&mut out).unwrap(), 4);
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(
+            csv,
+            "key,value\r\n\"a,b\",\"say \"\"hi\"\"\"\r\nmulti,\"line one\nline two\"\r\n\
+             padded,\" x \"\r\nplain,value\r\n"
+        );
+
+        let mut restored = MemoryDatabase::new();
+        assert_eq!(restored.import_csv(csv.as_bytes()).unwrap(), 4);
+        for (key, value) in &db {
+            assert_eq!(restored.retrieve(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_import_finds_columns_by_name() {
+        let mut db = MemoryDatabase::new();
+        let sheet = "id,Value,KEY\n1,red,colour\n2,\"big, round\",shape\n\n";
+        assert_eq!(db.import_csv(sheet.as_bytes()).unwrap(), 2);
+        assert_eq!(db.retrieve("shape"), Some("big, round"));
+
+        let err = db.import_csv("name,value\nx,1\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: No key column");
+        let err = db
+            .import_csv("key,value\na,1\n\"b\nc\",2\nd\n".as_bytes())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "line 5: Missing key or value");
+        assert_eq!(db.retrieve("a"), None);
+        let err = db
+            .import_csv("key,value\n\"open,1\n".as_bytes())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "line 2: Unterminated quoted field");
+    }
+}
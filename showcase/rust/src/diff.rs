@@ -0,0 +1,223 @@
+//! Comparing and reconciling two databases
+//!
+//! Both sides are compared by their live entries, so keys whose TTL has
+//! passed count as absent.
+use std::fmt;
+use std::hash::BuildHasher;
+use std::io;
+
+use crate::literals::escape_value;
+use crate::{Error, MemoryDatabase, StrDatabase};
+
+/// Keys added, removed or changed between two databases, sorted by key
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DbDiff {
+    pub added: Vec<(String, String)>,
+    pub removed: Vec<(String, String)>,
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl DbDiff {
+    /// Returns true when both sides hold the same entries
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// One tab-separated line per entry: `+ key value`, `- key value` or `~ key old new`
+///
+/// Fields are escaped with `escape_value`, so tabs and newlines inside keys
+/// and values can't split a line.
+impl fmt::Display for DbDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.added {
+            writeln!(f, "+\t{}\t{}", escape_value(key), escape_value(value))?;
+        }
+        for (key, value) in &self.removed {
+            writeln!(f, "-\t{}\t{}", escape_value(key), escape_value(value))?;
+        }
+        for (key, old, new) in &self.changed {
+            let (key, old, new) = (escape_value(key), escape_value(old), escape_value(new));
+            writeln!(f, "~\t{}\t{}\t{}", key, old, new)?;
+        }
+        Ok(())
+    }
+}
+
+/// What `merge_from` does with a key both databases hold with different values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep this database's value
+    #[default]
+    KeepOurs,
+    /// Take the other database's value
+    TakeTheirs,
+    /// Merge nothing and fail with `Error::Conflict`
+    Abort,
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Reports what changed from this database to `other`
+    pub fn diff<S2: BuildHasher + Clone>(&self, other: &StrDatabase<S2>) -> DbDiff {
+        let mut diff = DbDiff::default();
+        for (key, value) in other.iter() {
+            match self.live(key) {
+                None => diff.added.push((key.to_string(), value.to_string())),
+                Some(old) if old != value => {
+                    diff.changed
+                        .push((key.to_string(), old.to_string(), value.to_string()))
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, value) in self.iter() {
+            if other.live(key).is_none() {
+                diff.removed.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff
+    }
+
+    /// Copies in the entries of `other` that are missing or differ here
+    ///
+    /// Keys only this database holds are left alone. Returns how many entries
+    /// were written; the merge is all-or-nothing, so a conflict under
+    /// `Abort` or a refused write leaves the database unchanged.
+    pub fn merge_from<S2: BuildHasher + Clone>(
+        &mut self,
+        other: &StrDatabase<S2>,
+        policy: ConflictPolicy,
+    ) -> Result<usize, Error> {
+        let diff = self.diff(other);
+        if policy == ConflictPolicy::Abort && !diff.changed.is_empty() {
+            let keys = diff.changed.into_iter().map(|(key, _, _)| key).collect();
+            return Err(Error::Conflict { keys });
+        }
+        let taken = diff
+            .changed
+            .into_iter()
+            .filter(|_| policy == ConflictPolicy::TakeTheirs)
+            .map(|(key, _, new)| (key, new));
+        let mut tx = self.begin();
+        let mut written = 0;
+        for (key, value) in diff.added.into_iter().chain(taken) {
+            tx.try_insert(key, value)?;
+            written += 1;
+        }
+        tx.commit();
+        Ok(written)
+    }
+
+    /// The live value under `key`, without counting a read
+    pub(crate) fn live(&self, key: &str) -> Option<&str> {
+        self.store
+            .get(key)
+            .filter(|_| !self.deadlines.expired(key))
+            .map(|value| &**value)
+    }
+}
+
+impl MemoryDatabase {
+    /// Loads two database files and reports what changed from `a` to `b`
+    pub fn diff_files(a: &str, b: &str) -> io::Result<DbDiff> {
+        let before = Self::load_from_file(a)?;
+        let after = Self::load_from_file(b)?;
+        Ok(before.diff(&after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quota::QuotaPolicy;
+    use crate::Database;
+
+    #[test]
+    fn test_diff_files() {
+        let dir = std::env::temp_dir();
+        let a = dir.join("gvs_diff_a.db");
+        let b = dir.join("gvs_diff_b.db");
+
+        let mut before = MemoryDatabase::new();
+        before.insert("kept".to_string(), "1".to_string());
+        before.insert("changed".to_string(), "old".to_string());
+        before.insert("removed".to_string(), "x".to_string());
+        before.save_to_file(a.to_str().unwrap()).unwrap();
+
+        let mut after = MemoryDatabase::new();
+        after.insert("kept".to_string(), "1".to_string());
+        after.insert("changed".to_string(), "new".to_string());
+        after.insert("added".to_string(), "y".to_string());
+        after.save_to_file(b.to_str().unwrap()).unwrap();
+
+        let diff = MemoryDatabase::diff_files(a.to_str().unwrap(), b.to_str().unwrap()).unwrap();
+        assert_eq!(diff.added, vec![("added".to_string(), "y".to_string())]);
+        assert_eq!(diff.removed, vec![("removed".to_string(), "x".to_string())]);
+        assert_eq!(
+            diff.to_string(),
+            "+\tadded\ty\n-\tremoved\tx\n~\tchanged\told\tnew\n"
+        );
+
+        let tricky = DbDiff {
+            added: vec![("a\tb".to_string(), "line\nbreak".to_string())],
+            ..DbDiff::default()
+        };
+        assert_eq!(tricky.to_string(), "+\ta\\tb\tline\\nbreak\n");
+
+        let same = MemoryDatabase::diff_files(a.to_str().unwrap(), a.to_str().unwrap()).unwrap();
+        assert!(same.is_empty());
+
+        std::fs::remove_file(a).unwrap();
+        std::fs::remove_file(b).unwrap();
+    }
+
+    #[test]
+    fn test_merge_from_follows_policy() {
+        let mut local = MemoryDatabase::new();
+        local.insert("shared", "same");
+        local.insert("setting", "local");
+        local.insert("local_only", "1");
+        let mut backup = MemoryDatabase::new();
+        backup.insert("shared", "same");
+        backup.insert("setting", "restored");
+        backup.insert("restored_only", "2");
+
+        let diff = local.diff(&backup);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.removed, [("local_only".to_string(), "1".to_string())]);
+
+        let err = local
+            .merge_from(&backup, ConflictPolicy::Abort)
+            .unwrap_err();
+        assert!(matches!(&err, Error::Conflict { keys } if keys == &["setting"]));
+        assert_eq!(local.retrieve("restored_only"), None);
+
+        let mut kept = local.clone();
+        assert_eq!(
+            kept.merge_from(&backup, ConflictPolicy::KeepOurs).unwrap(),
+            1
+        );
+        assert_eq!(kept.retrieve("setting"), Some("local"));
+        assert_eq!(
+            local
+                .merge_from(&backup, ConflictPolicy::TakeTheirs)
+                .unwrap(),
+            2
+        );
+        assert_eq!(local.retrieve("setting"), Some("restored"));
+        assert_eq!(local.len(), 4);
+        assert!(local.diff(&backup).added.is_empty());
+
+        let mut small = MemoryDatabase::with_max_bytes(20, QuotaPolicy::Refuse);
+        small.insert("a", "1");
+        let mut big = MemoryDatabase::new();
+        big.insert("b", "2");
+        big.insert("c", "x".repeat(64));
+        assert!(small.merge_from(&big, ConflictPolicy::KeepOurs).is_err());
+        assert_eq!(small.len(), 1);
+    }
+}
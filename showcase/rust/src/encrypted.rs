@@ -0,0 +1,118 @@
+//! Encryption at rest (used with the `encrypted` feature)
+//!
+//! A file is `HEADER`, a random 12-byte nonce, then the `key:value` lines
+//! sealed with ChaCha20-Poly1305. The whole payload is built in memory, and
+//! the authentication tag rejects a wrong key and any tampering alike.
+use std::fs;
+use std::hash::BuildHasher;
+use std::io::{self, Write};
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+use crate::transfer::Backup;
+use crate::{persist, MemoryDatabase, StrDatabase};
+
+/// Marks an encrypted file and its layout version
+pub const HEADER: &[u8; 5] = b"GVSE\x01";
+
+/// A 256-bit ChaCha20-Poly1305 key
+pub type EncryptionKey = [u8; 32];
+
+const NONCE_LEN: usize = 12;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Saves the live entries to `path`, encrypted with `key`
+    pub fn save_encrypted(&self, path: &str, key: &EncryptionKey) -> io::Result<()> {
+        let mut plain = String::new();
+        for (key, value) in self.iter() {
+            plain.push_str(&persist::encode_line(key, value));
+            plain.push('\n');
+        }
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = ChaCha20Poly1305::new(key.into())
+            .encrypt(&nonce, plain.as_bytes())
+            .map_err(|_| io::Error::other("Encryption failed"))?;
+        let mut bytes = Vec::with_capacity(HEADER.len() + NONCE_LEN + sealed.len());
+        bytes.extend_from_slice(HEADER);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&sealed);
+        persist::write_atomically(path, |mut file| file.write_all(&bytes))
+    }
+}
+
+impl MemoryDatabase {
+    /// Loads data saved by `save_encrypted` with the same `key`
+    ///
+    /// Fails with `InvalidData` when the header is missing, or when the key is
+    /// wrong or the file was modified.
+    pub fn load_encrypted(path: &str, key: &EncryptionKey) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let body = bytes
+            .strip_prefix(HEADER)
+            .filter(|body| body.len() >= NONCE_LEN)
+            .ok_or_else(|| invalid("Not an encrypted database"))?;
+        let (nonce, sealed) = body.split_at(NONCE_LEN);
+        let plain = ChaCha20Poly1305::new(key.into())
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| invalid("Decryption failed: wrong key or corrupted file"))?;
+
+        let mut db = Self::new();
+        db.restore_from(&plain[..])?;
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let path = std::env::temp_dir().join("gvs_encrypted_round_trip.db");
+        let path = path.to_str().unwrap();
+        let key = [7; 32];
+        let mut db = MemoryDatabase::new();
+        db.insert("api:token", "s3cr3t\nvalue");
+        db.save_encrypted(path, &key).unwrap();
+
+        let bytes = fs::read(path).unwrap();
+        assert!(bytes.starts_with(HEADER));
+        assert!(!bytes.windows(6).any(|window| window == b"s3cr3t"));
+
+        let loaded = MemoryDatabase::load_encrypted(path, &key).unwrap();
+        assert_eq!(loaded.retrieve("api:token"), Some("s3cr3t\nvalue"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_key_and_tampering_are_rejected() {
+        let path = std::env::temp_dir().join("gvs_encrypted_rejects.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("a", "1");
+        db.save_encrypted(path, &[1; 32]).unwrap();
+
+        let err = MemoryDatabase::load_encrypted(path, &[2; 32]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decryption failed: wrong key or corrupted file"
+        );
+
+        let mut bytes = fs::read(path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(path, &bytes).unwrap();
+        assert!(MemoryDatabase::load_encrypted(path, &[1; 32]).is_err());
+
+        fs::write(path, "a:1\n").unwrap();
+        let err = MemoryDatabase::load_encrypted(path, &[1; 32]).unwrap_err();
+        assert_eq!(err.to_string(), "Not an encrypted database");
+        std::fs::remove_file(path).unwrap();
+    }
+}
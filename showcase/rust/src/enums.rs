@@ -0,0 +1,228 @@
+//! Wire-level enums: opcodes, database states, exit codes and permission flags
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use crate::DbOperation;
+
+/// One-byte tag identifying an operation in encoded logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum OpCode {
+    Insert = 0x01,
+    Retrieve = 0x02,
+    Delete = 0x03,
+    Update = 0x04,
+    Append = 0x10,
+    Increment = 0x11,
+}
+
+impl OpCode {
+    pub const fn as_byte(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns true for opcodes that modify the database
+    pub const fn is_write(self) -> bool {
+        !matches!(self, OpCode::Retrieve)
+    }
+
+    /// Returns true for opcodes whose result depends on the current value
+    pub const fn is_read_modify_write(self) -> bool {
+        matches!(self, OpCode::Append | OpCode::Increment)
+    }
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x01 => Ok(OpCode::Insert),
+            0x02 => Ok(OpCode::Retrieve),
+            0x03 => Ok(OpCode::Delete),
+            0x04 => Ok(OpCode::Update),
+            0x10 => Ok(OpCode::Append),
+            0x11 => Ok(OpCode::Increment),
+            _ => Err(format!("Unknown opcode: {:#04x}", byte)),
+        }
+    }
+}
+
+impl From<&DbOperation> for OpCode {
+    fn from(op: &DbOperation) -> Self {
+        match op {
+            DbOperation::Insert { .. } => OpCode::Insert,
+            DbOperation::Retrieve { .. } => OpCode::Retrieve,
+            DbOperation::Delete { .. } => OpCode::Delete,
+            DbOperation::Update { .. } => OpCode::Update,
+            DbOperation::Append { .. } => OpCode::Append,
+            DbOperation::Increment { .. } => OpCode::Increment,
+        }
+    }
+}
+
+/// Lifecycle state of a database handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DbState {
+    Open,
+    ReadOnly,
+    Recovering { replayed: u64 },
+    Closed,
+}
+
+impl DbState {
+    pub const fn accepts_reads(self) -> bool {
+        matches!(self, DbState::Open | DbState::ReadOnly)
+    }
+
+    pub const fn accepts_writes(self) -> bool {
+        matches!(self, DbState::Open)
+    }
+
+    /// Checks that `op` may run in this state
+    pub fn admit(self, op: &DbOperation) -> Result<(), String> {
+        let code = OpCode::from(op);
+        match self {
+            _ if code.is_write() && self.accepts_writes() => Ok(()),
+            _ if !code.is_write() && self.accepts_reads() => Ok(()),
+            DbState::Recovering { replayed } => Err(format!(
+                "Database is recovering ({} entries replayed)",
+                replayed
+            )),
+            other => Err(format!("Database is {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for DbState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbState::Open => f.write_str("open"),
+            DbState::ReadOnly => f.write_str("read-only"),
+            DbState::Recovering { .. } => f.write_str("recovering"),
+            DbState::Closed => f.write_str("closed"),
+        }
+    }
+}
+
+/// Process exit codes, following BSD `sysexits.h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ExitCode {
+    Ok = 0,
+    Usage = 64,
+    DataErr = 65,
+    NoInput = 66,
+    IoErr = 74,
+    TempFail = 75,
+}
+
+impl ExitCode {
+    pub const fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Set of operations a client may run, stored as bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OpFlags(u8);
+
+impl OpFlags {
+    pub const NONE: OpFlags = OpFlags(0);
+    pub const READ: OpFlags = OpFlags(1 << 0);
+    pub const WRITE: OpFlags = OpFlags(1 << 1);
+    pub const DELETE: OpFlags = OpFlags(1 << 2);
+    pub const ALL: OpFlags = OpFlags(Self::READ.0 | Self::WRITE.0 | Self::DELETE.0);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Builds flags from raw bits, dropping any unknown ones
+    pub const fn from_bits_truncate(bits: u8) -> Self {
+        OpFlags(bits & Self::ALL.0)
+    }
+
+    pub const fn contains(self, other: OpFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Flags needed to run `op`
+    pub fn required_for(op: &DbOperation) -> Self {
+        match OpCode::from(op) {
+            OpCode::Retrieve => Self::READ,
+            OpCode::Delete => Self::DELETE,
+            code if code.is_read_modify_write() => Self::READ | Self::WRITE,
+            _ => Self::WRITE,
+        }
+    }
+}
+
+impl BitOr for OpFlags {
+    type Output = OpFlags;
+
+    fn bitor(self, rhs: OpFlags) -> OpFlags {
+        OpFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for OpFlags {
+    fn bitor_assign(&mut self, rhs: OpFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for OpFlags {
+    type Output = OpFlags;
+
+    fn bitand(self, rhs: OpFlags) -> OpFlags {
+        OpFlags(self.0 & rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_round_trip() {
+        let append = DbOperation::Append {
+            key: "log".into(),
+            value: "line".into(),
+        };
+        let code = OpCode::from(&append);
+        assert_eq!(code.as_byte(), 0x10);
+        assert_eq!(OpCode::try_from(0x10), Ok(code));
+        assert!(code.is_write() && code.is_read_modify_write());
+        assert!(!OpCode::Retrieve.is_write());
+        assert_eq!(
+            OpCode::try_from(0xff),
+            Err("Unknown opcode: 0xff".to_string())
+        );
+        assert_eq!(ExitCode::DataErr.code(), 65);
+    }
+
+    #[test]
+    fn test_state_and_flags() {
+        let read = DbOperation::Retrieve { key: "k".into() };
+        let incr = DbOperation::Increment {
+            key: "k".into(),
+            by: 1,
+        };
+        assert!(DbState::ReadOnly.admit(&read).is_ok());
+        assert_eq!(
+            DbState::ReadOnly.admit(&incr),
+            Err("Database is read-only".to_string())
+        );
+        assert!(DbState::Recovering { replayed: 3 }.admit(&read).is_err());
+
+        let mut granted = OpFlags::READ;
+        assert!(!granted.contains(OpFlags::required_for(&incr)));
+        granted |= OpFlags::WRITE;
+        assert!(granted.contains(OpFlags::required_for(&incr)));
+        assert_eq!(OpFlags::from_bits_truncate(0xff), OpFlags::ALL);
+        assert_eq!((OpFlags::ALL & OpFlags::DELETE).bits(), 0b100);
+    }
+}
@@ -0,0 +1,103 @@
+//! Error type returned by long-running services, bounded databases, loads
+//! and operations
+use std::fmt;
+use std::io;
+
+/// Why a `Service` failed to start or stop, a bounded or policed database
+/// refused a write, a saved file failed verification, or a `DbOperation`
+/// could not run
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// `start` was called on a service that is already running
+    AlreadyRunning(&'static str),
+    /// A background worker panicked before it could be joined
+    WorkerPanicked(&'static str),
+    /// A write would take a database past its byte limit
+    OverQuota {
+        needed: usize,
+        limit: usize,
+    },
+    /// A saved line failed its checksum; `offset` is where the line starts
+    Corrupted {
+        line: usize,
+        offset: u64,
+    },
+    /// A `BeforeInsert` hook refused the write, for the reason given
+    Rejected(String),
+    /// A merge found keys both sides hold with different values
+    Conflict {
+        keys: Vec<String>,
+    },
+    /// A write would take a bucket past its entry limit
+    BucketFull {
+        bucket: String,
+        limit: usize,
+    },
+    /// A write would take a bucket past its byte limit
+    BucketOverQuota {
+        bucket: String,
+        needed: usize,
+        limit: usize,
+    },
+    /// A key policy rejected the key, for the reason given
+    InvalidKey {
+        key: String,
+        reason: String,
+    },
+    /// An operation needed a key that isn't stored
+    NotFound(String),
+    /// `Increment` found a value that isn't an integer
+    NotAnInteger(String),
+    /// `Increment` would take the value past `i64`
+    Overflow(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::AlreadyRunning(name) => write!(f, "{} already running", name),
+            Error::WorkerPanicked(name) => write!(f, "{} thread panicked", name),
+            Error::OverQuota { needed, limit } => {
+                write!(f, "Over quota: needs {} bytes, limit is {}", needed, limit)
+            }
+            Error::Corrupted { line, offset } => {
+                write!(f, "Checksum mismatch on line {} at byte {}", line, offset)
+            }
+            Error::Rejected(reason) => write!(f, "Rejected: {}", reason),
+            Error::Conflict { keys } => write!(f, "Conflicting keys: {}", keys.join(", ")),
+            Error::BucketFull { bucket, limit } => {
+                write!(f, "Bucket {:?} is full: limit is {} entries", bucket, limit)
+            }
+            Error::BucketOverQuota {
+                bucket,
+                needed,
+                limit,
+            } => write!(
+                f,
+                "Bucket {:?} over quota: needs {} bytes, limit is {}",
+                bucket, needed, limit
+            ),
+            Error::InvalidKey { key, reason } => write!(f, "Invalid key {:?}: {}", key, reason),
+            Error::NotFound(key) => write!(f, "Key not found: {}", key),
+            Error::NotAnInteger(key) => write!(f, "Not an integer: {}", key),
+            Error::Overflow(key) => write!(f, "Increment overflows: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,201 @@
+//! Bounded databases that evict entries when full
+//!
+//! Reads only hold `&self`, so recency is tracked behind a `Mutex` that is
+//! only touched in bounded mode; unbounded databases pay nothing for it.
+//! Clones and snapshots of a bounded database copy its recency order.
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::MemoryDatabase;
+
+/// Which entry a full database gives up to make room for a new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvictionPolicy {
+    /// Drop the entry that was read or written longest ago
+    Lru,
+}
+
+#[derive(Debug, Clone)]
+struct Recency<K> {
+    clock: u64,
+    order: BTreeMap<u64, K>,
+    stamps: HashMap<K, u64>,
+}
+
+/// Least-recently-used order of the keys in a bounded database
+#[derive(Debug)]
+pub(crate) struct Lru<K> {
+    pub(crate) max_entries: usize,
+    recency: Mutex<Recency<K>>,
+}
+
+impl<K: Clone> Clone for Lru<K> {
+    fn clone(&self) -> Self {
+        Self {
+            max_entries: self.max_entries,
+            recency: Mutex::new(self.lock().clone()),
+        }
+    }
+}
+
+impl<K> Lru<K> {
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            recency: Mutex::new(Recency {
+                clock: 0,
+                order: BTreeMap::new(),
+                stamps: HashMap::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Recency<K>> {
+        self.recency.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<K: Hash + Eq + Clone> Lru<K> {
+    /// Marks `key` as the most recently used, if it is tracked
+    pub(crate) fn touch<Q>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut recency = self.lock();
+        let Recency {
+            clock,
+            order,
+            stamps,
+        } = &mut *recency;
+        if let Some(stamp) = stamps.get_mut(key) {
+            *clock += 1;
+            if let Some(key) = order.remove(stamp) {
+                order.insert(*clock, key);
+            }
+            *stamp = *clock;
+        }
+    }
+
+    /// Tracks `key` as the most recently used
+    pub(crate) fn insert(&self, key: K) {
+        let mut recency = self.lock();
+        recency.clock += 1;
+        let stamp = recency.clock;
+        if let Some(old) = recency.stamps.insert(key.clone(), stamp) {
+            recency.order.remove(&old);
+        }
+        recency.order.insert(stamp, key);
+    }
+
+    pub(crate) fn forget<Q>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut recency = self.lock();
+        if let Some(stamp) = recency.stamps.remove(key) {
+            recency.order.remove(&stamp);
+        }
+    }
+
+    /// Stops tracking and returns the least recently used key
+    pub(crate) fn pop_oldest(&self) -> Option<K> {
+        let mut recency = self.lock();
+        let (_, key) = recency.order.pop_first()?;
+        recency.stamps.remove(&key);
+        Some(key)
+    }
+}
+
+impl<K, V> MemoryDatabase<K, V> {
+    /// Creates a database holding at most `max_entries`, evicting by `policy`
+    pub fn with_capacity_policy(max_entries: usize, policy: EvictionPolicy) -> Self {
+        let eviction = match policy {
+            EvictionPolicy::Lru => Lru::new(max_entries),
+        };
+        Self {
+            eviction: Some(eviction),
+            ..Self::default()
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Most entries a bounded database keeps, or `None` when unbounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.eviction.as_ref().map(|lru| lru.max_entries)
+    }
+
+    /// Evicts entries until the database is back within its capacity and byte limit
+    pub(crate) fn evict(&mut self) {
+        while self.over_limits() {
+            let Some(key) = self.eviction.as_ref().and_then(Lru::pop_oldest) else {
+                break;
+            };
+            self.take_entry(&key);
+        }
+    }
+
+    fn over_limits(&self) -> bool {
+        let Some(lru) = &self.eviction else {
+            return false;
+        };
+        let over_bytes = self
+            .quota
+            .as_ref()
+            .is_some_and(|quota| quota.used > quota.max_bytes);
+        self.store.len() > lru.max_entries || over_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut db = MemoryDatabase::with_capacity_policy(2, EvictionPolicy::Lru);
+        db.insert("a", "1");
+        db.insert("b", "2");
+        assert_eq!(db.retrieve("a"), Some("1"));
+
+        db.insert("c", "3");
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.retrieve("b"), None);
+        assert_eq!(db.retrieve("a"), Some("1"));
+
+        db.insert("c", "updated");
+        db.insert("d", "4");
+        assert_eq!(db.retrieve("a"), None);
+        assert_eq!(db.retrieve("c"), Some("updated"));
+        assert_eq!(db.capacity(), Some(2));
+    }
+
+    #[test]
+    fn test_removed_keys_are_not_evicted_later() {
+        let mut db: MemoryDatabase<u32, u32> =
+            MemoryDatabase::with_capacity_policy(2, EvictionPolicy::Lru);
+        db.put(1, 10);
+        db.put(2, 20);
+        db.remove(&1);
+        db.put(3, 30);
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.get(&2), Some(&20));
+
+        let snapshot = db.clone();
+        db.put(4, 40);
+        assert_eq!(db.get(&3), None);
+        assert_eq!(snapshot.get(&3), Some(&30));
+        assert_eq!(MemoryDatabase::new().capacity(), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Stable C ABI over `MemoryDatabase`
+//!
+//! The matching header lives in `include/gvs.h` and is regenerated with
+//! `cbindgen --config cbindgen.toml --output include/gvs.h src/ffi.rs`.
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+
+use crate::{Database, MemoryDatabase};
+
+/// Longest key, in bytes, accepted by the C API
+pub const GVS_MAX_KEY_LEN: usize = 1024;
+
+extern "C" {
+    fn strnlen(s: *const c_char, maxlen: usize) -> usize;
+}
+
+/// Status codes returned by every `gvs_*` function
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GvsStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    NotFound = 3,
+    Io = 4,
+    KeyTooLong = 5,
+    InteriorNul = 6,
+    Refused = 7,
+}
+
+/// Size summary filled in by `gvs_db_stats`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GvsStats {
+    pub entries: usize,
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+}
+
+/// Called once per entry by `gvs_db_foreach`; returning non-zero stops the walk
+pub type GvsVisitor = Option<
+    extern "C" fn(key: *const c_char, value: *const c_char, user_data: *mut c_void) -> c_int,
+>;
+
+/// Opaque database handle handed out to C callers
+pub struct GvsDb {
+    db: MemoryDatabase,
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<&'a str, GvsStatus> {
+    if ptr.is_null() {
+        return Err(GvsStatus::NullPointer);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| GvsStatus::InvalidUtf8)
+}
+
+unsafe fn key_arg<'a>(ptr: *const c_char) -> Result<&'a str, GvsStatus> {
+    // bound the scan so an unterminated buffer can't be walked past the limit
+    if !ptr.is_null() && strnlen(ptr, GVS_MAX_KEY_LEN + 1) > GVS_MAX_KEY_LEN {
+        return Err(GvsStatus::KeyTooLong);
+    }
+    str_arg(ptr)
+}
+
+/// Opens a database, loading `path` when it is not NULL
+///
+/// # Safety
+///
+/// `path` must be NULL or a valid NUL-terminated string and `out` must be a
+/// valid pointer. The handle written to `out` must be released with
+/// `gvs_db_close`.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_open(path: *const c_char, out: *mut *mut GvsDb) -> GvsStatus {
+    if out.is_null() {
+        return GvsStatus::NullPointer;
+    }
+    let db = if path.is_null() {
+        MemoryDatabase::new()
+    } else {
+        let path = match str_arg(path) {
+            Ok(path) => path,
+            Err(status) => return status,
+        };
+        match MemoryDatabase::load_from_file(path) {
+            Ok(db) => db,
+            Err(_) => return GvsStatus::Io,
+        }
+    };
+    *out = Box::into_raw(Box::new(GvsDb { db }));
+    GvsStatus::Ok
+}
+
+/// Persists the database to `path`
+///
+/// # Safety
+///
+/// `db` must come from `gvs_db_open` and `path` must be a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_save(db: *const GvsDb, path: *const c_char) -> GvsStatus {
+    let Some(handle) = db.as_ref() else {
+        return GvsStatus::NullPointer;
+    };
+    let path = match str_arg(path) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+    match handle.db.save_to_file(path) {
+        Ok(()) => GvsStatus::Ok,
+        Err(_) => GvsStatus::Io,
+    }
+}
+
+/// Looks up `key`, writing a newly allocated copy of the value to `out`
+///
+/// Values holding a NUL byte can't be returned as C strings and report
+/// `InteriorNul`.
+///
+/// # Safety
+///
+/// `db` must come from `gvs_db_open`, `key` must be a valid NUL-terminated
+/// string and `out` a valid pointer. The returned string must be released
+/// with `gvs_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_get(
+    db: *const GvsDb,
+    key: *const c_char,
+    out: *mut *mut c_char,
+) -> GvsStatus {
+    let Some(handle) = db.as_ref() else {
+        return GvsStatus::NullPointer;
+    };
+    if out.is_null() {
+        return GvsStatus::NullPointer;
+    }
+    let key = match key_arg(key) {
+        Ok(key) => key,
+        Err(status) => return status,
+    };
+    match handle.db.retrieve(key) {
+        Some(value) => match CString::new(value) {
+            Ok(value) => {
+                *out = value.into_raw();
+                GvsStatus::Ok
+            }
+            Err(_) => {
+                *out = ptr::null_mut();
+                GvsStatus::InteriorNul
+            }
+        },
+        None => {
+            *out = ptr::null_mut();
+            GvsStatus::NotFound
+        }
+    }
+}
+
+/// Inserts or replaces `key` with `value`
+///
+/// Returns `Refused` and changes nothing when a quota, key policy or hook
+/// turns the write down.
+///
+/// # Safety
+///
+/// `db` must come from `gvs_db_open` and both strings must be valid and
+/// NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_set(
+    db: *mut GvsDb,
+    key: *const c_char,
+    value: *const c_char,
+) -> GvsStatus {
+    let Some(handle) = db.as_mut() else {
+        return GvsStatus::NullPointer;
+    };
+    let (key, value) = match (key_arg(key), str_arg(value)) {
+        (Ok(key), Ok(value)) => (key, value),
+        (Err(status), _) | (_, Err(status)) => return status,
+    };
+    match handle.db.try_insert(key, value) {
+        Ok(()) => GvsStatus::Ok,
+        Err(_) => GvsStatus::Refused,
+    }
+}
+
+/// Writes live entry and byte counts for the database to `out`
+///
+/// # Safety
+///
+/// `db` must come from `gvs_db_open` and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_stats(db: *const GvsDb, out: *mut GvsStats) -> GvsStatus {
+    let (Some(handle), Some(out)) = (db.as_ref(), out.as_mut()) else {
+        return GvsStatus::NullPointer;
+    };
+    *out = handle
+        .db
+        .iter()
+        .fold(GvsStats::default(), |stats, (key, value)| GvsStats {
+            entries: stats.entries + 1,
+            key_bytes: stats.key_bytes + key.len(),
+            value_bytes: stats.value_bytes + value.len(),
+        });
+    GvsStatus::Ok
+}
+
+/// Calls `visitor` with every live entry, in unspecified order
+///
+/// The strings passed to `visitor` are only valid for the duration of the
+/// call. Entries containing NUL bytes are skipped.
+///
+/// # Safety
+///
+/// `db` must come from `gvs_db_open`; `user_data` is passed through untouched.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_foreach(
+    db: *const GvsDb,
+    visitor: GvsVisitor,
+    user_data: *mut c_void,
+) -> GvsStatus {
+    let (Some(handle), Some(visitor)) = (db.as_ref(), visitor) else {
+        return GvsStatus::NullPointer;
+    };
+    for (key, value) in handle.db.iter() {
+        let (Ok(key), Ok(value)) = (CString::new(key), CString::new(value)) else {
+            continue;
+        };
+        if visitor(key.as_ptr(), value.as_ptr(), user_data) != 0 {
+            break;
+        }
+    }
+    GvsStatus::Ok
+}
+
+/// Releases a handle returned by `gvs_db_open`
+///
+/// # Safety
+///
+/// `db` must be NULL or a handle that has not been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_db_close(db: *mut GvsDb) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
+}
+
+/// Releases a string returned by `gvs_db_get`
+///
+/// # Safety
+///
+/// `value` must be NULL or a string returned by `gvs_db_get` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn gvs_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quota::QuotaPolicy;
+
+    #[test]
+    fn test_set_then_get() {
+        let key = CString::new("language").unwrap();
+        let value = CString::new("Rust").unwrap();
+
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(gvs_db_open(ptr::null(), &mut db), GvsStatus::Ok);
+            assert_eq!(gvs_db_set(db, key.as_ptr(), value.as_ptr()), GvsStatus::Ok);
+
+            let mut out = ptr::null_mut();
+            assert_eq!(gvs_db_get(db, key.as_ptr(), &mut out), GvsStatus::Ok);
+            assert_eq!(CStr::from_ptr(out).to_str(), Ok("Rust"));
+
+            gvs_string_free(out);
+            gvs_db_close(db);
+        }
+    }
+
+    #[test]
+    fn test_missing_key_and_null_handle() {
+        let key = CString::new("missing").unwrap();
+
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(gvs_db_open(ptr::null(), &mut db), GvsStatus::Ok);
+
+            let mut out = ptr::null_mut();
+            assert_eq!(gvs_db_get(db, key.as_ptr(), &mut out), GvsStatus::NotFound);
+            assert!(out.is_null());
+            assert_eq!(
+                gvs_db_get(ptr::null(), key.as_ptr(), &mut out),
+                GvsStatus::NullPointer
+            );
+
+            gvs_db_close(db);
+        }
+    }
+
+    #[test]
+    fn test_refused_writes_and_nul_values() {
+        let key = CString::new("big").unwrap();
+        let value = CString::new("0123456789").unwrap();
+
+        unsafe {
+            let db = Box::into_raw(Box::new(GvsDb {
+                db: MemoryDatabase::with_max_bytes(8, QuotaPolicy::Refuse),
+            }));
+            assert_eq!(
+                gvs_db_set(db, key.as_ptr(), value.as_ptr()),
+                GvsStatus::Refused
+            );
+            assert!((*db).db.is_empty());
+
+            (*db).db = MemoryDatabase::new();
+            (*db).db.insert("big", "nul\0inside");
+            let mut out = ptr::null_mut();
+            assert_eq!(
+                gvs_db_get(db, key.as_ptr(), &mut out),
+                GvsStatus::InteriorNul
+            );
+            assert!(out.is_null());
+
+            gvs_db_close(db);
+        }
+    }
+
+    extern "C" fn collect(
+        key: *const c_char,
+        value: *const c_char,
+        user_data: *mut c_void,
+    ) -> c_int {
+        let seen = unsafe { &mut *user_data.cast::<Vec<String>>() };
+        let (key, value) = unsafe { (CStr::from_ptr(key), CStr::from_ptr(value)) };
+        seen.push(format!(
+            "{}={}",
+            key.to_string_lossy(),
+            value.to_string_lossy()
+        ));
+        0
+    }
+
+    #[test]
+    fn test_stats_foreach_and_key_limit() {
+        let key = CString::new("language").unwrap();
+        let value = CString::new("Rust").unwrap();
+        let long_key = CString::new("k".repeat(GVS_MAX_KEY_LEN + 1)).unwrap();
+
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(gvs_db_open(ptr::null(), &mut db), GvsStatus::Ok);
+            assert_eq!(gvs_db_set(db, key.as_ptr(), value.as_ptr()), GvsStatus::Ok);
+            assert_eq!(
+                gvs_db_set(db, long_key.as_ptr(), value.as_ptr()),
+                GvsStatus::KeyTooLong
+            );
+            (*db)
+                .db
+                .insert_with_ttl("expired", "x", std::time::Duration::ZERO);
+
+            let mut stats = GvsStats::default();
+            assert_eq!(gvs_db_stats(db, &mut stats), GvsStatus::Ok);
+            assert_eq!(
+                stats,
+                GvsStats {
+                    entries: 1,
+                    key_bytes: 8,
+                    value_bytes: 4
+                }
+            );
+
+            let mut seen: Vec<String> = Vec::new();
+            let user_data = (&mut seen as *mut Vec<String>).cast::<c_void>();
+            assert_eq!(gvs_db_foreach(db, Some(collect), user_data), GvsStatus::Ok);
+            assert_eq!(seen, ["language=Rust"]);
+
+            gvs_db_close(db);
+        }
+    }
+}
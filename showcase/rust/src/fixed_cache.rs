@@ -0,0 +1,176 @@
+//! Fixed-capacity read cache sized at compile time
+//!
+//! The slots live in an inline array, so a `FixedCache<N>` never allocates
+//! beyond the keys and values it holds, and lookups are a linear scan that
+//! stays in cache for the small `N` it is meant for.
+use std::sync::Arc;
+
+use crate::Database;
+
+struct Slot {
+    key: Arc<str>,
+    value: Arc<str>,
+    last_used: u64,
+}
+
+/// Least-recently-used cache holding at most `N` entries
+pub struct FixedCache<const N: usize> {
+    slots: [Option<Slot>; N],
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<const N: usize> FixedCache<N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "FixedCache needs at least one slot") };
+        Self {
+            slots: [const { None }; N],
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub const fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub const fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Arc<str>> {
+        let now = self.tick();
+        let slot = self
+            .slots
+            .iter_mut()
+            .flatten()
+            .find(|slot| &*slot.key == key);
+        match slot {
+            Some(slot) => {
+                slot.last_used = now;
+                self.hits += 1;
+                Some(Arc::clone(&slot.value))
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores `value`, evicting the least recently used entry when full
+    pub fn insert(&mut self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        let key = key.into();
+        let slot = Slot {
+            key,
+            value: value.into(),
+            last_used: self.tick(),
+        };
+
+        let index = self
+            .slots
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| s.key == slot.key))
+            .or_else(|| self.slots.iter().position(Option::is_none))
+            .unwrap_or_else(|| self.least_recently_used());
+        self.slots[index] = Some(slot);
+    }
+
+    fn least_recently_used(&self) -> usize {
+        (0..N)
+            .min_by_key(|&i| self.slots[i].as_ref().map_or(0, |slot| slot.last_used))
+            .unwrap_or(0)
+    }
+
+    pub fn invalidate(&mut self, key: &str) -> bool {
+        let index = self
+            .slots
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| &*s.key == key));
+        index.map(|i| self.slots[i].take()).is_some()
+    }
+
+    /// Serves `key` from the cache, falling back to `db` and caching the result
+    pub fn retrieve_through<D: Database>(&mut self, db: &D, key: &str) -> Option<Arc<str>> {
+        if let Some(value) = self.get(key) {
+            return Some(value);
+        }
+        let value: Arc<str> = db.retrieve(key)?.into();
+        self.insert(key, Arc::clone(&value));
+        Some(value)
+    }
+}
+
+impl<const N: usize> Default for FixedCache<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cache for the handful of hot keys a config-style workload touches
+pub type HotKeyCache = FixedCache<8>;
+
+/// Built entirely at compile time by the `const fn` constructor
+static EMPTY_HOT_KEYS: HotKeyCache = FixedCache::new();
+
+/// Capacity of the default hot-key cache, known without constructing one
+pub const HOT_KEY_CAPACITY: usize = EMPTY_HOT_KEYS.capacity();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = FixedCache::<2>::new();
+        cache.insert("a", "1");
+        cache.insert("b", "2");
+        assert_eq!(cache.get("a").as_deref(), Some("1"));
+
+        cache.insert("c", "3");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a").as_deref(), Some("1"));
+        assert!(cache.invalidate("c"));
+        assert!(!cache.invalidate("c"));
+        assert_eq!(HOT_KEY_CAPACITY, 8);
+    }
+
+    #[test]
+    fn test_retrieve_through_database() {
+        let mut db = MemoryDatabase::new();
+        db.insert("language", "Rust");
+        let mut cache = HotKeyCache::default();
+
+        assert_eq!(
+            cache.retrieve_through(&db, "language").as_deref(),
+            Some("Rust")
+        );
+        assert_eq!(
+            cache.retrieve_through(&db, "language").as_deref(),
+            Some("Rust")
+        );
+        assert_eq!(cache.retrieve_through(&db, "missing"), None);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+    }
+}
@@ -0,0 +1,84 @@
+//! Near-miss key lookups by edit distance
+//!
+//! `retrieve_fuzzy` scans every key, skipping those whose length alone puts
+//! them out of range, so it suits interactive lookups rather than hot paths.
+use std::hash::BuildHasher;
+
+use crate::StrDatabase;
+
+/// Levenshtein distance between `a` and `b`, counted in chars
+///
+/// Case is ignored, so `"Key"` and `"key"` are at distance 0.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, &ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// The closest key found by `retrieve_fuzzy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyMatch<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    /// Case-insensitive edit distance from the requested key
+    pub distance: usize,
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Live entry whose key is closest to `key`, if within `max_distance` edits
+    ///
+    /// Ties prefer the key matching `key`'s case, then the smaller key.
+    pub fn retrieve_fuzzy(&self, key: &str, max_distance: usize) -> Option<FuzzyMatch<'_>> {
+        let length = key.chars().count();
+        self.iter()
+            .filter(|(candidate, _)| candidate.chars().count().abs_diff(length) <= max_distance)
+            .map(|(candidate, value)| FuzzyMatch {
+                key: candidate,
+                value,
+                distance: edit_distance(key, candidate),
+            })
+            .filter(|found| found.distance <= max_distance)
+            .min_by_key(|found| (found.distance, found.key != key, found.key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("User", "user"), 0);
+        assert_eq!(edit_distance("naïve", "naive"), 1);
+    }
+
+    #[test]
+    fn test_retrieve_fuzzy_picks_closest() {
+        let mut db = MemoryDatabase::new();
+        db.insert("username", "alice");
+        db.insert("usernames", "list");
+        db.insert("hostname", "db01");
+
+        let found = db.retrieve_fuzzy("usrname", 2).unwrap();
+        assert_eq!(
+            (found.key, found.value, found.distance),
+            ("username", "alice", 1)
+        );
+        assert_eq!(db.retrieve_fuzzy("HOSTNAME", 0).unwrap().value, "db01");
+        assert_eq!(db.retrieve_fuzzy("usernames", 3).unwrap().distance, 0);
+        assert_eq!(db.retrieve_fuzzy("port", 2), None);
+    }
+}
@@ -0,0 +1,36 @@
+//! Constants generated by `build.rs` from `data/defaults.db`
+use crate::{Database, MemoryDatabase};
+
+include!(concat!(env!("OUT_DIR"), "/defaults.rs"));
+
+impl MemoryDatabase {
+    /// A database holding `DEFAULT_ENTRIES`
+    pub fn with_defaults() -> Self {
+        let mut db = Self::new();
+        for &(key, value) in DEFAULT_ENTRIES {
+            db.insert(key, value);
+        }
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServerConfig;
+
+    #[test]
+    fn test_defaults_match_server_config() {
+        let db = MemoryDatabase::with_defaults();
+        let config = ServerConfig::default();
+
+        assert_eq!(db.retrieve("server.host"), Some(&*config.host));
+        assert_eq!(db.retrieve("server.port"), Some(&*config.port.to_string()));
+        assert_eq!(
+            db.retrieve("server.timeout_ms"),
+            Some(&*config.timeout_ms.to_string())
+        );
+        assert!(VERSION.split('.').all(|part| part.parse::<u32>().is_ok()));
+        assert!(!PROFILE.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Key selection by glob pattern, or by regex with the `regex` feature
+//!
+//! Globs support `*` for any run of characters, `?` for exactly one, and
+//! `\` to match the next character literally.
+use std::hash::BuildHasher;
+
+use crate::StrDatabase;
+
+/// Returns true when the whole of `text` matches `pattern`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the last `*` seen, and the text position it currently absorbs up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            Some('\\') if pattern.get(p + 1) == Some(&text[t]) => {
+                p += 2;
+                t += 1;
+                continue;
+            }
+            Some(&c) if c != '\\' && c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match star {
+            Some((star_p, star_t)) => {
+                star = Some((star_p, star_t + 1));
+                p = star_p + 1;
+                t = star_t + 1;
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Live keys matching the glob `pattern`, sorted
+    pub fn keys_matching(&self, pattern: &str) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| glob_match(pattern, key))
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Live keys `regex` finds a match in, sorted
+    #[cfg(feature = "regex")]
+    pub fn keys_matching_regex(&self, regex: &regex::Regex) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| regex.is_match(key))
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Removes every entry whose key matches the glob `pattern`, returning how many
+    pub fn remove_matching(&mut self, pattern: &str) -> usize {
+        let keys: Vec<_> = self
+            .store
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| glob_match(pattern, key))
+            .cloned()
+            .collect();
+        keys.iter()
+            .filter(|key| self.remove(&***key).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("user:*", "user:1"));
+        assert!(glob_match("user:*", "user:"));
+        assert!(glob_match("*:name", "user:1:name"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(glob_match("log-??", "log-01"));
+        assert!(!glob_match("log-??", "log-1"));
+        assert!(glob_match("what\\?", "what?"));
+        assert!(!glob_match("what\\?", "whatz"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("é?", "éa"));
+    }
+
+    #[test]
+    fn test_keys_matching_and_bulk_remove() {
+        let mut db = MemoryDatabase::new();
+        for key in ["session:1", "session:2", "user:1", "sessions"] {
+            db.insert(key, "x");
+        }
+        assert_eq!(db.keys_matching("session:*"), ["session:1", "session:2"]);
+        assert_eq!(db.keys_matching("*:1"), ["session:1", "user:1"]);
+
+        #[cfg(feature = "regex")]
+        {
+            let regex = regex::Regex::new(r"^session:\d$").unwrap();
+            assert_eq!(db.keys_matching_regex(&regex), ["session:1", "session:2"]);
+        }
+
+        assert_eq!(db.remove_matching("session*"), 3);
+        assert_eq!(db.keys_matching("*"), ["user:1"]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Gzip-compressed persistence (used with the `gzip` feature)
+//!
+//! The decompressed stream is exactly what `save_to_file` writes, checksums
+//! included, so `gunzip` yields a file `load_from_file` accepts.
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufReader, BufWriter, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::transfer::Backup;
+use crate::{persist, MemoryDatabase, StrDatabase};
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Streams the live entries to `path` through a gzip encoder
+    pub fn save_compressed(&self, path: &str) -> io::Result<()> {
+        persist::write_atomically(path, |file| {
+            let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+            for (key, value) in self.iter() {
+                let line = persist::with_checksum(&persist::encode_line(key, value));
+                encoder.write_all(line.as_bytes())?;
+                encoder.write_all(b"\n")?;
+            }
+            encoder.finish()?.flush()
+        })
+    }
+}
+
+impl MemoryDatabase {
+    /// Loads data saved by `save_compressed`, decompressing as it reads
+    ///
+    /// Fails when the file isn't a complete gzip stream, and with
+    /// `InvalidData` on a malformed or corrupted line.
+    pub fn load_compressed(path: &str) -> io::Result<Self> {
+        let decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+        let mut db = Self::new();
+        db.restore_from(BufReader::new(decoder))?;
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+    use std::io::Read;
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let path = std::env::temp_dir().join("gvs_gzip_round_trip.db.gz");
+        let path = path.to_str().unwrap();
+        let db: MemoryDatabase = (0..1000)
+            .map(|i| (format!("user:{}", i), "the same value again"))
+            .collect();
+
+        db.save_compressed(path).unwrap();
+        let compressed = std::fs::metadata(path).unwrap().len();
+        let mut plain = String::new();
+        GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut plain)
+            .unwrap();
+        assert!(compressed * 2 < plain.len() as u64);
+
+        let loaded = MemoryDatabase::load_compressed(path).unwrap();
+        assert_eq!(loaded.len(), 1000);
+        assert_eq!(loaded.retrieve("user:42"), Some("the same value again"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_uncompressed_input() {
+        let path = std::env::temp_dir().join("gvs_gzip_plain.db");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "a:1\n").unwrap();
+        assert!(MemoryDatabase::load_compressed(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
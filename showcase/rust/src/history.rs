@@ -0,0 +1,154 @@
+//! Bounded per-key history of written values
+//!
+//! Once `enable_history` is called, every write records the new value with
+//! a timestamp and a version number counting up from 1 for its key. Only
+//! the newest `max_versions` are kept. History outlives removal, so the
+//! values of a deleted key can still be read back with `retrieve_at`.
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::time::SystemTime;
+
+use crate::MemoryDatabase;
+
+/// One recorded write of a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version<'a, V> {
+    pub number: u64,
+    pub written: SystemTime,
+    pub value: &'a V,
+}
+
+#[derive(Debug, Clone)]
+struct Versions<V> {
+    next: u64,
+    kept: VecDeque<(u64, SystemTime, V)>,
+}
+
+/// Recent versions of every key written since history was enabled
+#[derive(Debug, Clone)]
+pub(crate) struct History<K, V> {
+    max_versions: usize,
+    keys: HashMap<K, Versions<V>>,
+}
+
+impl<K: Hash + Eq, V> History<K, V> {
+    pub(crate) fn record(&mut self, key: K, value: V) {
+        let versions = self.keys.entry(key).or_insert_with(|| Versions {
+            next: 1,
+            kept: VecDeque::new(),
+        });
+        if versions.kept.len() == self.max_versions {
+            versions.kept.pop_front();
+        }
+        versions
+            .kept
+            .push_back((versions.next, SystemTime::now(), value));
+        versions.next += 1;
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Keeps the last `max_versions` values written to each key
+    ///
+    /// Entries already stored become version 1 of their key. Calling this
+    /// again changes the bound, trimming longer histories.
+    pub fn enable_history(&mut self, max_versions: usize) {
+        let max_versions = max_versions.max(1);
+        let mut history = self.history.take().unwrap_or_else(|| History {
+            max_versions,
+            keys: HashMap::new(),
+        });
+        history.max_versions = max_versions;
+        for versions in history.keys.values_mut() {
+            let excess = versions.kept.len().saturating_sub(max_versions);
+            versions.kept.drain(..excess);
+        }
+        for (key, value) in self.live_entries() {
+            if !history.keys.contains_key(key) {
+                history.record(key.clone(), value.clone());
+            }
+        }
+        self.history = Some(history);
+    }
+
+    /// Kept versions of `key`, oldest first
+    pub fn history<Q>(&self, key: &Q) -> Vec<Version<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(versions) = self.history.as_ref().and_then(|h| h.keys.get(key)) else {
+            return Vec::new();
+        };
+        versions
+            .kept
+            .iter()
+            .map(|(number, written, value)| Version {
+                number: *number,
+                written: *written,
+                value,
+            })
+            .collect()
+    }
+
+    /// The value `key` held at `version`, if that version is still kept
+    pub fn retrieve_at<Q>(&self, key: &Q, version: u64) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let versions = self.history.as_ref()?.keys.get(key)?;
+        let oldest = versions.kept.front()?.0;
+        let (_, _, value) = versions.kept.get(version.checked_sub(oldest)? as usize)?;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_history_keeps_recent_versions() {
+        let mut db = MemoryDatabase::new();
+        db.insert("config", "v1");
+        db.enable_history(3);
+        for value in ["v2", "v3", "v4"] {
+            db.insert("config", value);
+        }
+
+        let history = db.history("config");
+        let numbers: Vec<u64> = history.iter().map(|v| v.number).collect();
+        assert_eq!(numbers, [2, 3, 4]);
+        assert!(history[0].written <= history[2].written);
+        assert_eq!(db.retrieve_at("config", 3).map(|v| &**v), Some("v3"));
+        assert_eq!(db.retrieve_at("config", 1), None);
+        assert_eq!(db.retrieve_at("config", 5), None);
+
+        db.remove("config");
+        assert_eq!(db.retrieve("config"), None);
+        assert_eq!(db.retrieve_at("config", 4).map(|v| &**v), Some("v4"));
+    }
+
+    #[test]
+    fn test_history_is_off_by_default_and_rebounds() {
+        let mut db = MemoryDatabase::new();
+        db.insert("a", "1");
+        assert!(db.history("a").is_empty());
+        assert_eq!(db.retrieve_at("a", 1), None);
+
+        db.enable_history(4);
+        db.insert("a", "2");
+        db.insert("a", "3");
+        db.enable_history(1);
+        let history = db.history("a");
+        assert_eq!(history.len(), 1);
+        assert_eq!((history[0].number, &**history[0].value), (3, "3"));
+    }
+}
@@ -0,0 +1,266 @@
+//! Callbacks run around every write
+//!
+//! `add_hook` lets auditing, cache invalidation or replication sit beside
+//! the store without wrapping it. `BeforeInsert` hooks may refuse a write;
+//! removals can't be refused, since eviction and expiry must always
+//! succeed, so they only have an `AfterRemove` hook. `AfterExpire` hooks
+//! run besides those when a TTL entry is dropped for having expired, by a
+//! purge or by an overwrite of the expired entry. Hooks are shared with
+//! clones and run as each write happens, except inside a transaction:
+//! there `BeforeInsert` still runs per write, but the others are held back
+//! until `commit` and dropped on rollback.
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use crate::{Error, MemoryDatabase, StoredValue};
+
+/// A hook that may refuse the write it sees
+pub type CheckFn<K, V> = dyn Fn(&K, &V) -> Result<(), String> + Send + Sync;
+/// A hook that only observes the write it sees
+pub type ObserveFn<K, V> = dyn Fn(&K, &V) + Send + Sync;
+
+/// A callback and the point in a write it runs at
+pub enum Hook<K = Arc<str>, V = StoredValue> {
+    /// Runs before an entry is stored; an error refuses the write
+    BeforeInsert(Box<CheckFn<K, V>>),
+    /// Runs once an entry has been stored
+    AfterInsert(Box<ObserveFn<K, V>>),
+    /// Runs once an entry has been removed, evicted or purged
+    AfterRemove(Box<ObserveFn<K, V>>),
+    /// Runs once an expired entry has been purged or overwritten
+    AfterExpire(Box<ObserveFn<K, V>>),
+}
+
+/// An observing hook call held back until its transaction commits
+pub(crate) enum Deferred<K, V> {
+    Inserted(K, V),
+    Removed(K, V),
+    Expired(K, V),
+}
+
+/// Every installed hook, grouped by when it runs
+pub(crate) struct Hooks<K, V> {
+    before_insert: Vec<Arc<CheckFn<K, V>>>,
+    after_insert: Vec<Arc<ObserveFn<K, V>>>,
+    after_remove: Vec<Arc<ObserveFn<K, V>>>,
+    after_expire: Vec<Arc<ObserveFn<K, V>>>,
+    deferred: Option<Vec<Deferred<K, V>>>,
+}
+
+impl<K, V> Default for Hooks<K, V> {
+    fn default() -> Self {
+        Self {
+            before_insert: Vec::new(),
+            after_insert: Vec::new(),
+            after_remove: Vec::new(),
+            after_expire: Vec::new(),
+            deferred: None,
+        }
+    }
+}
+
+impl<K, V> Clone for Hooks<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            before_insert: self.before_insert.clone(),
+            after_insert: self.after_insert.clone(),
+            after_remove: self.after_remove.clone(),
+            after_expire: self.after_expire.clone(),
+            deferred: None,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Hooks<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("before_insert", &self.before_insert.len())
+            .field("after_insert", &self.after_insert.len())
+            .field("after_remove", &self.after_remove.len())
+            .field("after_expire", &self.after_expire.len())
+            .finish()
+    }
+}
+
+impl<K, V> Hooks<K, V> {
+    /// Fails with `Error::Rejected` carrying the first refusal
+    pub(crate) fn check_insert(&self, key: &K, value: &V) -> Result<(), Error> {
+        self.before_insert
+            .iter()
+            .try_for_each(|check| check(key, value))
+            .map_err(Error::Rejected)
+    }
+
+    /// Returns true when an `AfterInsert` hook needs to see writes
+    pub(crate) fn observes_inserts(&self) -> bool {
+        !self.after_insert.is_empty()
+    }
+}
+
+impl<K: Clone, V: Clone> Hooks<K, V> {
+    /// The same hooks, collecting observer calls for `take_deferred`
+    pub(crate) fn deferred(&self) -> Self {
+        Self {
+            deferred: Some(Vec::new()),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn take_deferred(&mut self) -> Vec<Deferred<K, V>> {
+        self.deferred.take().unwrap_or_default()
+    }
+
+    /// Makes the calls held back by a committed transaction, in order
+    pub(crate) fn run_deferred(&mut self, calls: Vec<Deferred<K, V>>) {
+        for call in calls {
+            match call {
+                Deferred::Inserted(key, value) => self.inserted(&key, &value),
+                Deferred::Removed(key, value) => self.removed(&key, &value),
+                Deferred::Expired(key, value) => self.expired(&key, &value),
+            }
+        }
+    }
+
+    pub(crate) fn inserted(&mut self, key: &K, value: &V) {
+        match &mut self.deferred {
+            Some(calls) if !self.after_insert.is_empty() => {
+                calls.push(Deferred::Inserted(key.clone(), value.clone()));
+            }
+            Some(_) => {}
+            None => self.after_insert.iter().for_each(|hook| hook(key, value)),
+        }
+    }
+
+    pub(crate) fn removed(&mut self, key: &K, value: &V) {
+        match &mut self.deferred {
+            Some(calls) if !self.after_remove.is_empty() => {
+                calls.push(Deferred::Removed(key.clone(), value.clone()));
+            }
+            Some(_) => {}
+            None => self.after_remove.iter().for_each(|hook| hook(key, value)),
+        }
+    }
+
+    pub(crate) fn expired(&mut self, key: &K, value: &V) {
+        match &mut self.deferred {
+            Some(calls) if !self.after_expire.is_empty() => {
+                calls.push(Deferred::Expired(key.clone(), value.clone()));
+            }
+            Some(_) => {}
+            None => self.after_expire.iter().for_each(|hook| hook(key, value)),
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Installs `hook`, after any already installed for the same point
+    pub fn add_hook(&mut self, hook: Hook<K, V>) {
+        let hooks = &mut self.hooks;
+        match hook {
+            Hook::BeforeInsert(check) => hooks.before_insert.push(Arc::from(check)),
+            Hook::AfterInsert(observe) => hooks.after_insert.push(Arc::from(observe)),
+            Hook::AfterRemove(observe) => hooks.after_remove.push(Arc::from(observe)),
+            Hook::AfterExpire(observe) => hooks.after_expire.push(Arc::from(observe)),
+        }
+    }
+
+    /// Removes every installed hook
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::eviction::EvictionPolicy;
+    use crate::{Database, DbOperation};
+
+    #[test]
+    fn test_hooks_see_inserts_and_removals() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut db: MemoryDatabase<&str, u32> =
+            MemoryDatabase::with_capacity_policy(2, EvictionPolicy::Lru);
+        let inserted = Arc::clone(&log);
+        db.add_hook(Hook::AfterInsert(Box::new(move |key, value| {
+            inserted.lock().unwrap().push(format!("+{}={}", key, value));
+        })));
+        let removed = Arc::clone(&log);
+        db.add_hook(Hook::AfterRemove(Box::new(move |key, _| {
+            removed.lock().unwrap().push(format!("-{}", key));
+        })));
+
+        db.put("a", 1);
+        db.put("b", 2);
+        db.put("c", 3);
+        db.remove("b");
+        assert_eq!(*log.lock().unwrap(), ["+a=1", "+b=2", "+c=3", "-a", "-b"]);
+
+        db.clear_hooks();
+        db.put("d", 4);
+        assert_eq!(log.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_before_insert_can_refuse() {
+        let mut db = MemoryDatabase::new();
+        db.add_hook(Hook::BeforeInsert(Box::new(|_, value| {
+            if value.starts_with("secret") {
+                Err("Secrets are not stored".to_string())
+            } else {
+                Ok(())
+            }
+        })));
+
+        db.insert("token", "secret-123");
+        assert_eq!(db.retrieve("token"), None);
+        let err = db.try_insert("token", "secret-456").unwrap_err();
+        assert!(matches!(err, Error::Rejected(_)));
+        let op = DbOperation::Insert {
+            key: "note".into(),
+            value: "secret".into(),
+        };
+        assert_eq!(
+            op.execute(&mut db).unwrap_err(),
+            "Rejected: Secrets are not stored"
+        );
+        db.insert("token", "public");
+        assert_eq!(db.retrieve("token"), Some("public"));
+    }
+
+    #[test]
+    fn test_transactions_hold_back_observers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut db = MemoryDatabase::new();
+        db.insert("balance", "10");
+        let inserted = Arc::clone(&log);
+        db.add_hook(Hook::AfterInsert(Box::new(move |key, value| {
+            inserted.lock().unwrap().push(format!("+{}={}", key, value));
+        })));
+        let removed = Arc::clone(&log);
+        db.add_hook(Hook::AfterRemove(Box::new(move |key, _| {
+            removed.lock().unwrap().push(format!("-{}", key));
+        })));
+
+        let mut tx = db.begin();
+        tx.insert("draft", "x");
+        tx.rollback();
+        let mut tx = db.begin();
+        tx.insert("balance", "5");
+        tx.remove("balance");
+        assert!(log.lock().unwrap().is_empty());
+        tx.commit();
+        assert_eq!(*log.lock().unwrap(), ["+balance=5", "-balance"]);
+
+        db.insert("after", "1");
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+}
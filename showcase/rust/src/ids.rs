@@ -0,0 +1,214 @@
+//! Record ids packed into 64 bits, plus the sizing maths for shards and buckets
+use std::fmt;
+use std::num::NonZeroU32;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+/// High bits of an id that hold the shard number
+pub const SHARD_BITS: u32 = 16;
+/// Low bits of an id that hold the per-shard sequence number
+pub const SEQUENCE_BITS: u32 = u64::BITS - SHARD_BITS;
+/// Largest sequence number a shard can hand out
+pub const SEQUENCE_MASK: u64 = (1u64 << SEQUENCE_BITS) - 1;
+
+/// Ids reserved by `IdAllocator::reserve` when no count is given
+pub const DEFAULT_BATCH: u64 = 1_000u64;
+/// Factor a bucket table grows by when it fills up
+pub const GROWTH_FACTOR: f32 = 2.5f32;
+/// Share of buckets that may be filled before growing
+pub const MAX_LOAD_FACTOR: f64 = 8.75e-1;
+/// Smallest non-zero timestamp resolution, in seconds
+pub const NANOSECOND: f64 = 1e-9;
+
+/// A non-zero id made of a shard number and a sequence number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId(NonZeroU64);
+
+impl RecordId {
+    /// Packs `shard` and `sequence`, or `None` if the result would be zero or
+    /// the sequence does not fit in `SEQUENCE_BITS`
+    pub const fn new(shard: u16, sequence: u64) -> Option<Self> {
+        if sequence > SEQUENCE_MASK {
+            return None;
+        }
+        match NonZeroU64::new((shard as u64) << SEQUENCE_BITS | sequence) {
+            Some(raw) => Some(RecordId(raw)),
+            None => None,
+        }
+    }
+
+    pub const fn shard(self) -> u16 {
+        (self.0.get() >> SEQUENCE_BITS) as u16
+    }
+
+    pub const fn sequence(self) -> u64 {
+        self.0.get() & SEQUENCE_MASK
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0.get()
+    }
+
+    /// Scrambles the bits so consecutive ids spread evenly across buckets
+    pub const fn mix(self) -> u64 {
+        let mut x = self.0.get();
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        x ^ (x >> 33)
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}-{:012x}", self.shard(), self.sequence())
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid record id: {}", s);
+        let (shard, sequence) = s.split_once('-').ok_or_else(invalid)?;
+        let shard = u16::from_str_radix(shard, 16).map_err(|_| invalid())?;
+        let sequence = u64::from_str_radix(sequence, 16).map_err(|_| invalid())?;
+        RecordId::new(shard, sequence).ok_or_else(invalid)
+    }
+}
+
+/// Hands out increasing ids within one shard
+#[derive(Debug, Clone)]
+pub struct IdAllocator {
+    shard: u16,
+    next: u64,
+}
+
+impl IdAllocator {
+    pub const fn new(shard: u16) -> Self {
+        Self { shard, next: 1 }
+    }
+
+    /// Next id, or `None` once the shard's sequence space is used up
+    pub fn next_id(&mut self) -> Option<RecordId> {
+        let id = RecordId::new(self.shard, self.next)?;
+        self.next += 1;
+        Some(id)
+    }
+
+    /// Next id, starting again from 1 once the sequence space is used up
+    pub fn next_wrapping(&mut self) -> RecordId {
+        let sequence = match self.next & SEQUENCE_MASK {
+            0 => 1,
+            sequence => sequence,
+        };
+        self.next = sequence + 1;
+        RecordId::new(self.shard, sequence).expect("sequence is masked and non-zero")
+    }
+
+    /// Reserves `count` ids at once, returning the first sequence number
+    pub fn reserve(&mut self, count: Option<u64>) -> Option<u64> {
+        let count = count.unwrap_or(DEFAULT_BATCH);
+        let (end, overflowed) = self.next.overflowing_add(count);
+        if overflowed || end - 1 > SEQUENCE_MASK {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.next, end))
+    }
+}
+
+/// Shard a key hash belongs to; uses a mask when `shards` is a power of two
+pub fn shard_for(hash: u64, shards: NonZeroU32) -> u32 {
+    let shards = shards.get();
+    if shards.is_power_of_two() {
+        (hash & u64::from(shards - 1)) as u32
+    } else {
+        (hash % u64::from(shards)) as u32
+    }
+}
+
+/// Buckets needed to hold `entries` under `MAX_LOAD_FACTOR`, rounded up to a
+/// power of two and capped at the largest power of two a `usize` can hold
+pub fn buckets_for(entries: usize) -> usize {
+    const MAX_BUCKETS: usize = 1 << (usize::BITS - 1);
+    if entries == 0 {
+        return 0;
+    }
+    let wanted = (entries as f64 / MAX_LOAD_FACTOR).ceil();
+    if wanted >= MAX_BUCKETS as f64 {
+        return MAX_BUCKETS;
+    }
+    (wanted as usize)
+        .checked_next_power_of_two()
+        .unwrap_or(MAX_BUCKETS)
+}
+
+/// Size of a table after growing by `GROWTH_FACTOR`, saturating at `usize::MAX`
+pub fn grown(buckets: usize) -> usize {
+    let next = (buckets as f32 * GROWTH_FACTOR) as usize;
+    next.max(buckets.saturating_add(1))
+}
+
+/// Converts a nanosecond count to seconds
+pub fn nanos_to_secs(nanos: u64) -> f64 {
+    nanos as f64 * NANOSECOND
+}
+
+/// Sum of `sizes`, and whether it wrapped around
+pub fn total_bytes(sizes: &[u64]) -> (u64, bool) {
+    sizes.iter().fold((0, false), |(total, wrapped), &size| {
+        let (total, overflow) = total.overflowing_add(size);
+        (total, wrapped | overflow)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_id_packing() {
+        let id = RecordId::new(0x00ab, 42).unwrap();
+        assert_eq!(id.get(), 0x00ab_0000_0000_002a);
+        assert_eq!((id.shard(), id.sequence()), (0xab, 42));
+        assert_eq!(id.to_string(), "00ab-00000000002a");
+        assert_eq!("00ab-00000000002a".parse(), Ok(id));
+        assert_eq!(RecordId::new(0, 0), None);
+        assert_eq!(RecordId::new(1, SEQUENCE_MASK + 1), None);
+        assert_ne!(id.mix(), RecordId::new(0xab, 43).unwrap().mix());
+
+        let mut ids = IdAllocator::new(7);
+        assert_eq!(ids.next_id().map(RecordId::sequence), Some(1));
+        assert_eq!(ids.reserve(None), Some(2));
+        assert_eq!(ids.next_id().unwrap().sequence(), 2 + DEFAULT_BATCH);
+
+        let mut full = IdAllocator {
+            shard: 1,
+            next: SEQUENCE_MASK,
+        };
+        assert_eq!(full.next_id().unwrap().sequence(), SEQUENCE_MASK);
+        assert_eq!(full.next_id(), None);
+        assert_eq!(full.reserve(Some(u64::MAX)), None);
+        assert_eq!(full.next_wrapping().sequence(), 1);
+        assert_eq!(full.next_wrapping().sequence(), 2);
+    }
+
+    #[test]
+    fn test_sizing_maths() {
+        let eight = NonZeroU32::new(8).unwrap();
+        assert_eq!(shard_for(0xdead_beef, eight), 0xdead_beef & 0b111);
+        assert_eq!(shard_for(10, NonZeroU32::new(3).unwrap()), 1);
+
+        assert_eq!(buckets_for(0), 0);
+        assert_eq!(buckets_for(7), 8);
+        assert_eq!(buckets_for(8), 16);
+        assert_eq!(buckets_for(usize::MAX), 1 << (usize::BITS - 1));
+        assert_eq!(grown(4), 10);
+        assert_eq!(grown(usize::MAX), usize::MAX);
+
+        assert_eq!(total_bytes(&[1_024, 2_048]), (3_072, false));
+        assert_eq!(total_bytes(&[u64::MAX, 2]), (1, true));
+        assert!((nanos_to_secs(2_500_000_000) - 2.5).abs() < 1e-12);
+    }
+}
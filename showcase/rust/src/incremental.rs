@@ -0,0 +1,163 @@
+//! Incremental saves that append only what changed
+//!
+//! The line format has no tombstones, so appends can only add or overwrite
+//! entries; the loader keeps the last line for each key. Any removal, or a
+//! file grown to twice the live entry count, triggers a full rewrite.
+//! Tracking starts with the first `save_incremental` and costs one set insert
+//! per write from then on.
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::persist::{encode_line, with_checksum, SaveOptions, SaveStats};
+use crate::{perf, StrDatabase};
+
+/// Keys written since the last incremental save, and the file they go to
+#[derive(Debug, Clone)]
+pub(crate) struct Journal<K> {
+    path: String,
+    lines: usize,
+    dirty: HashSet<K>,
+    removed: bool,
+}
+
+impl<K: Hash + Eq> Journal<K> {
+    pub(crate) fn mark(&mut self, key: K) {
+        self.dirty.insert(key);
+    }
+
+    pub(crate) fn mark_removed(&mut self) {
+        self.removed = true;
+    }
+}
+
+/// How `save_incremental` brought the file up to date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncrementalSave {
+    /// Rewrote the whole file
+    Compacted(SaveStats),
+    /// Appended this many changed entries
+    Appended(usize),
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Appends the entries changed since the last call, or rewrites `path`
+    ///
+    /// The file is rewritten on the first call, when `path` differs from the
+    /// last one, after a removal or expiry, or once the file would hold more
+    /// than twice as many lines as there are live entries.
+    pub fn save_incremental(&mut self, path: &str) -> io::Result<IncrementalSave> {
+        let now = Instant::now();
+        let live =
+            |key: &Arc<str>| self.store.contains_key(key) && !self.deadlines.expired_at(key, now);
+        let appendable = self.journal.as_ref().is_some_and(|journal| {
+            journal.path == path
+                && !journal.removed
+                && journal.lines + journal.dirty.len() <= 2 * self.len().max(1)
+                && journal.dirty.iter().all(live)
+        });
+        if !appendable {
+            let stats = self.save_with(path, &SaveOptions::default())?;
+            self.journal = Some(Journal {
+                path: path.to_string(),
+                lines: stats.entries,
+                dirty: HashSet::new(),
+                removed: false,
+            });
+            return Ok(IncrementalSave::Compacted(stats));
+        }
+
+        let dirty = self
+            .journal
+            .as_mut()
+            .map(|journal| std::mem::take(&mut journal.dirty))
+            .unwrap_or_default();
+        let file = OpenOptions::new().append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        let mut bytes = 0;
+        for key in &dirty {
+            let value = self
+                .store
+                .get(key)
+                .expect("dirty keys were checked to be live");
+            let line = with_checksum(&encode_line(key, value)) + "\n";
+            writer.write_all(line.as_bytes())?;
+            bytes += line.len() as u64;
+        }
+        writer.flush()?;
+        perf::record_bytes_persisted(bytes);
+
+        if let Some(journal) = self.journal.as_mut() {
+            journal.lines += dirty.len();
+        }
+        Ok(IncrementalSave::Appended(dirty.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_appends_only_changed_entries() {
+        let path = std::env::temp_dir().join("gvs_incremental_append.db");
+        let path = path.to_str().unwrap();
+        let mut db: MemoryDatabase = (0..4).map(|i| (format!("key{}", i), "v")).collect();
+
+        assert!(matches!(
+            db.save_incremental(path).unwrap(),
+            IncrementalSave::Compacted(SaveStats { entries: 4, .. })
+        ));
+        db.insert("key1", "changed");
+        db.insert("key1", "twice");
+        db.insert("new", "entry");
+        assert_eq!(
+            db.save_incremental(path).unwrap(),
+            IncrementalSave::Appended(2)
+        );
+        assert_eq!(
+            db.save_incremental(path).unwrap(),
+            IncrementalSave::Appended(0)
+        );
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(contents.lines().count(), 6);
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(loaded.retrieve("key1"), Some("twice"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_compacts_after_removal_or_growth() {
+        let path = std::env::temp_dir().join("gvs_incremental_compact.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("a", "1");
+        db.insert("b", "2");
+        db.save_incremental(path).unwrap();
+
+        db.remove("a");
+        assert!(matches!(
+            db.save_incremental(path).unwrap(),
+            IncrementalSave::Compacted(_)
+        ));
+        assert_eq!(MemoryDatabase::load_from_file(path).unwrap().len(), 1);
+
+        let mut rewrites = 0;
+        for round in 0..4 {
+            db.insert("b", round.to_string());
+            if let IncrementalSave::Compacted(_) = db.save_incremental(path).unwrap() {
+                rewrites += 1;
+            }
+        }
+        assert_eq!(rewrites, 2);
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.retrieve("b"), Some("3"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+//! Secondary indexes from a derived term to the keys holding it
+//!
+//! Each index maps the term its function derives from a value to the set of
+//! keys whose values produce it, so `lookup` costs one hash probe rather
+//! than a scan. Every write re-derives the terms of the entry it touches.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use crate::MemoryDatabase;
+
+type Derive<V> = Arc<dyn Fn(&V) -> Option<String> + Send + Sync>;
+
+struct Index<K, V> {
+    name: String,
+    derive: Derive<V>,
+    terms: HashMap<String, HashSet<K>>,
+}
+
+impl<K: Clone, V> Clone for Index<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            derive: Arc::clone(&self.derive),
+            terms: self.terms.clone(),
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Index<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Index")
+            .field("name", &self.name)
+            .field("terms", &self.terms.len())
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Index<K, V> {
+    fn add(&mut self, key: &K, value: &V) {
+        if let Some(term) = (self.derive)(value) {
+            self.terms.entry(term).or_default().insert(key.clone());
+        }
+    }
+
+    fn forget(&mut self, key: &K, value: &V) {
+        let Some(term) = (self.derive)(value) else {
+            return;
+        };
+        if let Some(keys) = self.terms.get_mut(&term) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.terms.remove(&term);
+            }
+        }
+    }
+}
+
+/// Every index registered on a database
+#[derive(Debug)]
+pub(crate) struct Indexes<K, V> {
+    indexes: Vec<Index<K, V>>,
+}
+
+impl<K, V> Default for Indexes<K, V> {
+    fn default() -> Self {
+        Self {
+            indexes: Vec::new(),
+        }
+    }
+}
+
+impl<K: Clone, V> Clone for Indexes<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            indexes: self.indexes.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Indexes<K, V> {
+    /// Moves `key` from the terms of `old` to those of `new`
+    pub(crate) fn update(&mut self, key: &K, old: Option<&V>, new: Option<&V>) {
+        for index in &mut self.indexes {
+            if let Some(old) = old {
+                index.forget(key, old);
+            }
+            if let Some(new) = new {
+                index.add(key, new);
+            }
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Indexes every entry under the term `derive` returns for its value
+    ///
+    /// Values for which `derive` returns `None` are left out. Replaces any
+    /// index already called `name`; building it walks every entry once.
+    pub fn create_index<F>(&mut self, name: &str, derive: F)
+    where
+        F: Fn(&V) -> Option<String> + Send + Sync + 'static,
+    {
+        let mut index = Index {
+            name: name.to_string(),
+            derive: Arc::new(derive),
+            terms: HashMap::new(),
+        };
+        for (key, value) in self.live_entries() {
+            index.add(key, value);
+        }
+        self.drop_index(name);
+        self.indexes.indexes.push(index);
+    }
+
+    /// Removes the index called `name`, returning true if there was one
+    pub fn drop_index(&mut self, name: &str) -> bool {
+        let before = self.indexes.indexes.len();
+        self.indexes.indexes.retain(|index| index.name != name);
+        self.indexes.indexes.len() < before
+    }
+
+    /// Live entries whose value derives `term` in the index called `name`
+    ///
+    /// Returns `None` when no such index exists. Entries come in no
+    /// particular order.
+    pub fn lookup<'a>(
+        &'a self,
+        name: &str,
+        term: &str,
+    ) -> Option<impl Iterator<Item = (&'a K, &'a V)> + 'a> {
+        let index = self
+            .indexes
+            .indexes
+            .iter()
+            .find(|index| index.name == name)?;
+        let keys = index.terms.get(term).into_iter().flatten();
+        Some(keys.filter_map(|key| Some((key, self.get_exact(key)?))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::sync::Arc;
+
+    use crate::{Database, MemoryDatabase};
+
+    fn as_strs<'a, V: Deref<Target = str>>(
+        (key, value): (&'a Arc<str>, &'a V),
+    ) -> (&'a str, &'a str) {
+        (key, value)
+    }
+
+    fn sorted<'a>(entries: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<&'a str> {
+        let mut keys: Vec<&str> = entries.map(|(key, _)| key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_index_follows_writes() {
+        let mut db = MemoryDatabase::new();
+        db.insert("user:1", "alice@example.com");
+        db.insert("user:2", "bob@test.org");
+        db.create_index("by_domain", |v| {
+            v.split_once('@').map(|(_, domain)| domain.to_string())
+        });
+        db.insert("user:3", "carol@example.com");
+        db.insert("user:2", "bob@example.com");
+        db.insert("user:1", "alice@test.org");
+        db.remove("user:3");
+        db.insert("note", "no address");
+
+        let by_domain = |term| sorted(db.lookup("by_domain", term).unwrap().map(as_strs));
+        assert_eq!(by_domain("example.com"), ["user:2"]);
+        assert_eq!(by_domain("test.org"), ["user:1"]);
+        assert!(by_domain("absent.net").is_empty());
+        assert!(db.lookup("missing", "example.com").is_none());
+    }
+
+    #[test]
+    fn test_replace_and_drop_index() {
+        let mut db = MemoryDatabase::new();
+        db.insert("a", "red");
+        db.insert("b", "blue");
+        db.create_index("by_value", |v| Some(v.to_string()));
+        db.create_index("by_value", |v| Some(v.len().to_string()));
+        db.insert_with_ttl("c", "teal", std::time::Duration::ZERO);
+
+        let values: Vec<_> = db.lookup("by_value", "4").unwrap().map(as_strs).collect();
+        assert_eq!(values, [("b", "blue")]);
+        assert!(db.drop_index("by_value"));
+        assert!(!db.drop_index("by_value"));
+        assert!(db.lookup("by_value", "4").is_none());
+    }
+}
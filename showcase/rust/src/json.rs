@@ -0,0 +1,85 @@
+//! JSON persistence, for values the `key:value` line format cannot hold
+//!
+//! The database is stored as one JSON object with keys in sorted order, so
+//! saved files diff cleanly and values may contain `:`, newlines or any
+//! Unicode.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufReader, BufWriter, Write};
+
+use crate::{persist, MemoryDatabase, StrDatabase};
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    fn sorted_entries(&self) -> BTreeMap<&str, &str> {
+        self.scan().collect()
+    }
+
+    /// Serializes the database as a pretty-printed JSON object
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(&self.sorted_entries()).expect("string map serializes")
+    }
+
+    /// Saves data to `path` as JSON
+    pub fn save_json(&self, path: &str) -> io::Result<()> {
+        persist::write_atomically(path, |file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut writer, &self.sorted_entries())?;
+            writer.write_all(b"\n")?;
+            writer.flush()
+        })
+    }
+}
+
+impl MemoryDatabase {
+    /// Parses a JSON object of string values
+    pub fn from_json_str(json: &str) -> io::Result<Self> {
+        let entries: BTreeMap<String, String> = serde_json::from_str(json)?;
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Loads data saved by `save_json`
+    ///
+    /// Fails with `InvalidData` if the file is not a JSON object of strings.
+    pub fn load_json(path: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let entries: BTreeMap<String, String> = serde_json::from_reader(reader)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_json_round_trip_keeps_special_characters() {
+        let path = std::env::temp_dir().join("gvs_json_round_trip.json");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("url", "https://example.com:8080");
+        db.insert("poem", "line one\nline two");
+        db.insert("key:with:colons", "🦀 café");
+
+        db.save_json(path).unwrap();
+        let loaded = MemoryDatabase::load_json(path).unwrap();
+        assert_eq!(loaded.retrieve("url"), Some("https://example.com:8080"));
+        assert_eq!(loaded.retrieve("poem"), Some("line one\nline two"));
+        assert_eq!(loaded.retrieve("key:with:colons"), Some("🦀 café"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_json_string_is_sorted_and_validated() {
+        let mut db = MemoryDatabase::new();
+        db.insert("b", "2");
+        db.insert("a", "1");
+        assert_eq!(db.to_json_string(), "{\n  \"a\": \"1\",\n  \"b\": \"2\"\n}");
+
+        let parsed = MemoryDatabase::from_json_str(r#"{"lang": "Rust"}"#).unwrap();
+        assert_eq!(parsed.retrieve("lang"), Some("Rust"));
+        let err = MemoryDatabase::from_json_str(r#"{"port": 8080}"#).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,119 @@
+//! Rules new keys must pass before they are stored
+//!
+//! `set_key_policy` installs a check that runs on every key written through
+//! `insert`, `try_insert` and `insert_with_ttl`, after any case folding.
+//! Keys already stored and entries loaded from files are not rechecked.
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use crate::{Error, StrDatabase};
+
+type Check = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// The installed check, shared with clones and snapshots
+#[derive(Clone)]
+pub(crate) struct KeyPolicy {
+    check: Check,
+}
+
+impl fmt::Debug for KeyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPolicy").finish_non_exhaustive()
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Rejects keys for which `check` returns an error
+    ///
+    /// `insert` drops rejected entries; `try_insert` and operations report
+    /// them as `Error::InvalidKey` carrying the reason `check` gave.
+    pub fn set_key_policy<F>(&mut self, check: F)
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.key_policy = Some(KeyPolicy {
+            check: Arc::new(check),
+        });
+    }
+
+    /// Removes the key policy, accepting every key again
+    pub fn clear_key_policy(&mut self) {
+        self.key_policy = None;
+    }
+
+    pub(crate) fn check_key(&self, key: &str) -> Result<(), Error> {
+        match &self.key_policy {
+            Some(policy) => (policy.check)(key).map_err(|reason| Error::InvalidKey {
+                key: key.to_string(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Database, DbOperation, Error, MemoryDatabase};
+
+    fn config_keys(key: &str) -> Result<(), String> {
+        if key.is_empty() {
+            Err("Key is empty".to_string())
+        } else if key.len() > 16 {
+            Err("Key exceeds 16 bytes".to_string())
+        } else if let Some(c) = key.chars().find(|c| c.is_whitespace() || c.is_control()) {
+            Err(format!("Forbidden character {:?}", c))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_policy_rejects_bad_keys() {
+        let mut db = MemoryDatabase::new();
+        db.set_key_policy(config_keys);
+
+        db.insert("", "dropped");
+        db.insert_with_ttl("has space", "dropped", Duration::from_secs(60));
+        db.insert("server.port", "8080");
+        assert_eq!(db.len(), 1);
+
+        let err = db.try_insert("a".repeat(17), "v").unwrap_err();
+        assert!(
+            matches!(&err, Error::InvalidKey { reason, .. } if reason == "Key exceeds 16 bytes")
+        );
+        let op = DbOperation::Insert {
+            key: "tab\there".into(),
+            value: "v".into(),
+        };
+        assert_eq!(
+            op.execute(&mut db).unwrap_err(),
+            "Invalid key \"tab\\there\": Forbidden character '\\t'"
+        );
+
+        db.clear_key_policy();
+        db.insert("", "allowed");
+        assert_eq!(db.retrieve(""), Some("allowed"));
+    }
+
+    #[test]
+    fn test_policy_sees_folded_keys_and_clones() {
+        let mut db = MemoryDatabase::new_case_insensitive();
+        db.set_key_policy(|key| {
+            if key.chars().any(char::is_uppercase) {
+                Err("Uppercase".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(db.try_insert("Host", "localhost").is_ok());
+        assert_eq!(db.retrieve("host"), Some("localhost"));
+
+        let mut copy = db.clone();
+        copy.fold_case = false;
+        assert!(copy.try_insert("Host", "v").is_err());
+    }
+}
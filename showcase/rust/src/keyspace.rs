@@ -0,0 +1,228 @@
+//! Namespaced keys layered over any [`Database`]
+//!
+//! A [`Keyspace`] prefixes every key with a namespace and `/`, so several
+//! components can share one [`MemoryDatabase`] without their keys colliding.
+//!
+//! # Examples
+//!
+//! ```
+//! use gvs_showcase::keyspace::Keyspace;
+//! use gvs_showcase::{Database, MemoryDatabase};
+//!
+//! let mut db = MemoryDatabase::new();
+//! let users = Keyspace::new("users").unwrap();
+//! users.insert(&mut db, "ferris", "crab");
+//!
+//! assert_eq!(db.retrieve("users/ferris"), Some("crab"));
+//! assert_eq!(users.retrieve(&db, "ferris"), Some("crab"));
+//! ```
+//!
+//! Keyspaces nest with [`Keyspace::child`]:
+//!
+//! ```
+//! # use gvs_showcase::keyspace::Keyspace;
+//! let admins = Keyspace::new("users").unwrap().child("admins");
+//! assert_eq!(admins.key("root"), "users/admins/root");
+//! ```
+use std::fmt;
+
+use crate::{Database, MemoryDatabase};
+
+/// Separator placed between a namespace and the key inside it
+pub const SEPARATOR: char = '/';
+
+/// Why a namespace was rejected by [`Keyspace::new`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyspaceError {
+    /// The namespace was empty
+    Empty,
+    /// The namespace contained [`SEPARATOR`] or `:`, the save-file separator
+    ReservedChar(char),
+}
+
+impl fmt::Display for KeyspaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyspaceError::Empty => f.write_str("namespace must not be empty"),
+            KeyspaceError::ReservedChar(c) => write!(f, "namespace must not contain {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for KeyspaceError {}
+
+/// A namespace within a database
+///
+/// See the [module documentation](self) for an overview.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Keyspace {
+    prefix: String,
+}
+
+fn validate(name: &str) -> Result<(), KeyspaceError> {
+    if name.is_empty() {
+        return Err(KeyspaceError::Empty);
+    }
+    match name.chars().find(|&c| c == SEPARATOR || c == ':') {
+        Some(c) => Err(KeyspaceError::ReservedChar(c)),
+        None => Ok(()),
+    }
+}
+
+impl Keyspace {
+    /// Creates a top-level namespace
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeyspaceError::Empty`] for an empty name and
+    /// [`KeyspaceError::ReservedChar`] when `name` contains `/` or `:`.
+    ///
+    /// ```
+    /// # use gvs_showcase::keyspace::{Keyspace, KeyspaceError};
+    /// assert_eq!(Keyspace::new(""), Err(KeyspaceError::Empty));
+    /// assert_eq!(Keyspace::new("a/b"), Err(KeyspaceError::ReservedChar('/')));
+    /// ```
+    pub fn new(name: &str) -> Result<Self, KeyspaceError> {
+        validate(name)?;
+        Ok(Self {
+            prefix: name.to_string(),
+        })
+    }
+
+    /// Creates a namespace nested inside this one
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` would be rejected by [`Keyspace::new`]; child names
+    /// are expected to be literals chosen by the program, not user input.
+    ///
+    /// ```should_panic
+    /// # use gvs_showcase::keyspace::Keyspace;
+    /// Keyspace::new("users").unwrap().child("");
+    /// ```
+    pub fn child(&self, name: &str) -> Self {
+        if let Err(e) = validate(name) {
+            panic!("invalid child keyspace {:?}: {}", name, e);
+        }
+        Self {
+            prefix: format!("{}{}{}", self.prefix, SEPARATOR, name),
+        }
+    }
+
+    /// The full prefix, without the trailing separator
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The database key `name` is stored under
+    pub fn key(&self, name: &str) -> String {
+        format!("{}{}{}", self.prefix, SEPARATOR, name)
+    }
+
+    /// Returns the part of `key` inside this namespace, or `None` if it is outside
+    ///
+    /// ```
+    /// # use gvs_showcase::keyspace::Keyspace;
+    /// let users = Keyspace::new("users").unwrap();
+    /// assert_eq!(users.strip("users/ferris"), Some("ferris"));
+    /// assert_eq!(users.strip("usersferris"), None);
+    /// ```
+    pub fn strip<'a>(&self, key: &'a str) -> Option<&'a str> {
+        key.strip_prefix(self.prefix.as_str())?
+            .strip_prefix(SEPARATOR)
+    }
+
+    /// Like [`strip`](Self::strip), without checking that `key` is inside the namespace
+    ///
+    /// # Safety
+    ///
+    /// `key` must start with [`prefix`](Self::prefix) followed by
+    /// [`SEPARATOR`], for example a key produced by [`Keyspace::key`].
+    /// Otherwise the returned slice may start inside a UTF-8 sequence.
+    pub unsafe fn strip_unchecked<'a>(&self, key: &'a str) -> &'a str {
+        debug_assert!(self.strip(key).is_some());
+        // SAFETY: the caller guarantees the prefix and the one-byte separator are present
+        unsafe { key.get_unchecked(self.prefix.len() + SEPARATOR.len_utf8()..) }
+    }
+
+    /// Inserts through [`Database::insert`] under this namespace
+    pub fn insert<D: Database>(&self, db: &mut D, name: &str, value: &str) {
+        db.insert(self.key(name), value);
+    }
+
+    /// Reads through [`Database::retrieve`] from this namespace
+    pub fn retrieve<'a, D: Database>(&self, db: &'a D, name: &str) -> Option<&'a str> {
+        db.retrieve(&self.key(name))
+    }
+
+    /// Copies this namespace out of `db` into a file in the
+    /// [`save_to_file`](MemoryDatabase::save_to_file) format
+    ///
+    /// Keys are written without the namespace prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from writing `path`.
+    ///
+    /// ```no_run
+    /// # use gvs_showcase::keyspace::Keyspace;
+    /// # use gvs_showcase::MemoryDatabase;
+    /// let db = MemoryDatabase::load_from_file("data.db")?;
+    /// Keyspace::new("users").unwrap().export(&db, "users.db")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn export(&self, db: &MemoryDatabase, path: &str) -> std::io::Result<()> {
+        let stored = Self {
+            prefix: db.fold_key(&self.prefix).into_owned(),
+        };
+        let mut exported = MemoryDatabase::new();
+        for (key, value) in db.scan_prefix(&stored.prefix) {
+            if let Some(name) = stored.strip(key) {
+                exported.insert(name, value);
+            }
+        }
+        exported.save_to_file(path)
+    }
+}
+
+impl fmt::Display for Keyspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix, SEPARATOR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces_do_not_collide() {
+        let mut db = MemoryDatabase::new();
+        let api = Keyspace::new("api").unwrap();
+        let cache = Keyspace::new("cache").unwrap();
+        api.insert(&mut db, "port", "8080");
+        cache.insert(&mut db, "port", "6379");
+
+        assert_eq!(api.retrieve(&db, "port"), Some("8080"));
+        assert_eq!(cache.retrieve(&db, "port"), Some("6379"));
+        let key = api.child("v2").key("port");
+        assert_eq!(unsafe { api.strip_unchecked(&key) }, "v2/port");
+        assert_eq!(Keyspace::new("a:b"), Err(KeyspaceError::ReservedChar(':')));
+    }
+
+    #[test]
+    fn test_export_strips_prefix() {
+        let path = std::env::temp_dir().join("gvs_keyspace_export.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        let users = Keyspace::new("users").unwrap();
+        users.insert(&mut db, "ferris", "crab");
+        db.insert("other", "skipped");
+
+        users.export(&db, path).unwrap();
+        let exported = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(exported.retrieve("ferris"), Some("crab"));
+        assert_eq!(exported.retrieve("other"), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
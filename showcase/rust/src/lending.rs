@@ -0,0 +1,143 @@
+//! Zero-copy retrieval through borrowed guards
+//!
+//! `LendingDatabase` hands out a guard tied to the borrow of the store, so
+//! callers read values in place whether the store is owned, snapshotted or
+//! shared behind a lock.
+use std::hash::BuildHasher;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use crate::snapshot::Snapshot;
+use crate::{Database, StrDatabase};
+
+/// A store that lends out values without copying them
+pub trait LendingDatabase {
+    type Guard<'a>: Deref<Target = str>
+    where
+        Self: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<Self::Guard<'a>>;
+}
+
+impl<S: BuildHasher + Clone> LendingDatabase for StrDatabase<S> {
+    type Guard<'a>
+        = &'a str
+    where
+        S: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<&'a str> {
+        self.retrieve(key)
+    }
+}
+
+impl<S: BuildHasher + Clone> LendingDatabase for Snapshot<S> {
+    type Guard<'a>
+        = &'a str
+    where
+        S: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<&'a str> {
+        self.retrieve(key)
+    }
+}
+
+/// A value read in place, keeping the database read-locked while alive
+pub struct ValueGuard<'a, S> {
+    guard: RwLockReadGuard<'a, StrDatabase<S>>,
+    key: Arc<str>,
+}
+
+impl<'a, S: BuildHasher + Clone + 'a> Deref for ValueGuard<'a, S> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        // the key was present when locked and writers are shut out until drop
+        self.guard
+            .retrieve(&self.key)
+            .expect("value removed while read-locked")
+    }
+}
+
+impl<S: BuildHasher + Clone> LendingDatabase for RwLock<StrDatabase<S>> {
+    type Guard<'a>
+        = ValueGuard<'a, S>
+    where
+        S: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<ValueGuard<'a, S>> {
+        let guard = self.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        guard.retrieve(key)?;
+        Some(ValueGuard {
+            guard,
+            key: key.into(),
+        })
+    }
+}
+
+/// Calls `visit` with every value found for `keys`; returns how many were found
+pub fn visit_values<D, F>(db: &D, keys: &[&str], mut visit: F) -> usize
+where
+    D: LendingDatabase,
+    F: for<'v> FnMut(&'v str),
+{
+    let mut found = 0;
+    for key in keys {
+        if let Some(value) = db.lend(key) {
+            visit(&value);
+            found += 1;
+        }
+    }
+    found
+}
+
+/// Returns the first of `keys` whose value satisfies `predicate`
+pub fn find_key<'k, D, P>(db: &D, keys: &[&'k str], predicate: P) -> Option<&'k str>
+where
+    D: LendingDatabase,
+    P: for<'v> Fn(&'v str) -> bool,
+{
+    keys.iter()
+        .copied()
+        .find(|key| db.lend(key).is_some_and(|value| predicate(&value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    fn sample() -> MemoryDatabase {
+        let mut db = MemoryDatabase::new();
+        db.insert("language", "Rust");
+        db.insert("year", "2010");
+        db
+    }
+
+    #[test]
+    fn test_same_api_across_stores() {
+        let db = sample();
+        let snapshot = db.snapshot();
+        let locked = RwLock::new(sample());
+        let keys = ["language", "missing", "year"];
+
+        let mut total = 0;
+        assert_eq!(visit_values(&db, &keys, |v| total += v.len()), 2);
+        assert_eq!(visit_values(&snapshot, &keys, |v| total += v.len()), 2);
+        assert_eq!(visit_values(&locked, &keys, |v| total += v.len()), 2);
+        assert_eq!(total, 3 * 8);
+
+        let numeric = |v: &str| v.bytes().all(|b| b.is_ascii_digit());
+        assert_eq!(find_key(&locked, &keys, numeric), Some("year"));
+    }
+
+    #[test]
+    fn test_guard_holds_read_lock() {
+        let locked = RwLock::new(sample());
+        let value = locked.lend("language").unwrap();
+
+        assert_eq!(&*value, "Rust");
+        assert!(locked.try_write().is_err());
+        drop(value);
+        assert!(locked.try_write().is_ok());
+    }
+}
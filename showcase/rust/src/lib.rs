@@ -0,0 +1,1032 @@
+// This is a comprehensive Rust example showcasing enhanced theme colors
+use std::borrow::{Borrow, Cow};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::case::FoldCase;
+
+pub mod arena;
+#[cfg(feature = "async")]
+pub mod async_db;
+#[cfg(feature = "auth")]
+pub mod auth;
+pub mod backup;
+#[cfg(feature = "bincode")]
+pub mod binary;
+pub mod bucket;
+pub mod bucket_quota;
+pub mod case;
+pub mod changelog;
+pub mod changes;
+pub mod client;
+pub mod closures;
+pub mod cluster;
+pub mod compact;
+pub mod concurrent;
+pub mod csv;
+pub mod diff;
+#[cfg(feature = "encrypted")]
+pub mod encrypted;
+pub mod enums;
+pub mod error;
+pub mod eviction;
+pub mod ffi;
+pub mod fixed_cache;
+pub mod fuzzy;
+pub mod generated;
+pub mod glob;
+#[cfg(feature = "gzip")]
+pub mod gzip;
+pub mod history;
+pub mod hooks;
+pub mod ids;
+pub mod incremental;
+pub mod index;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod key_policy;
+pub mod keyspace;
+pub mod lending;
+pub mod literals;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "napi")]
+pub mod node;
+pub mod options;
+pub mod patterns;
+pub mod perf;
+pub mod persist;
+pub mod pipeline;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod quota;
+pub mod readonly;
+pub mod record;
+pub mod registry;
+pub mod replication;
+pub mod scores;
+pub mod search;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod services;
+#[cfg(feature = "serde")]
+pub mod settings;
+pub mod sharded;
+pub mod snapshot;
+pub mod sorted;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "arc-swap")]
+pub mod swap;
+pub mod sweeper;
+pub mod testkit;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod transaction;
+pub mod transfer;
+pub mod ttl;
+pub mod value;
+pub mod value_mut;
+pub mod wal;
+pub mod writer;
+
+pub use error::Error;
+pub use gvs_macros::{db_ops, timed, DbRecord};
+
+/// A background component with an explicit lifecycle
+pub trait Service {
+    fn start(&mut self) -> Result<(), Error>;
+    fn stop(&mut self) -> Result<(), Error>;
+}
+
+/// A trait for database operations
+///
+/// Implementors also provide `IntoIterator` for themselves and their
+/// references, so `for (key, value) in &db` works on any of them.
+pub trait Database {
+    fn insert(&mut self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>);
+    fn retrieve(&self, key: &str) -> Option<&str>;
+
+    /// Every live entry, in an order chosen by the implementation
+    fn iter(&self) -> impl Iterator<Item = (&str, &str)>;
+
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Inserts every entry, in order, so later duplicates win
+    fn insert_many<K, V>(&mut self, entries: impl IntoIterator<Item = (K, V)>)
+    where
+        K: Into<Arc<str>>,
+        V: Into<Arc<str>> + AsRef<str>,
+    {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+
+    /// Looks up every key, returning the values in the same order
+    fn retrieve_many(&self, keys: &[&str]) -> Vec<Option<&str>> {
+        keys.iter().map(|key| self.retrieve(key)).collect()
+    }
+
+    /// Copies the value out, for callers that need it past the borrow
+    fn retrieve_owned(&self, key: &str) -> Option<String> {
+        self.retrieve(key).map(str::to_string)
+    }
+
+    /// Removes `key`, returning true if it was present
+    fn delete(&mut self, key: &str) -> bool;
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.retrieve(key).is_some()
+    }
+
+    /// Number of stored entries, as the implementation counts them
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every entry
+    fn clear(&mut self);
+
+    /// Like `insert`, but fails when the store refuses the write
+    ///
+    /// Stores without limits never refuse, which the default assumes.
+    fn try_insert(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+    ) -> Result<(), Error> {
+        self.insert(key, value);
+        Ok(())
+    }
+
+    /// Stores `combine(old, value)` under `key`, or just `value` if it is absent
+    ///
+    /// Returns the value now stored; the read and write happen under one
+    /// borrow, so no other writer can interleave. Fails when the result is
+    /// refused, as `try_insert` does.
+    fn merge<F>(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+        combine: F,
+    ) -> Result<Arc<str>, Error>
+    where
+        F: FnOnce(&str, &str) -> String,
+    {
+        let (key, value) = (key.into(), value.into());
+        let merged: Arc<str> = match self.retrieve(&key) {
+            Some(old) => combine(old, &value).into(),
+            None => value,
+        };
+        self.try_insert(key, Arc::clone(&merged))?;
+        Ok(merged)
+    }
+}
+
+/// A simple in-memory database implementation
+///
+/// Generic over key and value types; the defaults store shared `Arc<str>`s,
+/// so handing them to operations and snapshots bumps a reference count
+/// instead of copying the string. The hasher defaults to the DoS-resistant
+/// `RandomState`; see `with_hasher`. Writes need `K: Clone` and `V: Clone`
+/// because they copy any shard still shared with a snapshot.
+#[derive(Debug, Clone)]
+pub struct MemoryDatabase<K = Arc<str>, V = StoredValue, S = RandomState> {
+    store: snapshot::CowMap<K, V, S>,
+    deadlines: ttl::Deadlines<K>,
+    eviction: Option<eviction::Lru<K>>,
+    quota: Option<quota::Quota<K, V>>,
+    journal: Option<incremental::Journal<K>>,
+    changes: Option<changes::Changes<K>>,
+    counters: stats::Counters,
+    indexes: index::Indexes<K, V>,
+    text_index: Option<search::TextIndex<K, V>>,
+    history: Option<history::History<K, V>>,
+    fold_case: bool,
+    key_policy: Option<key_policy::KeyPolicy>,
+    hooks: hooks::Hooks<K, V>,
+    changelog: Option<changelog::Changelog<K, V>>,
+    bucket_quotas: Option<bucket_quota::BucketQuotas<K, V>>,
+}
+
+/// Representation of values inside the store
+#[cfg(not(feature = "compact-values"))]
+type StoredValue = Arc<str>;
+#[cfg(feature = "compact-values")]
+type StoredValue = compact::CompactValue;
+
+/// Converts a value for the store; short values are copied inline under
+/// `compact-values` without first building an `Arc<str>`
+fn stored(value: impl Into<Arc<str>> + AsRef<str>) -> StoredValue {
+    #[cfg(feature = "compact-values")]
+    return compact::CompactValue::inline(value.as_ref())
+        .unwrap_or_else(|| compact::CompactValue::Shared(value.into()));
+    #[cfg(not(feature = "compact-values"))]
+    value.into()
+}
+
+fn shared(value: StoredValue) -> Arc<str> {
+    #[cfg(feature = "compact-values")]
+    let value = Arc::from(value);
+    value
+}
+
+/// The string-keyed `MemoryDatabase` behind the `Database` trait
+pub type StrDatabase<S = RandomState> = MemoryDatabase<Arc<str>, StoredValue, S>;
+
+/// `MemoryDatabase` hashing with aHash, for short keys from trusted sources
+#[cfg(feature = "ahash")]
+pub type AHashDatabase = StrDatabase<ahash::RandomState>;
+
+/// `MemoryDatabase` hashing with FxHash, for short keys from trusted sources
+#[cfg(feature = "fxhash")]
+pub type FxDatabase = StrDatabase<rustc_hash::FxBuildHasher>;
+
+impl MemoryDatabase {
+    /// Creates a new empty database
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Loads data from a file
+    ///
+    /// Fails with `InvalidData` naming the first malformed line; a line that
+    /// fails its checksum carries an `Error::Corrupted` with its offset.
+    pub fn load_from_file(path: &str) -> io::Result<Self> {
+        Self::load_from_file_with_hasher(path, RandomState::new())
+    }
+}
+
+impl<K, V, S: Default + Clone> Default for MemoryDatabase<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S: Clone> MemoryDatabase<K, V, S> {
+    /// Creates a new empty database hashing keys with `hasher`
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            store: snapshot::CowMap::with_hasher(hasher),
+            deadlines: ttl::Deadlines::default(),
+            eviction: None,
+            quota: None,
+            journal: None,
+            changes: None,
+            counters: stats::Counters::default(),
+            indexes: index::Indexes::default(),
+            text_index: None,
+            history: None,
+            fold_case: false,
+            key_policy: None,
+            hooks: hooks::Hooks::default(),
+            changelog: None,
+            bucket_quotas: None,
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Number of live entries; expired ones not yet purged are left out
+    pub fn len(&self) -> usize {
+        let expired = self.deadlines.due_keys(Instant::now());
+        self.store.len() - expired.filter(|key| self.store.contains_key(*key)).count()
+    }
+
+    /// Returns true when no live entry is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks up a value by any borrowed form of the key
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + FoldCase + ?Sized,
+    {
+        self.get_exact(&*self.fold_query(key))
+    }
+
+    /// Like `get`, with `key` already as stored
+    pub(crate) fn get_exact<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let value = self.store.get(key).filter(|_| !self.deadlines.expired(key));
+        self.counters.record_read(value.is_some());
+        let value = value?;
+        if let Some(lru) = &self.eviction {
+            lru.touch(key);
+        }
+        Some(value)
+    }
+
+    /// Stores `value` under `key`, returning the value it replaced
+    ///
+    /// A database with a refusing byte limit drops entries that don't fit;
+    /// `try_put` reports them instead.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        self.try_put(key, value).unwrap_or_default()
+    }
+
+    /// Returns the value under `key`, first storing `default()` if it is absent
+    ///
+    /// `default` only runs for a missing or expired key. Fails with
+    /// `Error::OverQuota` when the new entry doesn't fit, as `try_put` does.
+    pub fn get_or_insert_with<F>(&mut self, key: K, default: F) -> Result<&V, Error>
+    where
+        F: FnOnce() -> V,
+    {
+        if self.contains_exact(&key) {
+            if let Some(lru) = &self.eviction {
+                lru.touch(&key);
+            }
+        } else {
+            self.try_put(key.clone(), default())?;
+        }
+        // eviction drops the oldest entries first, never the one just written
+        Ok(self
+            .store
+            .get(&key)
+            .expect("entry was just checked or stored"))
+    }
+
+    /// Stores an entry that already passed the quota check
+    pub(crate) fn put_entry(&mut self, key: K, value: V) -> Option<V> {
+        self.counters.record_insert();
+        if self.changes.is_some() {
+            let kind = if self.contains_exact(&key) {
+                changes::ChangeKind::Updated
+            } else {
+                changes::ChangeKind::Inserted
+            };
+            self.notify(key.clone(), kind);
+        }
+        if self.deadlines.expired(&key) {
+            if let Some(old) = self.store.get(&key) {
+                self.hooks.expired(&key, old);
+            }
+        }
+        self.deadlines.clear(&key);
+        if let Some(lru) = &self.eviction {
+            lru.insert(key.clone());
+        }
+        if let Some(quota) = &mut self.quota {
+            let freed = self.store.get(&key).map_or(0, |old| quota.size(&key, old));
+            quota.used = quota.used - freed + quota.size(&key, &value);
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.mark(key.clone());
+        }
+        self.indexes
+            .update(&key, self.store.get(&key), Some(&value));
+        if let Some(text) = &mut self.text_index {
+            text.update(&key, self.store.get(&key), Some(&value));
+        }
+        if let Some(history) = &mut self.history {
+            history.record(key.clone(), value.clone());
+        }
+        if let Some(changelog) = &mut self.changelog {
+            changelog.record(key.clone(), Some(value.clone()));
+        }
+        if let Some(quotas) = &mut self.bucket_quotas {
+            quotas.update(&key, self.store.get(&key), Some(&value));
+        }
+        let replaced = if self.hooks.observes_inserts() {
+            let replaced = self.store.insert(key.clone(), value);
+            let stored = self.store.get(&key).expect("entry was just stored");
+            self.hooks.inserted(&key, stored);
+            replaced
+        } else {
+            self.store.insert(key, value)
+        };
+        self.evict();
+        replaced
+    }
+
+    /// Removes `key`, returning its value if it was present
+    ///
+    /// An expired entry reads as absent, so it is purged instead and `None`
+    /// is returned.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + FoldCase + ?Sized,
+    {
+        let key = self.fold_query(key);
+        self.remove_exact(&*key)
+    }
+
+    /// Like `remove`, with `key` already as stored
+    pub(crate) fn remove_exact<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.deadlines.expired(key) {
+            self.expire(key);
+            return None;
+        }
+        self.take_entry(key).map(|(_, value)| value)
+    }
+
+    /// Drops the entry under `key`, keeping every index and counter in step
+    pub(crate) fn take_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.deadlines.clear(key);
+        if let Some(lru) = &self.eviction {
+            lru.forget(key);
+        }
+        let (key, value) = self.store.remove_entry(key)?;
+        if let Some(journal) = &mut self.journal {
+            journal.mark_removed();
+        }
+        if let Some(quota) = &mut self.quota {
+            quota.used -= quota.size(&key, &value);
+        }
+        self.counters.record_delete();
+        self.indexes.update(&key, Some(&value), None);
+        if let Some(text) = &mut self.text_index {
+            text.update(&key, Some(&value), None);
+        }
+        if let Some(changelog) = &mut self.changelog {
+            changelog.record(key.clone(), None);
+        }
+        if let Some(quotas) = &mut self.bucket_quotas {
+            quotas.update(&key, Some(&value), None);
+        }
+        self.hooks.removed(&key, &value);
+        self.notify(key.clone(), changes::ChangeKind::Deleted);
+        Some((key, value))
+    }
+
+    /// Makes room for at least `additional` more entries without rehashing
+    pub fn reserve(&mut self, additional: usize) {
+        self.store.reserve(additional);
+    }
+
+    /// Removes every entry, as `remove` would one at a time
+    pub fn clear(&mut self) {
+        let keys: Vec<K> = self.store.iter().map(|(key, _)| key.clone()).collect();
+        for key in &keys {
+            self.remove_exact(key);
+        }
+    }
+
+    /// Returns true when `key` is stored
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + FoldCase + ?Sized,
+    {
+        let key = self.fold_query(key);
+        self.contains_exact(&*key)
+    }
+
+    pub(crate) fn contains_exact<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.store.contains_key(key) && !self.deadlines.expired(key)
+    }
+
+    /// Every live entry, in unspecified order
+    pub(crate) fn live_entries(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.live_at(Instant::now())
+    }
+
+    /// Every entry still live at `now`, in unspecified order
+    pub(crate) fn live_at(&self, now: Instant) -> impl Iterator<Item = (&K, &V)> {
+        self.store
+            .iter()
+            .filter(move |(key, _)| !self.deadlines.expired_at(*key, now))
+    }
+
+    pub(crate) fn notify(&mut self, key: K, kind: changes::ChangeKind) {
+        if let Some(changes) = &mut self.changes {
+            changes.notify(key, kind);
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Loads data from a file into a database hashing keys with `hasher`
+    ///
+    /// Lines are streamed through one reused buffer, so memory use is the
+    /// database itself rather than a copy of the whole file. A first pass
+    /// counts the lines so the store is sized once instead of rehashing.
+    pub fn load_from_file_with_hasher(path: &str, hasher: S) -> io::Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let lines = persist::count_lines(&mut file)?;
+        let mut reader = io::BufReader::new(file);
+        let mut db = Self::with_hasher(hasher);
+        db.reserve(lines);
+        let mut checksums = persist::Checksums::saved_file();
+        let mut buffer = String::new();
+        let mut number = 0;
+        while reader.read_line(&mut buffer)? > 0 {
+            number += 1;
+            let line = checksums.verify(number, &buffer)?;
+            if !line.is_empty() {
+                let (key, value) = checksums
+                    .decode(line)
+                    .map_err(|e| persist::invalid_line(number, e))?;
+                db.store.insert(key.into(), stored(value));
+            }
+            buffer.clear();
+        }
+        Ok(db)
+    }
+
+    /// Saves data to a file
+    pub fn save_to_file(&self, path: &str) -> io::Result<()> {
+        self.save_with(path, &persist::SaveOptions::default())
+            .map(|_| ())
+    }
+}
+
+impl<S: BuildHasher + Clone> Database for StrDatabase<S> {
+    /// Stores the entry; see `try_insert` for databases with a byte limit
+    /// or key policy
+    fn insert(&mut self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        let key = self.fold_owned_key(key.into());
+        if self.check_key(&key).is_ok() {
+            self.put(key, stored(value));
+        }
+    }
+
+    fn retrieve(&self, key: &str) -> Option<&str> {
+        self.get(&*self.fold_key(key)).map(|value| &**value)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        StrDatabase::iter(self)
+    }
+
+    fn delete(&mut self, key: &str) -> bool {
+        let key = self.fold_key(key).into_owned();
+        self.remove(&*key).is_some()
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        MemoryDatabase::contains_key(self, &*self.fold_key(key))
+    }
+
+    fn len(&self) -> usize {
+        MemoryDatabase::len(self)
+    }
+
+    fn clear(&mut self) {
+        MemoryDatabase::clear(self);
+    }
+
+    /// Fails with `Error::OverQuota` when a refusing byte limit is full
+    fn try_insert(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+    ) -> Result<(), Error> {
+        StrDatabase::try_insert(self, key, value)
+    }
+}
+
+/// Configuration for the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerConfig {
+    /// Borrowed for the built-in default, owned once read from a file
+    pub host: Cow<'static, str>,
+    pub port: u16,
+    pub timeout_ms: u64,
+    pub max_connections: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: Cow::Borrowed("127.0.0.1"),
+            port: 8080,
+            timeout_ms: 5000,
+            max_connections: 100,
+        }
+    }
+}
+
+/// Enum for database operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "op", rename_all = "snake_case"))]
+pub enum DbOperation {
+    Insert { key: Arc<str>, value: Arc<str> },
+    Retrieve { key: Arc<str> },
+    Delete { key: Arc<str> },
+    Update { key: Arc<str>, value: Arc<str> },
+    Append { key: Arc<str>, value: Arc<str> },
+    Increment { key: Arc<str>, by: i64 },
+}
+
+impl DbOperation {
+    /// The key the operation reads or writes
+    pub fn key(&self) -> &Arc<str> {
+        match self {
+            DbOperation::Insert { key, .. }
+            | DbOperation::Retrieve { key }
+            | DbOperation::Delete { key }
+            | DbOperation::Update { key, .. }
+            | DbOperation::Append { key, .. }
+            | DbOperation::Increment { key, .. } => key,
+        }
+    }
+
+    /// Execute the operation on the database
+    ///
+    /// Works through the `Database` trait alone, so any store can run it.
+    pub fn execute<D: Database + ?Sized>(&self, db: &mut D) -> Result<String, String> {
+        self.try_execute(db).map_err(|e| e.to_string())
+    }
+
+    /// Like `execute`, keeping why it failed as an `Error`
+    pub fn try_execute<D: Database + ?Sized>(&self, db: &mut D) -> Result<String, Error> {
+        let started = perf::start();
+        let result = match self {
+            DbOperation::Insert { key, value } => db
+                .try_insert(Arc::clone(key), Arc::clone(value))
+                .map(|()| format!("Inserted: {} = {}", key, value)),
+            DbOperation::Retrieve { key } => retrieve_message(key, db.retrieve(key)),
+            DbOperation::Delete { key } => {
+                if db.delete(key) {
+                    Ok(format!("Deleted: {}", key))
+                } else {
+                    Err(Error::NotFound(key.to_string()))
+                }
+            }
+            DbOperation::Update { key, value } => {
+                if db.contains_key(key) {
+                    db.try_insert(Arc::clone(key), Arc::clone(value))
+                        .map(|()| format!("Updated: {} = {}", key, value))
+                } else {
+                    Err(Error::NotFound(key.to_string()))
+                }
+            }
+            DbOperation::Append { key, value } => db
+                .merge(Arc::clone(key), Arc::clone(value), |old, new| {
+                    old.to_owned() + new
+                })
+                .map(|appended| format!("Appended: {} = {}", key, appended)),
+            DbOperation::Increment { key, by } => {
+                match db.retrieve(key).map_or(Ok(0), str::parse::<i64>) {
+                    Ok(current) => match current.checked_add(*by) {
+                        Some(next) => db
+                            .try_insert(Arc::clone(key), next.to_string())
+                            .map(|()| format!("Incremented: {} = {}", key, next)),
+                        None => Err(Error::Overflow(key.to_string())),
+                    },
+                    Err(_) => Err(Error::NotAnInteger(key.to_string())),
+                }
+            }
+        };
+        perf::record_op(perf::OpKind::from(self), started);
+        result
+    }
+
+    /// Runs a read-only operation through a shared borrow, or `None` for writes
+    pub fn execute_read<D: Database + ?Sized>(&self, db: &D) -> Option<Result<String, String>> {
+        self.execute_lookup(|key| db.retrieve(key))
+    }
+
+    /// Like `execute_read`, reading through `retrieve` instead of a `Database`
+    pub(crate) fn execute_lookup<'a>(
+        &self,
+        retrieve: impl FnOnce(&str) -> Option<&'a str>,
+    ) -> Option<Result<String, String>> {
+        let DbOperation::Retrieve { key } = self else {
+            return None;
+        };
+        let started = perf::start();
+        let result = retrieve_message(key, retrieve(key)).map_err(|e| e.to_string());
+        perf::record_op(perf::OpKind::from(self), started);
+        Some(result)
+    }
+}
+
+fn retrieve_message(key: &str, value: Option<&str>) -> Result<String, Error> {
+    match value {
+        Some(value) => Ok(format!("Retrieved: {} = {}", key, value)),
+        None => Err(Error::NotFound(key.to_string())),
+    }
+}
+
+/// Processing function with lifetime annotations
+pub fn process_data<'a>(input: &'a str, config: &ServerConfig) -> Result<&'a str, String> {
+    const MAX_SIZE: usize = 1024 * 1024;
+
+    if input.len() > MAX_SIZE {
+        return Err(format!("Input exceeds max size: {}", MAX_SIZE));
+    }
+
+    if config.timeout_ms == 0 {
+        return Err("Invalid timeout".to_string());
+    }
+
+    Ok(input)
+}
+
+/// Macros for debugging
+#[macro_export]
+macro_rules! debug_print {
+    ($($arg:tt)*) => {
+        #[cfg(debug_assertions)]
+        println!($($arg)*);
+    };
+}
+
+#[macro_export]
+macro_rules! assert_db_insert {
+    ($db:expr, $key:expr, $value:expr) => {
+        $db.insert($key.to_string(), $value.to_string());
+        assert!($db.retrieve($key).is_some());
+    };
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: perf::CountingAlloc<std::alloc::System> = perf::CountingAlloc(std::alloc::System);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_database() {
+        let mut db = MemoryDatabase::new();
+        let key = "username".to_string();
+        let value = "admin".to_string();
+
+        db.insert(key.clone(), value.clone());
+
+        match db.retrieve(&key) {
+            Some(retrieved_value) => {
+                assert_eq!(retrieved_value, &value);
+                println!("Test passed: {} = {}", key, retrieved_value);
+            }
+            None => panic!("Key not found"),
+        }
+    }
+
+    #[test]
+    fn test_server_config() {
+        let config = ServerConfig::default();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.max_connections, 100);
+        debug_print!("Config: {:?}", config);
+    }
+
+    #[test]
+    fn test_db_operations() {
+        let mut db = MemoryDatabase::new();
+
+        let insert_op = DbOperation::Insert {
+            key: "test_key".into(),
+            value: "test_value".into(),
+        };
+
+        match insert_op.execute(&mut db) {
+            Ok(msg) => println!("Operation result: {}", msg),
+            Err(e) => panic!("Operation failed: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_operations_share_strings() {
+        let mut db = MemoryDatabase::new();
+        let key: Arc<str> = "shared_key".into();
+        let value: Arc<str> = "shared_value".into();
+
+        let insert_op = DbOperation::Insert {
+            key: Arc::clone(&key),
+            value: Arc::clone(&value),
+        };
+        insert_op.execute(&mut db).unwrap();
+
+        let (stored_key, stored_value) = db.store.get_key_value("shared_key").unwrap();
+        assert!(Arc::ptr_eq(stored_key, &key));
+        #[cfg(not(feature = "compact-values"))]
+        assert!(Arc::ptr_eq(stored_value, &value));
+        #[cfg(feature = "compact-values")]
+        assert_eq!(&**stored_value, &*value);
+    }
+
+    #[test]
+    fn test_bulk_inserts_copy_no_strings() {
+        let ops: Vec<DbOperation> = (0..100)
+            .map(|i| DbOperation::Insert {
+                key: format!("key-{}", i).into(),
+                value: format!("value-{}", i).into(),
+            })
+            .collect();
+        let mut db = MemoryDatabase::new();
+        for op in &ops {
+            op.execute(&mut db).unwrap();
+        }
+
+        // spare room keeps a full table from growing on the rewrites
+        db.reserve(ops.len());
+        let mut rewrite = |copy: bool| {
+            let entries = ops.iter().map(|op| match op {
+                DbOperation::Insert { key, value } => (key, value),
+                _ => unreachable!(),
+            });
+            perf::count_allocations(|| {
+                for (key, value) in entries {
+                    if copy {
+                        db.insert(key.to_string(), value.to_string());
+                    } else {
+                        db.insert(Arc::clone(key), Arc::clone(value));
+                    }
+                }
+            })
+            .1
+        };
+        assert_eq!(rewrite(false), 0);
+        assert!(rewrite(true) >= 200);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut counts: MemoryDatabase<&str, u32> =
+            MemoryDatabase::with_capacity_policy(2, eviction::EvictionPolicy::Lru);
+        let mut calls = 0;
+        for word in ["a", "b", "a", "a"] {
+            let count = *counts
+                .get_or_insert_with(word, || {
+                    calls += 1;
+                    0
+                })
+                .unwrap();
+            counts.put(word, count + 1);
+        }
+        assert_eq!(calls, 2);
+        assert_eq!(counts.get("a"), Some(&3));
+
+        counts.get_or_insert_with("c", || 0).unwrap();
+        assert_eq!(counts.get("b"), None);
+
+        let mut db: StrDatabase = MemoryDatabase::with_max_bytes(4, quota::QuotaPolicy::Refuse);
+        assert!(db
+            .get_or_insert_with("key".into(), || "long".into())
+            .is_err());
+        assert_eq!(
+            &**db.get_or_insert_with("k".into(), || "v".into()).unwrap(),
+            "v"
+        );
+    }
+
+    #[test]
+    fn test_merge_combines_with_existing() {
+        let add = |old: &str, new: &str| {
+            let sum = old.parse::<u64>().unwrap_or(0) + new.parse::<u64>().unwrap_or(0);
+            sum.to_string()
+        };
+        let mut db = MemoryDatabase::new();
+        assert_eq!(&*db.merge("hits", "2", add).unwrap(), "2");
+        assert_eq!(&*db.merge("hits", "3", add).unwrap(), "5");
+        assert_eq!(db.retrieve("hits"), Some("5"));
+
+        let mut db = MemoryDatabase::with_max_bytes(6, quota::QuotaPolicy::Refuse);
+        db.insert("k", "abc");
+        let concat = |old: &str, new: &str| format!("{}{}", old, new);
+        assert!(db.merge("k", "defg", concat).is_err());
+        assert_eq!(db.retrieve("k"), Some("abc"));
+    }
+
+    #[test]
+    fn test_retrieve_owned_outlives_database() {
+        let owned = {
+            let mut db = MemoryDatabase::new();
+            db.insert("k", "v");
+            assert_eq!(db.retrieve_owned("missing"), None);
+            db.retrieve_owned("k")
+        };
+        assert_eq!(owned.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_batch_operations() {
+        fn batch<D: Database>(db: &mut D) -> Vec<Option<&str>> {
+            db.insert_many([("a", "1"), ("b", "2"), ("a", "3")]);
+            db.retrieve_many(&["a", "missing", "b"])
+        }
+
+        let expected = [Some("3"), None, Some("2")];
+        assert_eq!(batch(&mut MemoryDatabase::new()), expected);
+        assert_eq!(batch(&mut sorted::SortedDatabase::new()), expected);
+    }
+
+    #[test]
+    fn test_operations_run_on_any_database() {
+        fn run<D: Database>(db: &mut D) -> Vec<Result<String, String>> {
+            let ops = [
+                DbOperation::Append {
+                    key: "log".into(),
+                    value: "a".into(),
+                },
+                DbOperation::Append {
+                    key: "log".into(),
+                    value: "b".into(),
+                },
+                DbOperation::Delete { key: "log".into() },
+                DbOperation::Delete { key: "log".into() },
+            ];
+            ops.iter().map(|op| op.execute(db)).collect()
+        }
+
+        let mut sorted = sorted::SortedDatabase::new();
+        let results = run(&mut sorted);
+        assert_eq!(results, run(&mut MemoryDatabase::new()));
+        assert_eq!(results[1], Ok("Appended: log = ab".to_string()));
+        assert_eq!(results[3], Err("Key not found: log".to_string()));
+
+        sorted.insert_many([("a", "1"), ("b", "2")]);
+        assert!(Database::contains_key(&sorted, "a"));
+        assert_eq!(Database::len(&sorted), 2);
+        Database::clear(&mut sorted);
+        assert!(Database::is_empty(&sorted));
+    }
+
+    #[test]
+    fn test_typed_keys_and_values() {
+        let mut blobs: MemoryDatabase<u64, Vec<u8>> = MemoryDatabase::default();
+        assert!(blobs.is_empty());
+        assert_eq!(blobs.put(7, vec![0xde, 0xad]), None);
+        assert_eq!(blobs.put(7, vec![0xbe, 0xef]), Some(vec![0xde, 0xad]));
+        assert_eq!(blobs.get(&7), Some(&vec![0xbe, 0xef]));
+        assert!(blobs.contains_key(&7));
+        assert_eq!(blobs.remove(&7), Some(vec![0xbe, 0xef]));
+        assert_eq!(blobs.len(), 0);
+
+        let mut names: MemoryDatabase<String, Arc<ServerConfig>> = MemoryDatabase::default();
+        names.put("local".to_string(), Arc::new(ServerConfig::default()));
+        assert_eq!(names.get("local").map(|config| config.port), Some(8080));
+    }
+
+    #[test]
+    fn test_custom_hasher() {
+        type Fixed = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+        let mut db = MemoryDatabase::with_hasher(Fixed::default());
+
+        assert_db_insert!(db, "language", "Rust");
+        let update_op = DbOperation::Update {
+            key: "language".into(),
+            value: "Rust 2021".into(),
+        };
+        assert!(update_op.execute(&mut db).is_ok());
+        assert_eq!(db.retrieve("language"), Some("Rust 2021"));
+    }
+
+    #[test]
+    fn test_read_modify_write_operations() {
+        let mut db = MemoryDatabase::new();
+        let incr = DbOperation::Increment {
+            key: "visits".into(),
+            by: 2,
+        };
+        incr.execute(&mut db).unwrap();
+        assert_eq!(
+            incr.execute(&mut db),
+            Ok("Incremented: visits = 4".to_string())
+        );
+
+        let append = DbOperation::Append {
+            key: "visits".into(),
+            value: "x".into(),
+        };
+        append.execute(&mut db).unwrap();
+        assert_eq!(db.retrieve("visits"), Some("4x"));
+        assert_eq!(
+            incr.execute(&mut db),
+            Err("Not an integer: visits".to_string())
+        );
+    }
+}
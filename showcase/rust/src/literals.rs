@@ -0,0 +1,142 @@
+//! Seed data, file magic and value escaping built from string and byte literals
+use crate::transfer::Backup;
+use crate::MemoryDatabase;
+
+/// Bytes every binary database file starts with
+pub const MAGIC: &[u8; 4] = b"GVS\x01";
+
+/// Line ending written by the text format, as raw bytes
+pub const NEWLINE: &[u8] = b"\n";
+
+/// Bytes that must be escaped inside a stored value
+pub const SPECIAL_BYTES: &[u8] = br":\";
+
+/// Version banner the binaries print for `--version`
+pub const BANNER: &str = concat!(
+    "gvs-showcase ",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    file!(),
+    ")"
+);
+
+/// Entries loaded into a fresh database by `MemoryDatabase::seeded`,
+/// in the escaped, checksummed `save_to_file` format
+pub const SEED: &str = include_str!("../data/seed.db");
+
+/// The crab Rustaceans use as a mascot
+pub const CRAB: char = '\u{1F980}';
+
+/// `--help` text; `###` lets it quote the `"#` sequences shown in the examples
+pub const USAGE: &str = r###"usage: gvs-showcase [diff <a> <b> | cluster <endpoint>... | --version | --help]
+
+  (no arguments)       run the tour
+  diff <a> <b>         compare two saved databases
+  cluster <endpoint>   show which gvs-server nodes answer and what they own
+  --version            print the version
+
+gvs-repl takes commands on stdin, e.g. set motto "#1 crab"; values
+containing "#" or quotes need no escaping: r##"raw"## style.
+"###;
+
+/// Escapes `\`, `:`, newlines and control characters so a value fits on one line
+pub fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\\"),
+            ':' => escaped.push_str(r"\:"),
+            '\n' => escaped.push_str(r"\n"),
+            '\t' => escaped.push_str(r"\t"),
+            '\x00'..='\x1f' | '\x7f' => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape_value`, or returns `None` for a malformed escape
+pub fn unescape_value(escaped: &str) -> Option<String> {
+    let mut value = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => value.push('\\'),
+            ':' => value.push(':'),
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let (hex, tail) = rest.split_once('}')?;
+                value.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+                chars = tail.chars();
+            }
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Returns true when `bytes` starts with the binary format's `MAGIC`
+pub fn has_magic(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Returns true when `value` contains a byte from `SPECIAL_BYTES`
+pub fn needs_escaping(value: &str) -> bool {
+    value
+        .bytes()
+        .any(|b| SPECIAL_BYTES.contains(&b) || b.is_ascii_control())
+}
+
+impl MemoryDatabase {
+    /// A database preloaded with the entries in `SEED`
+    pub fn seeded() -> Self {
+        let mut db = Self::new();
+        db.restore_from(SEED.as_bytes())
+            .expect("data/seed.db is well formed");
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_literal_constants() {
+        assert_eq!(MAGIC, &[0x47, 0x56, 0x53, 0x01]);
+        assert!(has_magic(b"GVS\x01rest"));
+        assert!(!has_magic(br"GVS\x01"));
+        assert_eq!(SPECIAL_BYTES, b":\\");
+        assert_eq!(CRAB, '🦀');
+        assert_eq!("\u{4e16}\u{754c}", "世界");
+        let version = format!("gvs-showcase {} (", crate::generated::VERSION);
+        assert!(BANNER.starts_with(&version));
+        assert!(USAGE.contains(r##"set motto "#1 crab""##));
+
+        let db = MemoryDatabase::seeded();
+        assert_eq!(db.retrieve("crab"), Some("🦀"));
+        assert_eq!(db.retrieve("path"), Some(r"C:\Users\ferris"));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/seed.db");
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert!(loaded.diff(&db).is_empty());
+    }
+
+    #[test]
+    fn test_escape_round_trip() {
+        let value = "a:b\\c\nd\te\x07";
+        let escaped = escape_value(value);
+        assert_eq!(escaped, r"a\:b\\c\nd\te\u{7}");
+        assert!(!escaped.contains('\n'));
+        assert_eq!(unescape_value(&escaped).as_deref(), Some(value));
+        assert!(needs_escaping("key:value") && !needs_escaping("plain"));
+        assert_eq!(unescape_value(r"bad\q"), None);
+        assert_eq!(unescape_value(r"\u{110000}"), None);
+    }
+}
@@ -0,0 +1,110 @@
+// This is a comprehensive Rust example showcasing enhanced theme colors
+use gvs_showcase::cluster::{ClusterClient, Router};
+use gvs_showcase::{
+    debug_print, literals, perf, process_data, Database, MemoryDatabase, ServerConfig,
+};
+
+#[global_allocator]
+static ALLOCATOR: perf::CountingAlloc<std::alloc::System> = perf::CountingAlloc(std::alloc::System);
+
+fn main() {
+    // GVS_PERF=1 turns the counters on and dumps them to stderr on exit
+    if std::env::var_os("GVS_PERF").is_some() {
+        perf::enable();
+    }
+
+    // `diff <a> <b>` compares two persisted databases and `cluster <endpoint>...`
+    // shows the dashboard for a set of servers instead of running the tour
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("--version") => {
+            println!("{}", literals::BANNER);
+            return;
+        }
+        Some("--help") => {
+            print!("{}", literals::USAGE);
+            return;
+        }
+        _ => {}
+    }
+    if let [_, command, a, b] = args.as_slice() {
+        if command == "diff" {
+            match MemoryDatabase::diff_files(a, b) {
+                Ok(diff) => print!("{}", diff),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    }
+    if let [_, command, endpoints @ ..] = args.as_slice() {
+        if command == "cluster" && !endpoints.is_empty() {
+            let mut router = Router::new();
+            for endpoint in endpoints {
+                router.add_node(endpoint);
+            }
+            print!("{}", ClusterClient::new(router).dashboard());
+            return;
+        }
+    }
+
+    println!("🦀 Rust Theme Showcase");
+    println!("======================\n");
+
+    // Numbers with different formats
+    let decimal: i32 = 42;
+    let hex: u32 = 0xFF_FF_FF_FF;
+    #[allow(unused_variables)]
+    let octal: u8 = 0o77;
+    #[allow(unused_variables)]
+    let binary: u16 = 0b1111_0000;
+    #[allow(clippy::approx_constant)]
+    let float: f64 = 3.14159;
+
+    println!("Decimal: {}", decimal);
+    println!("Hex: 0x{:X}", hex);
+    println!("Float: {:.5}", float);
+
+    // String examples
+    let name = "Rust";
+    let greeting = format!("Welcome to {}", name);
+    let raw_string = r#"Raw string with "quotes" and \backslashes"#;
+
+    println!("{}", greeting);
+    println!("{}", raw_string);
+
+    // Database example
+    let mut database = MemoryDatabase::new();
+    database.insert("language".to_string(), "Rust".to_string());
+    database.insert("year".to_string(), "2010".to_string());
+
+    if let Some(value) = database.retrieve("language") {
+        println!("Language: {}", value);
+    }
+
+    // Configuration
+    let config = ServerConfig::default();
+    println!("Server running at {}:{}", config.host, config.port);
+
+    // Closures with lifetimes
+    #[allow(clippy::useless_vec)]
+    let data = vec![1, 2, 3, 4, 5];
+    #[allow(clippy::unnecessary_fold)]
+    let sum: u32 = data.iter().fold(0, |acc, &x| acc + x);
+    println!("Sum: {}", sum);
+
+    // Match statement
+    let result = process_data("Hello, Rust!", &config);
+    match result {
+        Ok(data) => println!("Processed: {}", data),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+
+    debug_print!("Debug info: {:?}", config);
+
+    if perf::is_enabled() {
+        eprintln!("{}", perf::report().to_json());
+    }
+}
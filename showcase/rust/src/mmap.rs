@@ -0,0 +1,192 @@
+//! Read-only databases served from a memory-mapped file
+//!
+//! `MmapDatabase::open` maps a file in the `persist` line format and decodes
+//! only its keys, remembering where each line sits. A value is checked
+//! against its checksum and unescaped the first time it is read, then
+//! cached, so records that are never read cost no more than their key.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::str;
+use std::sync::OnceLock;
+
+use memmap2::Mmap;
+
+use crate::persist::{self, Checksums};
+
+/// Where one entry's line sits in the mapped file
+#[derive(Debug)]
+struct Record {
+    number: usize,
+    bytes: Range<usize>,
+    value: OnceLock<Box<str>>,
+}
+
+/// A read-only database over a memory-mapped saved file
+///
+/// The file must not be modified while it is open: the mapping would see
+/// the change, and truncating the file faults on the next read.
+#[derive(Debug)]
+pub struct MmapDatabase {
+    map: Mmap,
+    checksums: Checksums,
+    keys: HashMap<Box<str>, Record>,
+}
+
+impl MmapDatabase {
+    /// Maps the file at `path` and indexes its keys
+    ///
+    /// Fails with `InvalidData` naming the first line whose key is malformed;
+    /// a bad value or checksum is only reported when that entry is read.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the caller keeps the file unmodified while it is mapped
+        let map = unsafe { Mmap::map(&file)? };
+        let mut checksums = Checksums::saved_file();
+        let mut keys = HashMap::new();
+        let mut start = 0;
+        for (index, raw) in map.split(|&byte| byte == b'\n').enumerate() {
+            let number = index + 1;
+            let offset = start;
+            start += raw.len() + 1;
+            let line = str::from_utf8(raw)
+                .map_err(|_| persist::invalid_line(number, "Invalid UTF-8".to_string()))?;
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+            if keys.is_empty() {
+                // the first line decides whether the file is checksummed
+                checksums = checksums.at(offset as u64);
+                checksums.verify(number, line)?;
+            }
+            let key = checksums
+                .decode_key(line)
+                .map_err(|e| persist::invalid_line(number, e))?;
+            let record = Record {
+                number,
+                bytes: offset..offset + line.len(),
+                value: OnceLock::new(),
+            };
+            keys.insert(key.into_boxed_str(), record);
+        }
+        Ok(Self {
+            map,
+            checksums,
+            keys,
+        })
+    }
+
+    /// Reads the value stored under `key`, decoding it on first access
+    pub fn retrieve(&self, key: &str) -> io::Result<Option<&str>> {
+        self.keys
+            .get(key)
+            .map(|record| self.value(record))
+            .transpose()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Iterates over every key without decoding any value
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(|key| &**key)
+    }
+
+    /// Iterates over every entry, decoding values not read before
+    pub fn iter(&self) -> impl Iterator<Item = io::Result<(&str, &str)>> {
+        self.keys
+            .iter()
+            .map(|(key, record)| Ok((&**key, self.value(record)?)))
+    }
+
+    fn value<'a>(&'a self, record: &'a Record) -> io::Result<&'a str> {
+        if let Some(value) = record.value.get() {
+            return Ok(value);
+        }
+        let line = str::from_utf8(&self.map[record.bytes.clone()]).expect("checked by open");
+        let body = self
+            .checksums
+            .at(record.bytes.start as u64)
+            .verify(record.number, line)?;
+        let (_, value) = self
+            .checksums
+            .decode(body)
+            .map_err(|e| persist::invalid_line(record.number, e))?;
+        Ok(record.value.get_or_init(|| value.into_boxed_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, Error, MemoryDatabase};
+
+    #[test]
+    fn test_reads_saved_entries_lazily() {
+        let path = std::env::temp_dir().join("gvs_mmap_read.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("user:1", "alice");
+        db.insert("a:b", "multi\nline\tvalue");
+        db.save_incremental(path).unwrap();
+        db.insert("user:1", "bob");
+        db.save_incremental(path).unwrap();
+
+        let mapped = MmapDatabase::open(path).unwrap();
+        assert_eq!(mapped.len(), 2);
+        assert!(mapped
+            .keys
+            .values()
+            .all(|record| record.value.get().is_none()));
+        assert_eq!(mapped.retrieve("user:1").unwrap(), Some("bob"));
+        assert_eq!(mapped.retrieve("a:b").unwrap(), Some("multi\nline\tvalue"));
+        assert_eq!(mapped.retrieve("missing").unwrap(), None);
+        let mut entries: Vec<_> = mapped.iter().collect::<io::Result<_>>().unwrap();
+        entries.sort();
+        assert_eq!(entries, [("a:b", "multi\nline\tvalue"), ("user:1", "bob")]);
+
+        std::fs::write(path, "legacy:1\n\nplain:2\n").unwrap();
+        let mapped = MmapDatabase::open(path).unwrap();
+        assert_eq!(mapped.retrieve("plain").unwrap(), Some("2"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_corruption_surfaces_on_read() {
+        let path = std::env::temp_dir().join("gvs_mmap_corrupt.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("good", "fine");
+        db.save_to_file(path).unwrap();
+        let mut contents = std::fs::read_to_string(path).unwrap();
+        contents.push_str(&contents.replace("good:fine", "bad:fin3"));
+        std::fs::write(path, &contents).unwrap();
+
+        let mapped = MmapDatabase::open(path).unwrap();
+        assert_eq!(mapped.retrieve("good").unwrap(), Some("fine"));
+        let err = mapped.retrieve("bad").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let offset = contents.find("bad").unwrap() as u64;
+        assert!(matches!(
+            err.get_ref().and_then(|e| e.downcast_ref::<Error>()),
+            Some(&Error::Corrupted { line: 2, offset: o }) if o == offset
+        ));
+
+        let lines = ["ok:1", "bad\\q:2"].map(persist::with_checksum);
+        std::fs::write(path, lines.join("\n")).unwrap();
+        let err = MmapDatabase::open(path).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: Malformed escape in key");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+//! Node.js bindings for the in-memory database (enabled with the `napi` feature)
+//!
+//! File and network access run on the tokio blocking pool so the JavaScript
+//! event loop never waits on disk or on `gvs-server`.
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::client::Client;
+use crate::{perf, Database, DbOperation, MemoryDatabase};
+
+fn to_napi_error(err: impl std::fmt::Display) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+/// JavaScript wrapper around a shared `MemoryDatabase`
+#[napi(js_name = "MemoryDatabase")]
+pub struct JsMemoryDatabase {
+    inner: Arc<Mutex<MemoryDatabase>>,
+}
+
+impl JsMemoryDatabase {
+    fn lock(&self) -> MutexGuard<'_, MemoryDatabase> {
+        let waiting = perf::start();
+        let guard = self.inner.lock().unwrap();
+        perf::record_lock_wait(waiting);
+        guard
+    }
+}
+
+impl Default for JsMemoryDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[napi]
+impl JsMemoryDatabase {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(MemoryDatabase::new())),
+        }
+    }
+
+    #[napi]
+    pub fn insert(&self, key: String, value: String) {
+        self.lock().insert(key, value);
+    }
+
+    #[napi]
+    pub fn retrieve(&self, key: String) -> Option<String> {
+        self.lock().retrieve(&key).map(str::to_string)
+    }
+
+    /// Runs an `insert`, `retrieve`, `delete` or `update` operation
+    #[napi]
+    pub fn execute(&self, op: String, key: String, value: Option<String>) -> Result<String> {
+        let op = match (op.as_str(), value) {
+            ("insert", Some(value)) => DbOperation::Insert {
+                key: key.into(),
+                value: value.into(),
+            },
+            ("update", Some(value)) => DbOperation::Update {
+                key: key.into(),
+                value: value.into(),
+            },
+            ("retrieve", _) => DbOperation::Retrieve { key: key.into() },
+            ("delete", _) => DbOperation::Delete { key: key.into() },
+            (op, _) => return Err(to_napi_error(format!("Invalid operation: {}", op))),
+        };
+        op.execute(&mut *self.lock()).map_err(to_napi_error)
+    }
+
+    /// Persists the database without blocking the event loop
+    #[napi]
+    pub async fn save(&self, path: String) -> Result<()> {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || inner.lock().unwrap().save_to_file(&path))
+            .await
+            .map_err(to_napi_error)?
+            .map_err(to_napi_error)
+    }
+}
+
+/// Loads a database persisted by `save` or `save_to_file`
+#[napi]
+pub async fn load_database(path: String) -> Result<JsMemoryDatabase> {
+    let db = tokio::task::spawn_blocking(move || MemoryDatabase::load_from_file(&path))
+        .await
+        .map_err(to_napi_error)?
+        .map_err(to_napi_error)?;
+    Ok(JsMemoryDatabase {
+        inner: Arc::new(Mutex::new(db)),
+    })
+}
+
+/// JavaScript wrapper around a `Client` connection to `gvs-server`
+#[napi(js_name = "Client")]
+pub struct JsClient {
+    inner: Arc<Mutex<Client>>,
+}
+
+impl JsClient {
+    /// Runs one request on the blocking pool, one at a time per connection
+    async fn request<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Client) -> std::result::Result<T, crate::client::ClientError>
+            + Send
+            + 'static,
+    ) -> Result<T> {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || f(&mut inner.lock().unwrap()))
+            .await
+            .map_err(to_napi_error)?
+            .map_err(to_napi_error)
+    }
+}
+
+#[napi]
+impl JsClient {
+    #[napi]
+    pub async fn auth(&self, user: String, password: String) -> Result<()> {
+        self.request(move |client| client.auth(&user, &password))
+            .await
+    }
+
+    /// Reads `key`, resolving to `null` when the server doesn't hold it
+    #[napi]
+    pub async fn get(&self, key: String) -> Result<Option<String>> {
+        self.request(move |client| client.get(&key)).await
+    }
+
+    #[napi]
+    pub async fn set(&self, key: String, value: String) -> Result<()> {
+        self.request(move |client| client.set(&key, &value)).await
+    }
+
+    /// Removes `key`, resolving to whether the server held it
+    #[napi]
+    pub async fn delete(&self, key: String) -> Result<bool> {
+        self.request(move |client| client.delete(&key)).await
+    }
+
+    /// Sends a raw REPL command and resolves to the server's reply
+    #[napi]
+    pub async fn command(&self, line: String) -> Result<String> {
+        self.request(move |client| client.command(&line)).await
+    }
+}
+
+/// Connects to a `gvs-server` listening on `addr`
+#[napi]
+pub async fn connect(addr: String) -> Result<JsClient> {
+    let client = tokio::task::spawn_blocking(move || Client::connect(addr))
+        .await
+        .map_err(to_napi_error)?
+        .map_err(to_napi_error)?;
+    Ok(JsClient {
+        inner: Arc::new(Mutex::new(client)),
+    })
+}
@@ -0,0 +1,168 @@
+//! Write limits and durability settings checked on insert
+#![deny(unsafe_code)]
+#![warn(missing_docs)]
+
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use crate::{Database, StrDatabase};
+
+/// Directory databases are kept in when no path is given
+#[cfg(unix)]
+pub const DEFAULT_DATA_DIR: &str = "/var/lib/gvs";
+/// Directory databases are kept in when no path is given
+#[cfg(windows)]
+pub const DEFAULT_DATA_DIR: &str = r"C:\ProgramData\gvs";
+/// Directory databases are kept in when no path is given
+#[cfg(not(any(unix, windows)))]
+pub const DEFAULT_DATA_DIR: &str = "gvs-data";
+
+/// How hard writes try to reach stable storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[non_exhaustive]
+pub enum Durability {
+    /// Kept in memory until the next explicit save
+    #[default]
+    Memory,
+    /// Flushed to the OS after every save
+    Flush,
+    /// Synced to disk after every save
+    Fsync,
+}
+
+/// Limits applied by [`MemoryDatabase::insert_with`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[must_use = "options only take effect when passed to `insert_with`"]
+pub struct DbOptions {
+    /// Longest key, in bytes
+    pub max_key_len: usize,
+    /// Longest value, in bytes
+    pub max_value_len: usize,
+    /// Durability expected from saves
+    pub durability: Durability,
+    /// Rejects every write when set
+    pub read_only: bool,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DbOptions {
+    /// Options with generous limits and in-memory durability
+    pub const fn new() -> Self {
+        Self {
+            max_key_len: 1024,
+            max_value_len: 1024 * 1024,
+            durability: Durability::Memory,
+            read_only: false,
+        }
+    }
+
+    /// Sets the longest accepted key
+    #[inline]
+    pub const fn max_key_len(mut self, len: usize) -> Self {
+        self.max_key_len = len;
+        self
+    }
+
+    /// Sets the longest accepted value
+    #[inline]
+    pub const fn max_value_len(mut self, len: usize) -> Self {
+        self.max_value_len = len;
+        self
+    }
+
+    /// Sets the durability level
+    #[inline]
+    pub const fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Turns writes off entirely
+    #[inline]
+    pub const fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Enables fsync on save
+    #[deprecated(since = "0.2.0", note = "use `durability(Durability::Fsync)` instead")]
+    pub const fn sync_writes(self, enabled: bool) -> Self {
+        self.durability(if enabled {
+            Durability::Fsync
+        } else {
+            Durability::Memory
+        })
+    }
+
+    /// Returns true when `key` and `value` fit within the limits
+    #[must_use]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn permits(&self, key: &str, value: &str) -> bool {
+        self.check(key, value).is_ok()
+    }
+
+    fn check(&self, key: &str, value: &str) -> Result<(), String> {
+        if self.read_only {
+            return Err("Database is read-only".to_string());
+        }
+        if key.len() > self.max_key_len {
+            return Err(format!("Key exceeds {} bytes", self.max_key_len));
+        }
+        if value.len() > self.max_value_len {
+            return Err(format!("Value exceeds {} bytes", self.max_value_len));
+        }
+        Ok(())
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Inserts `value` only if it satisfies `options`
+    pub fn insert_with(
+        &mut self,
+        options: &DbOptions,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+    ) -> Result<(), String> {
+        let (key, value) = (key.into(), value.into());
+        options.check(&key, &value)?;
+        self.insert(key, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_limits_are_enforced() {
+        let options = DbOptions::new().max_key_len(4).max_value_len(8);
+        let mut db = MemoryDatabase::new();
+
+        assert!(db.insert_with(&options, "port", "8080").is_ok());
+        assert_eq!(
+            db.insert_with(&options, "hostname", "x"),
+            Err("Key exceeds 4 bytes".to_string())
+        );
+        assert!(!options.permits("name", "a long value"));
+        assert!(db
+            .insert_with(&options.clone().read_only(true), "port", "1")
+            .is_err());
+        assert_eq!(db.retrieve("port"), Some("8080"));
+    }
+
+    #[test]
+    fn test_deprecated_builder_still_works() {
+        let options = DbOptions::default().sync_writes(true);
+        assert_eq!(options.durability, Durability::Fsync);
+        assert!(Durability::Fsync > Durability::Flush);
+    }
+}
@@ -0,0 +1,166 @@
+//! Command parsing and operation auditing built on pattern matching
+use crate::DbOperation;
+
+/// Longest value accepted from the command line
+pub const MAX_VALUE_LEN: usize = 4096;
+
+/// Parses `get <key>`, `set <key> <value...>`, `update <key> <value...>`,
+/// `append <key> <value...>`, `incr <key> [by]` or `del <key>`
+pub fn parse_command(line: &str) -> Result<DbOperation, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((command, args)) = words.split_first() else {
+        return Err("Empty command".to_string());
+    };
+
+    match (command.to_ascii_lowercase().as_str(), args) {
+        ("get" | "retrieve", [key]) => Ok(DbOperation::Retrieve { key: (*key).into() }),
+        ("del" | "delete", [key]) => Ok(DbOperation::Delete { key: (*key).into() }),
+        ("incr" | "increment", [key]) => Ok(DbOperation::Increment {
+            key: (*key).into(),
+            by: 1,
+        }),
+        ("incr" | "increment", [key, by]) => Ok(DbOperation::Increment {
+            key: (*key).into(),
+            by: by
+                .parse()
+                .map_err(|_| format!("Invalid increment: {}", by))?,
+        }),
+        (command @ ("set" | "insert" | "update" | "append"), [key, value @ ..])
+            if !value.is_empty() =>
+        {
+            let value = value.join(" ");
+            if value.len() > MAX_VALUE_LEN {
+                return Err(format!("Value exceeds {} bytes", MAX_VALUE_LEN));
+            }
+            let (key, value) = ((*key).into(), value.into());
+            Ok(match command {
+                "update" => DbOperation::Update { key, value },
+                "append" => DbOperation::Append { key, value },
+                _ => DbOperation::Insert { key, value },
+            })
+        }
+        (command @ ("get" | "retrieve" | "del" | "delete"), _) => {
+            Err(format!("usage: {} <key>", command))
+        }
+        (command @ ("set" | "insert" | "update" | "append"), _) => {
+            Err(format!("usage: {} <key> <value>", command))
+        }
+        (command @ ("incr" | "increment"), _) => Err(format!("usage: {} <key> [by]", command)),
+        (command, _) => Err(format!("Unknown command: {}", command)),
+    }
+}
+
+/// Key an operation touches
+pub fn key_of(op: &DbOperation) -> &str {
+    match op {
+        DbOperation::Insert { key, .. }
+        | DbOperation::Retrieve { key }
+        | DbOperation::Delete { key }
+        | DbOperation::Update { key, .. }
+        | DbOperation::Append { key, .. }
+        | DbOperation::Increment { key, .. } => key,
+    }
+}
+
+/// Shape of a stored value, guessed from its bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Empty,
+    Boolean,
+    Integer,
+    Quoted,
+    Text,
+}
+
+pub fn classify_value(value: &str) -> ValueKind {
+    match value.as_bytes() {
+        [] => ValueKind::Empty,
+        b"true" | b"false" => ValueKind::Boolean,
+        [b'-' | b'+', digits @ ..] | digits
+            if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) =>
+        {
+            ValueKind::Integer
+        }
+        [b'"', .., b'"'] | [b'\'', .., b'\''] => ValueKind::Quoted,
+        _ => ValueKind::Text,
+    }
+}
+
+/// One-line audit record describing what `op` does and how risky it is
+pub fn audit(op: &DbOperation) -> String {
+    let key = key_of(op);
+    let scope = if let Some(user) = key.strip_prefix("__auth:user:") {
+        format!("account {}", user)
+    } else if let [b'_', b'_', ..] = key.as_bytes() {
+        "internal".to_string()
+    } else {
+        "data".to_string()
+    };
+
+    let action = match op {
+        DbOperation::Retrieve { .. } => "read".to_string(),
+        DbOperation::Delete { key } if key.starts_with("__") => "delete (protected)".to_string(),
+        DbOperation::Delete { .. } => "delete".to_string(),
+        DbOperation::Increment { by, .. } => format!("increment by {}", by),
+        DbOperation::Insert { value, .. }
+        | DbOperation::Update { value, .. }
+        | DbOperation::Append { value, .. } => match (classify_value(value), value.len()) {
+            (ValueKind::Empty, _) => "write empty".to_string(),
+            (kind, len @ 1024..) => format!("write {:?} ({} bytes, large)", kind, len),
+            (kind, len) => format!("write {:?} ({} bytes)", kind, len),
+        },
+    };
+
+    format!("{} {}: {}", scope, key, action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let op = parse_command("SET greeting hello   world").unwrap();
+        let DbOperation::Insert { key, value } = &op else {
+            panic!("expected insert, got {:?}", op);
+        };
+        assert_eq!((&**key, &**value), ("greeting", "hello world"));
+
+        assert!(matches!(
+            parse_command("update year 2021"),
+            Ok(DbOperation::Update { .. })
+        ));
+        assert_eq!(key_of(&parse_command("del year").unwrap()), "year");
+        assert_eq!(parse_command("get"), Err("usage: get <key>".to_string()));
+        assert_eq!(parse_command(" "), Err("Empty command".to_string()));
+        assert!(matches!(
+            parse_command("incr hits -3"),
+            Ok(DbOperation::Increment { by: -3, .. })
+        ));
+        assert!(matches!(
+            parse_command("append log more text"),
+            Ok(DbOperation::Append { .. })
+        ));
+        assert!(parse_command("drop table").is_err());
+    }
+
+    #[test]
+    fn test_classify_and_audit() {
+        assert_eq!(classify_value(""), ValueKind::Empty);
+        assert_eq!(classify_value("false"), ValueKind::Boolean);
+        assert_eq!(classify_value("-42"), ValueKind::Integer);
+        assert_eq!(classify_value("-"), ValueKind::Text);
+        assert_eq!(classify_value("\"quoted\""), ValueKind::Quoted);
+
+        let delete = DbOperation::Delete {
+            key: "__auth:user:ferris".into(),
+        };
+        assert_eq!(
+            audit(&delete),
+            "account ferris __auth:user:ferris: delete (protected)"
+        );
+
+        let insert = parse_command("set port 8080").unwrap();
+        assert_eq!(audit(&insert), "data port: write Integer (4 bytes)");
+    }
+}
@@ -0,0 +1,309 @@
+//! Process-wide performance counters, off until `enable()` is called
+//!
+//! Counters are relaxed atomics, so recording costs one flag check while
+//! disabled. Allocation counts need `CountingAlloc` installed as the global
+//! allocator by the binary.
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::DbOperation;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COUNTERS: Counters = Counters::new();
+
+/// Kind of operation a latency sample belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    Insert,
+    Retrieve,
+    Delete,
+    Update,
+}
+
+impl OpKind {
+    const ALL: [OpKind; 4] = [
+        OpKind::Insert,
+        OpKind::Retrieve,
+        OpKind::Delete,
+        OpKind::Update,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            OpKind::Insert => "insert",
+            OpKind::Retrieve => "retrieve",
+            OpKind::Delete => "delete",
+            OpKind::Update => "update",
+        }
+    }
+}
+
+impl From<&DbOperation> for OpKind {
+    fn from(op: &DbOperation) -> Self {
+        match op {
+            DbOperation::Insert { .. } => OpKind::Insert,
+            DbOperation::Retrieve { .. } => OpKind::Retrieve,
+            DbOperation::Delete { .. } => OpKind::Delete,
+            DbOperation::Update { .. }
+            | DbOperation::Append { .. }
+            | DbOperation::Increment { .. } => OpKind::Update,
+        }
+    }
+}
+
+struct Counters {
+    allocations: AtomicU64,
+    allocated_bytes: AtomicU64,
+    deallocations: AtomicU64,
+    bytes_persisted: AtomicU64,
+    lock_waits: AtomicU64,
+    lock_wait_ns: AtomicU64,
+    op_counts: [AtomicU64; 4],
+    op_ns: [AtomicU64; 4],
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            allocations: AtomicU64::new(0),
+            allocated_bytes: AtomicU64::new(0),
+            deallocations: AtomicU64::new(0),
+            bytes_persisted: AtomicU64::new(0),
+            lock_waits: AtomicU64::new(0),
+            lock_wait_ns: AtomicU64::new(0),
+            op_counts: [const { AtomicU64::new(0) }; 4],
+            op_ns: [const { AtomicU64::new(0) }; 4],
+        }
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Zeroes every counter
+pub fn reset() {
+    let c = &COUNTERS;
+    for counter in [
+        &c.allocations,
+        &c.allocated_bytes,
+        &c.deallocations,
+        &c.bytes_persisted,
+        &c.lock_waits,
+        &c.lock_wait_ns,
+    ]
+    .into_iter()
+    .chain(&c.op_counts)
+    .chain(&c.op_ns)
+    {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Starts a timing sample, or returns `None` while counters are disabled
+pub fn start() -> Option<Instant> {
+    is_enabled().then(Instant::now)
+}
+
+fn nanos(elapsed: Duration) -> u64 {
+    u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Records the latency of an operation started with `start()`
+pub fn record_op(kind: OpKind, started: Option<Instant>) {
+    if let Some(started) = started {
+        COUNTERS.op_counts[kind.index()].fetch_add(1, Ordering::Relaxed);
+        COUNTERS.op_ns[kind.index()].fetch_add(nanos(started.elapsed()), Ordering::Relaxed);
+    }
+}
+
+/// Records the time spent waiting for a lock acquired after `start()`
+pub fn record_lock_wait(started: Option<Instant>) {
+    if let Some(started) = started {
+        COUNTERS.lock_waits.fetch_add(1, Ordering::Relaxed);
+        COUNTERS
+            .lock_wait_ns
+            .fetch_add(nanos(started.elapsed()), Ordering::Relaxed);
+    }
+}
+
+pub fn record_bytes_persisted(bytes: u64) {
+    if is_enabled() {
+        COUNTERS.bytes_persisted.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+thread_local! {
+    static THREAD_ALLOCATIONS: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Runs `f`, returning its result and how many allocations this thread made
+///
+/// Counts only while `CountingAlloc` is the global allocator, whether or not
+/// perf is enabled; other threads' allocations are left out, so the count
+/// holds while tests run in parallel.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let outer = THREAD_ALLOCATIONS.replace(Some(0));
+    let result = f();
+    let counted = THREAD_ALLOCATIONS.take().unwrap_or(0);
+    THREAD_ALLOCATIONS.set(outer.map(|n| n + counted));
+    (result, counted)
+}
+
+/// Global allocator wrapper counting allocations while perf is enabled
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: CountingAlloc<std::alloc::System> = CountingAlloc(std::alloc::System);
+/// ```
+pub struct CountingAlloc<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = THREAD_ALLOCATIONS.try_with(|count| count.set(count.get().map(|n| n + 1)));
+        if is_enabled() {
+            COUNTERS.allocations.fetch_add(1, Ordering::Relaxed);
+            COUNTERS
+                .allocated_bytes
+                .fetch_add(layout.size() as u64, Ordering::Relaxed);
+        }
+        self.0.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if is_enabled() {
+            COUNTERS.deallocations.fetch_add(1, Ordering::Relaxed);
+        }
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+/// Latency totals for one operation kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpStats {
+    pub count: u64,
+    pub total_ns: u64,
+}
+
+/// Point-in-time copy of every counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PerfReport {
+    pub enabled: bool,
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+    pub deallocations: u64,
+    pub bytes_persisted: u64,
+    pub lock_waits: u64,
+    pub lock_wait_ns: u64,
+    pub ops: [OpStats; 4],
+}
+
+impl PerfReport {
+    pub fn op(&self, kind: OpKind) -> OpStats {
+        self.ops[kind.index()]
+    }
+
+    /// Renders the report as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        let ops: Vec<String> = OpKind::ALL
+            .iter()
+            .map(|kind| {
+                let stats = self.op(*kind);
+                format!(
+                    "\"{}\":{{\"count\":{},\"total_ns\":{}}}",
+                    kind.name(),
+                    stats.count,
+                    stats.total_ns
+                )
+            })
+            .collect();
+        format!(
+            "{{\"enabled\":{},\"allocations\":{},\"allocated_bytes\":{},\"deallocations\":{},\
+             \"bytes_persisted\":{},\"lock_waits\":{},\"lock_wait_ns\":{},\"ops\":{{{}}}}}",
+            self.enabled,
+            self.allocations,
+            self.allocated_bytes,
+            self.deallocations,
+            self.bytes_persisted,
+            self.lock_waits,
+            self.lock_wait_ns,
+            ops.join(",")
+        )
+    }
+}
+
+/// Reads every counter
+pub fn report() -> PerfReport {
+    let c = &COUNTERS;
+    let mut ops = [OpStats::default(); 4];
+    for kind in OpKind::ALL {
+        ops[kind.index()] = OpStats {
+            count: c.op_counts[kind.index()].load(Ordering::Relaxed),
+            total_ns: c.op_ns[kind.index()].load(Ordering::Relaxed),
+        };
+    }
+    PerfReport {
+        enabled: is_enabled(),
+        allocations: c.allocations.load(Ordering::Relaxed),
+        allocated_bytes: c.allocated_bytes.load(Ordering::Relaxed),
+        deallocations: c.deallocations.load(Ordering::Relaxed),
+        bytes_persisted: c.bytes_persisted.load(Ordering::Relaxed),
+        lock_waits: c.lock_waits.load(Ordering::Relaxed),
+        lock_wait_ns: c.lock_wait_ns.load(Ordering::Relaxed),
+        ops,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_records_ops_and_persisted_bytes() {
+        enable();
+        let before = report();
+
+        let mut db = MemoryDatabase::new();
+        let insert_op = DbOperation::Insert {
+            key: "perf".into(),
+            value: "on".into(),
+        };
+        insert_op.execute(&mut db).unwrap();
+
+        let path = std::env::temp_dir().join("gvs_perf.db");
+        db.save_to_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let after = report();
+        assert!(after.op(OpKind::Insert).count > before.op(OpKind::Insert).count);
+        assert!(after.bytes_persisted >= before.bytes_persisted + "perf:on\n".len() as u64);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let report = PerfReport {
+            lock_waits: 2,
+            ..PerfReport::default()
+        };
+        let json = report.to_json();
+
+        assert!(json.starts_with("{\"enabled\":false,"));
+        assert!(json.contains("\"lock_waits\":2,"));
+        assert!(json.ends_with("\"update\":{\"count\":0,\"total_ns\":0}}}"));
+    }
+}
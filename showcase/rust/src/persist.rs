@@ -0,0 +1,434 @@
+//! Buffered, streaming persistence for large databases
+//!
+//! Entries are stored one per line as `key:value`, with both sides escaped by
+//! `literals::escape_value` so keys may hold `:` and values may hold newlines.
+//! Saved files follow each line with a tab and its CRC32 in hex; tabs are
+//! always escaped, so the last raw tab on a line starts the checksum.
+//!
+//! Files saved before escaping carry no checksums either. Loading one reads
+//! it in that original format, splitting each line at its first `:` and
+//! keeping backslashes and tabs as written; saving it again upgrades it.
+use std::fs::{self, File};
+use std::hash::BuildHasher;
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::backup::crc32;
+use crate::literals::{escape_value, unescape_value};
+use crate::{perf, Error, StrDatabase};
+
+/// When saved data is forced to stable storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// Leave flushing to the operating system
+    #[default]
+    Never,
+    /// Sync once after the last entry is written
+    OnFinish,
+    /// Sync after every `n` entries and once more at the end
+    EveryEntries(usize),
+}
+
+/// Tuning for `MemoryDatabase::save_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveOptions {
+    pub buffer_size: usize,
+    pub fsync: FsyncPolicy,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: 1024 * 1024,
+            fsync: FsyncPolicy::Never,
+        }
+    }
+}
+
+/// What a save wrote and how long it took
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SaveStats {
+    pub entries: usize,
+    pub bytes_written: u64,
+    pub syncs: usize,
+    pub elapsed: Duration,
+}
+
+impl SaveStats {
+    /// Bytes written per second, or 0 when the save took no measurable time
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes_written as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Formats one entry of the line format, without the trailing newline
+pub fn encode_line(key: &str, value: &str) -> String {
+    format!("{}:{}", escape_value(key), escape_value(value))
+}
+
+/// Parses a line written by `encode_line`
+pub fn decode_line(line: &str) -> Result<(String, String), String> {
+    let (key, value) = decode_key(line)?;
+    let value = unescape_value(value).ok_or("Malformed escape in value")?;
+    Ok((key, value))
+}
+
+/// Decodes only the key of a line, returning the value still escaped
+pub(crate) fn decode_key(line: &str) -> Result<(String, &str), String> {
+    let mut chars = line.char_indices();
+    let split = loop {
+        match chars.next() {
+            Some((_, '\\')) => {
+                chars.next();
+            }
+            Some((i, ':')) => break i,
+            Some(_) => {}
+            None => return Err("Missing ':' separator".to_string()),
+        }
+    };
+    let key = unescape_value(&line[..split]).ok_or("Malformed escape in key")?;
+    Ok((key, &line[split + 1..]))
+}
+
+/// Counts the newlines in `file`, then rewinds it for reading
+pub(crate) fn count_lines(file: &mut File) -> io::Result<usize> {
+    let mut buffer = vec![0; 64 * 1024];
+    let mut lines = 0;
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => lines += buffer[..read].iter().filter(|&&byte| byte == b'\n').count(),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    file.rewind()?;
+    Ok(lines)
+}
+
+/// Wraps a `decode_line` error with the 1-based line it came from
+pub(crate) fn invalid_line(number: usize, err: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", number, err),
+    )
+}
+
+/// Appends the tab-separated CRC32 that saved files carry on every line
+pub(crate) fn with_checksum(line: &str) -> String {
+    format!("{}\t{:08x}", line, crc32(line.as_bytes()))
+}
+
+/// Verifies and strips line checksums while reading a saved file
+///
+/// Whether a file is checksummed is decided by its first line, so a line
+/// whose checksum was cut off is caught too. Offsets count the raw bytes
+/// read, line endings included, so they hold for CRLF files.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Checksums {
+    checksummed: Option<bool>,
+    legacy: bool,
+    offset: u64,
+}
+
+/// Whether `line` ends in a tab and eight hex digits, as saved lines do
+fn has_checksum(line: &str) -> bool {
+    line.rsplit_once('\t')
+        .is_some_and(|(_, sum)| sum.len() == 8 && sum.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+impl Checksums {
+    /// Reads a file on disk, where lines without checksums are unescaped
+    pub(crate) fn saved_file() -> Self {
+        Self {
+            legacy: true,
+            ..Self::default()
+        }
+    }
+
+    /// Verifies lines from `offset` on, keeping what earlier lines decided
+    #[cfg(feature = "mmap")]
+    pub(crate) fn at(&self, offset: u64) -> Self {
+        Self {
+            offset,
+            ..self.clone()
+        }
+    }
+
+    /// Returns `raw` without its line ending and checksum, or
+    /// `Error::Corrupted` as `InvalidData`
+    pub(crate) fn verify<'a>(&mut self, number: usize, raw: &'a str) -> io::Result<&'a str> {
+        let start = self.offset;
+        self.offset += raw.len() as u64;
+        let line = raw.strip_suffix('\n').unwrap_or(raw);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() {
+            return Ok(line);
+        }
+        let checksummed = *self.checksummed.get_or_insert_with(|| has_checksum(line));
+        let (body, valid) = match line.rsplit_once('\t') {
+            Some((body, checksum)) if checksummed => (
+                body,
+                checksum.len() == 8
+                    && u32::from_str_radix(checksum, 16) == Ok(crc32(body.as_bytes())),
+            ),
+            Some(_) => (line, self.legacy),
+            None => (line, !checksummed),
+        };
+        if !valid {
+            let corrupted = Error::Corrupted {
+                line: number,
+                offset: start,
+            };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, corrupted));
+        }
+        Ok(body)
+    }
+
+    fn unescaped(&self) -> bool {
+        self.legacy && self.checksummed == Some(false)
+    }
+
+    /// Parses a line `verify` returned, in the format its file was saved in
+    pub(crate) fn decode(&self, line: &str) -> Result<(String, String), String> {
+        if self.unescaped() {
+            let (key, value) = line.split_once(':').ok_or("Missing ':' separator")?;
+            return Ok((key.to_string(), value.to_string()));
+        }
+        decode_line(line)
+    }
+
+    /// Parses only the key of a line that still carries its checksum
+    #[cfg(feature = "mmap")]
+    pub(crate) fn decode_key(&self, line: &str) -> Result<String, String> {
+        if self.unescaped() {
+            let (key, _) = line.split_once(':').ok_or("Missing ':' separator")?;
+            return Ok(key.to_string());
+        }
+        let body = line.rsplit_once('\t').map_or(line, |(body, _)| body);
+        decode_key(body).map(|(key, _)| key)
+    }
+}
+
+/// Writes `path` through a uniquely named temporary file renamed over it
+///
+/// Concurrent savers each rename a complete file into place, so readers see
+/// one of them whole instead of an interleaving; the last rename wins.
+pub(crate) fn write_atomically<T>(
+    path: &str,
+    write: impl FnOnce(File) -> io::Result<T>,
+) -> io::Result<T> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let temp = format!(
+        "{}.{}-{}.tmp",
+        path,
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    );
+    let result = File::create(&temp)
+        .and_then(write)
+        .and_then(|written| fs::rename(&temp, path).map(|()| written));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Streams every entry through a buffered writer, honouring `options.fsync`
+    ///
+    /// The file is replaced atomically, so a failed or concurrent save never
+    /// leaves it half-written.
+    pub fn save_with(&self, path: &str, options: &SaveOptions) -> io::Result<SaveStats> {
+        write_atomically(path, |file| write_entries(file, self.iter(), options))
+    }
+}
+
+/// Streams `entries` into `file` as lines, honouring `options.fsync`
+pub(crate) fn write_entries<'a>(
+    file: File,
+    entries: impl Iterator<Item = (&'a str, &'a str)>,
+    options: &SaveOptions,
+) -> io::Result<SaveStats> {
+    let started = Instant::now();
+    let mut stats = SaveStats::default();
+    let mut writer = BufWriter::with_capacity(options.buffer_size.max(1), file);
+
+    for (key, value) in entries {
+        let line = with_checksum(&encode_line(key, value));
+        for chunk in [line.as_bytes(), b"\n"] {
+            writer.write_all(chunk)?;
+            stats.bytes_written += chunk.len() as u64;
+        }
+        stats.entries += 1;
+
+        if let FsyncPolicy::EveryEntries(n) = options.fsync {
+            if n > 0 && stats.entries % n == 0 {
+                writer.flush()?;
+                writer.get_ref().sync_data()?;
+                stats.syncs += 1;
+            }
+        }
+    }
+
+    writer.flush()?;
+    if options.fsync != FsyncPolicy::Never {
+        writer.get_ref().sync_all()?;
+        stats.syncs += 1;
+    }
+    stats.elapsed = started.elapsed();
+    perf::record_bytes_persisted(stats.bytes_written);
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_save_with_stats() {
+        let path = std::env::temp_dir().join("gvs_persist_stats.db");
+        let path = path.to_str().unwrap();
+
+        let mut db = MemoryDatabase::new();
+        for i in 0..10 {
+            db.insert(format!("key{}", i), "value");
+        }
+
+        let options = SaveOptions {
+            buffer_size: 16,
+            fsync: FsyncPolicy::EveryEntries(4),
+        };
+        let stats = db.save_with(path, &options).unwrap();
+
+        assert_eq!(stats.entries, 10);
+        assert_eq!(stats.bytes_written, std::fs::metadata(path).unwrap().len());
+        assert_eq!(stats.syncs, 3);
+
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.retrieve("key7"), Some("value"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_checksums_catch_corruption() {
+        let path = std::env::temp_dir().join("gvs_persist_checksums.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("key", "value");
+        db.save_to_file(path).unwrap();
+        let saved = std::fs::read_to_string(path).unwrap();
+        assert_eq!(saved, format!("{}\n", with_checksum("key:value")));
+
+        let corrupted_at = |contents: &str| {
+            std::fs::write(path, contents).unwrap();
+            let err = MemoryDatabase::load_from_file(path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            match err.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+                Some(&Error::Corrupted { line, offset }) => (line, offset),
+                _ => panic!("not a checksum error: {}", err),
+            }
+        };
+        let line = saved.trim_end();
+        assert_eq!(corrupted_at(&format!("{}\nkey:valve\t0\n", line)), (2, 19));
+        assert_eq!(corrupted_at(&format!("{}\n\nkey:val\n", line)), (3, 20));
+        assert_eq!(corrupted_at(&saved.replace("value", "vaLue")), (1, 0));
+        let crlf = format!("{}\r\n{}\r\nkey:valve\t0\r\n", line, line);
+        assert_eq!(corrupted_at(&crlf), (3, 40));
+
+        std::fs::write(path, "legacy:line\n").unwrap();
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.retrieve("legacy"), Some("line"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_files_saved_before_escaping_still_load() {
+        let path = std::env::temp_dir().join("gvs_persist_legacy.db");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "dir:C:\\Users\\ferris\r\ncols:a\tb\n\nurl:http://x\n").unwrap();
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.retrieve("dir"), Some("C:\\Users\\ferris"));
+        assert_eq!(loaded.retrieve("cols"), Some("a\tb"));
+        assert_eq!(loaded.retrieve("url"), Some("http://x"));
+
+        loaded.save_to_file(path).unwrap();
+        let upgraded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(upgraded.retrieve("dir"), Some("C:\\Users\\ferris"));
+        assert_eq!(upgraded.retrieve("cols"), Some("a\tb"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_saves_never_interleave() {
+        let path = std::env::temp_dir().join("gvs_persist_concurrent.db");
+        let path = path.to_str().unwrap();
+
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                scope.spawn(move || {
+                    let db: MemoryDatabase = (0..500)
+                        .map(|i| (format!("key{}", i), writer.to_string()))
+                        .collect();
+                    for _ in 0..5 {
+                        db.save_to_file(path).unwrap();
+                    }
+                });
+            }
+        });
+
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.len(), 500);
+        let first = loaded.retrieve("key0").unwrap();
+        assert!(loaded.values().all(|value| value == first));
+
+        let dir = std::env::temp_dir();
+        let leftovers = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("gvs_persist_concurrent.db.") && name.ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_line_format_escapes_and_rejects_malformed_lines() {
+        let line = encode_line("a:b", "one\ntwo \\ three");
+        assert_eq!(line, r"a\:b:one\ntwo \\ three");
+        assert_eq!(
+            decode_line(&line),
+            Ok(("a:b".to_string(), "one\ntwo \\ three".to_string()))
+        );
+        assert_eq!(decode_line("url:http://x").unwrap().1, "http://x");
+
+        assert_eq!(
+            decode_line("no separator"),
+            Err("Missing ':' separator".to_string())
+        );
+        assert_eq!(
+            decode_line(r"k:\q"),
+            Err("Malformed escape in value".to_string())
+        );
+
+        let path = std::env::temp_dir().join("gvs_persist_malformed.db");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "good:1\n\nbroken\n").unwrap();
+        let err = MemoryDatabase::load_from_file(path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 3: Missing ':' separator");
+        std::fs::remove_file(path).unwrap();
+    }
+}
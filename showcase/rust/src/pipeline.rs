@@ -0,0 +1,338 @@
+//! Iterator-driven scans, imports and reports over a `MemoryDatabase`
+use std::collections::{hash_map, HashMap};
+use std::hash::BuildHasher;
+use std::iter::FusedIterator;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::snapshot::CowMap;
+use crate::ttl::Deadlines;
+use crate::{shared, Database, MemoryDatabase, StoredValue, StrDatabase};
+
+/// Iterator over every entry, walking the store shard by shard
+pub struct Scan<'a, S> {
+    shards: std::slice::Iter<'a, Arc<HashMap<Arc<str>, StoredValue, S>>>,
+    current: Option<hash_map::Iter<'a, Arc<str>, StoredValue>>,
+    remaining: usize,
+}
+
+impl<'a, S> Scan<'a, S> {
+    fn new(store: &'a CowMap<Arc<str>, StoredValue, S>) -> Self {
+        Scan {
+            shards: store.shards().iter(),
+            current: None,
+            remaining: store.len(),
+        }
+    }
+}
+
+impl<'a, S> Iterator for Scan<'a, S> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.current.as_mut().and_then(Iterator::next) {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+            self.current = Some(self.shards.next()?.iter());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<S> ExactSizeIterator for Scan<'_, S> {}
+
+impl<S> FusedIterator for Scan<'_, S> {}
+
+/// Iterator over the live entries, skipping expired ones
+pub struct Iter<'a, S> {
+    scan: Scan<'a, S>,
+    deadlines: &'a Deadlines<Arc<str>>,
+    now: Instant,
+}
+
+impl<'a, S> Iter<'a, S> {
+    pub(crate) fn new(
+        store: &'a CowMap<Arc<str>, StoredValue, S>,
+        deadlines: &'a Deadlines<Arc<str>>,
+    ) -> Self {
+        Iter {
+            scan: Scan::new(store),
+            deadlines,
+            now: Instant::now(),
+        }
+    }
+}
+
+impl<'a, S> Iterator for Iter<'a, S> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (deadlines, now) = (self.deadlines, self.now);
+        self.scan.find(|(key, _)| !deadlines.expired_at(*key, now))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.scan.size_hint().1)
+    }
+}
+
+impl<S> FusedIterator for Iter<'_, S> {}
+
+/// Owning iterator over the live entries, returned by `into_iter`
+pub struct IntoIter<S> {
+    shards: std::vec::IntoIter<Arc<HashMap<Arc<str>, StoredValue, S>>>,
+    current: Option<hash_map::IntoIter<Arc<str>, StoredValue>>,
+    deadlines: Deadlines<Arc<str>>,
+    now: Instant,
+}
+
+impl<S: Clone> Iterator for IntoIter<S> {
+    type Item = (Arc<str>, Arc<str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.current.as_mut().and_then(Iterator::next) {
+                if self.deadlines.expired_at(&key, self.now) {
+                    continue;
+                }
+                return Some((key, shared(value)));
+            }
+            // a shard still shared with a snapshot is copied, not stolen
+            let shard = Arc::unwrap_or_clone(self.shards.next()?);
+            self.current = Some(shard.into_iter());
+        }
+    }
+}
+
+impl<S: Clone> FusedIterator for IntoIter<S> {}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Scans every entry in unspecified order
+    pub fn scan(&self) -> Scan<'_, S> {
+        Scan::new(&self.store)
+    }
+
+    /// Iterates over the live entries in unspecified order
+    ///
+    /// Unlike `scan`, entries whose TTL has passed are skipped.
+    pub fn iter(&self) -> Iter<'_, S> {
+        Iter::new(&self.store, &self.deadlines)
+    }
+
+    /// Live entries whose keys start with `prefix`, in unspecified order
+    ///
+    /// Keys are hashed, so this still visits every entry; use a
+    /// `SortedDatabase` when prefix scans dominate.
+    pub fn scan_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let prefix = self.fold_key(prefix);
+        self.iter()
+            .filter(move |(key, _)| key.starts_with(&*prefix))
+    }
+}
+
+impl<'a, S: BuildHasher + Clone> IntoIterator for &'a StrDatabase<S> {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a, S>;
+
+    fn into_iter(self) -> Iter<'a, S> {
+        self.iter()
+    }
+}
+
+impl<S: BuildHasher + Clone> IntoIterator for StrDatabase<S> {
+    type Item = (Arc<str>, Arc<str>);
+    type IntoIter = IntoIter<S>;
+
+    fn into_iter(self) -> IntoIter<S> {
+        IntoIter {
+            shards: self.store.into_shards().into_iter(),
+            current: None,
+            deadlines: self.deadlines,
+            now: Instant::now(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for StrDatabase<S>
+where
+    K: Into<Arc<str>>,
+    V: Into<Arc<str>> + AsRef<str>,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut db = Self::with_hasher(S::default());
+        db.extend(iter);
+        db
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for StrDatabase<S>
+where
+    K: Into<Arc<str>>,
+    V: Into<Arc<str>> + AsRef<str>,
+    S: BuildHasher + Clone,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.insert_many(iter);
+    }
+}
+
+impl MemoryDatabase {
+    /// Collects `iter` into a database sized up front for `hint` entries
+    ///
+    /// Use when `iter` can't report its own length, such as a stream of
+    /// records whose count is known from a header.
+    pub fn from_iter_with_capacity<K, V, I>(iter: I, hint: usize) -> Self
+    where
+        K: Into<Arc<str>>,
+        V: Into<Arc<str>> + AsRef<str>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut db = Self::new();
+        db.reserve(hint);
+        db.extend(iter);
+        db
+    }
+}
+
+/// Builds a database from `key: value` lines, skipping blanks and `#` comments
+pub fn parse_entries(text: &str) -> MemoryDatabase {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Sorted keys grouped into comma-separated batches of at most `size`
+pub fn key_batches<S: BuildHasher + Clone>(db: &StrDatabase<S>, size: usize) -> Vec<String> {
+    let mut keys: Vec<&str> = db.keys().collect();
+    keys.sort_unstable();
+    keys.chunks(size.max(1))
+        .map(|batch| batch.join(","))
+        .collect()
+}
+
+/// Running totals of the numeric values under `keys`, skipping the rest
+pub fn running_totals<'k, S: BuildHasher + Clone>(
+    db: &StrDatabase<S>,
+    keys: &[&'k str],
+) -> Vec<(&'k str, i64)> {
+    keys.iter()
+        .filter_map(|&key| Some((key, db.retrieve(key)?.parse::<i64>().ok()?)))
+        .scan(0i64, |total, (key, value)| {
+            *total = total.saturating_add(value);
+            Some((key, *total))
+        })
+        .collect()
+}
+
+/// `rank. key (len)` lines for the `limit` longest values, longest first
+pub fn longest_values<S: BuildHasher + Clone>(db: &StrDatabase<S>, limit: usize) -> Vec<String> {
+    let mut entries: Vec<(&str, usize)> = db
+        .iter()
+        .map(|(key, value)| (key, value.chars().count()))
+        .collect();
+    entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    (1..)
+        .zip(entries.into_iter().take(limit))
+        .map(|(rank, (key, len))| format!("{}. {} ({})", rank, key, len))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loads_are_sized_up_front() {
+        let entries = (0..1000).map(|i| (format!("key{}", i), i.to_string()));
+        let db = MemoryDatabase::from_iter_with_capacity(entries, 1000);
+        assert_eq!(db.len(), 1000);
+        assert!(db.store.capacity() >= 1000);
+
+        let path = std::env::temp_dir().join("gvs_pipeline_presized.db");
+        let path = path.to_str().unwrap();
+        db.save_to_file(path).unwrap();
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.retrieve("key999"), Some("999"));
+        assert!(loaded.store.capacity() >= 1000);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_scan_and_collect() {
+        let mut db: MemoryDatabase = (0..100)
+            .map(|i| (format!("key{}", i), i.to_string()))
+            .collect();
+        let scan = db.scan();
+
+        assert_eq!(scan.len(), 100);
+        let mut total: u32 = 0;
+        for (key, value) in scan {
+            assert_eq!(key.strip_prefix("key"), Some(value));
+            total += value.parse::<u32>().unwrap();
+        }
+        assert_eq!(total, 4950);
+
+        let mut tens: Vec<&str> = db.scan_prefix("key9").map(|(_, value)| value).collect();
+        tens.sort_unstable();
+        assert_eq!(tens.len(), 11);
+        assert_eq!(tens[..2], ["9", "90"]);
+
+        db.insert_with_ttl("key9:gone", "x", std::time::Duration::ZERO);
+        assert_eq!(db.scan_prefix("key9").count(), 11);
+        assert_eq!(db.scan_prefix("key9:").next(), None);
+    }
+
+    #[test]
+    fn test_reports() {
+        let db = parse_entries(
+            "# scores\n\
+             alice: 10\n\
+             bob: 5\n\
+             \n\
+             carol: n/a\n\
+             dave: 20\n\
+             broken line\n",
+        );
+
+        assert_eq!(
+            running_totals(&db, &["alice", "bob", "carol", "dave"]),
+            vec![("alice", 10), ("bob", 15), ("dave", 35)]
+        );
+        assert_eq!(key_batches(&db, 3), vec!["alice,bob,carol", "dave"]);
+        assert_eq!(longest_values(&db, 2), vec!["1. carol (3)", "2. alice (2)"]);
+    }
+
+    fn sorted_keys<D: Database>(db: &D) -> Vec<&str> {
+        let mut keys: Vec<&str> = db.keys().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn test_iteration_skips_expired() {
+        let mut db: MemoryDatabase = [("a", "1"), ("b", "2")].into_iter().collect();
+        db.insert_with_ttl("gone", "x", std::time::Duration::ZERO);
+        assert_eq!(db.scan().len(), 3);
+        assert_eq!(sorted_keys(&db), ["a", "b"]);
+        assert_eq!(db.values().map(|value| value.len()).sum::<usize>(), 2);
+
+        let sorted: crate::sorted::SortedDatabase = (&db).into_iter().collect();
+        assert_eq!(sorted_keys(&sorted), ["a", "b"]);
+
+        let snapshot = db.snapshot();
+        let mut owned: Vec<(Arc<str>, Arc<str>)> = db.into_iter().collect();
+        owned.sort_unstable();
+        assert_eq!(owned, [("a".into(), "1".into()), ("b".into(), "2".into())]);
+        assert_eq!(snapshot.retrieve("a"), Some("1"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Python bindings for the in-memory database (enabled with the `pyo3` feature)
+//!
+//! Errors raise the closest built-in exception: missing keys `KeyError`,
+//! bad input `ValueError`, overflow `OverflowError` and I/O `OSError`.
+//! Writes a quota, hook or key policy refuses raise `RefusedError`, and
+//! `Client` raises `ServerError` for the server's own error replies.
+use pyo3::create_exception;
+use pyo3::exceptions::{
+    PyConnectionError, PyException, PyIOError, PyKeyError, PyOverflowError, PyValueError,
+};
+use pyo3::prelude::*;
+
+use crate::client::{Client, ClientError};
+use crate::{Database, DbOperation, Error, MemoryDatabase};
+
+create_exception!(vsassist_db, RefusedError, PyException);
+create_exception!(vsassist_db, ServerError, PyException);
+
+fn py_error(e: Error) -> PyErr {
+    match e {
+        Error::NotFound(key) => PyKeyError::new_err(key),
+        Error::NotAnInteger(_) | Error::InvalidKey { .. } => PyValueError::new_err(e.to_string()),
+        Error::Overflow(_) => PyOverflowError::new_err(e.to_string()),
+        Error::Io(e) => PyIOError::new_err(e.to_string()),
+        e => RefusedError::new_err(e.to_string()),
+    }
+}
+
+fn py_client_error(e: ClientError) -> PyErr {
+    match e {
+        ClientError::Io(e) => PyConnectionError::new_err(e.to_string()),
+        ClientError::Server(message) => ServerError::new_err(message),
+        ClientError::InvalidArgument(reason) => PyValueError::new_err(reason),
+    }
+}
+
+/// Python wrapper around `MemoryDatabase`
+#[pyclass(name = "MemoryDatabase")]
+pub struct PyMemoryDatabase {
+    inner: MemoryDatabase,
+}
+
+#[pymethods]
+impl PyMemoryDatabase {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: MemoryDatabase::new(),
+        }
+    }
+
+    /// Loads a database persisted by `save_to_file`
+    #[staticmethod]
+    fn load_from_file(path: &str) -> PyResult<Self> {
+        let inner =
+            MemoryDatabase::load_from_file(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    fn save_to_file(&self, path: &str) -> PyResult<()> {
+        self.inner
+            .save_to_file(path)
+            .map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    /// Stores `value`, raising `RefusedError` or `ValueError` when refused
+    fn insert(&mut self, key: String, value: String) -> PyResult<()> {
+        self.inner.try_insert(key, value).map_err(py_error)
+    }
+
+    fn retrieve(&self, key: &str) -> Option<String> {
+        self.inner.retrieve(key).map(str::to_string)
+    }
+
+    /// Runs an operation, raising `KeyError` when the key is missing
+    fn execute(&mut self, op: &PyDbOperation) -> PyResult<String> {
+        op.inner.try_execute(&mut self.inner).map_err(py_error)
+    }
+
+    fn __getitem__(&self, key: &str) -> PyResult<String> {
+        self.retrieve(key)
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __setitem__(&mut self, key: String, value: String) -> PyResult<()> {
+        self.insert(key, value)
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.inner.retrieve(key).is_some()
+    }
+}
+
+/// Python wrapper around `DbOperation`, built through the static constructors
+#[pyclass(name = "DbOperation")]
+pub struct PyDbOperation {
+    inner: DbOperation,
+}
+
+#[pymethods]
+impl PyDbOperation {
+    #[staticmethod]
+    fn insert(key: String, value: String) -> Self {
+        Self {
+            inner: DbOperation::Insert {
+                key: key.into(),
+                value: value.into(),
+            },
+        }
+    }
+
+    #[staticmethod]
+    fn retrieve(key: String) -> Self {
+        Self {
+            inner: DbOperation::Retrieve { key: key.into() },
+        }
+    }
+
+    #[staticmethod]
+    fn delete(key: String) -> Self {
+        Self {
+            inner: DbOperation::Delete { key: key.into() },
+        }
+    }
+
+    #[staticmethod]
+    fn update(key: String, value: String) -> Self {
+        Self {
+            inner: DbOperation::Update {
+                key: key.into(),
+                value: value.into(),
+            },
+        }
+    }
+}
+
+/// Python wrapper around a `Client` connection to `gvs-server`
+#[pyclass(name = "Client")]
+pub struct PyClient {
+    inner: Client,
+}
+
+#[pymethods]
+impl PyClient {
+    /// Connects to `addr`, raising `ConnectionError` when it can't
+    #[new]
+    fn new(addr: &str) -> PyResult<Self> {
+        let inner = Client::connect(addr).map_err(|e| PyConnectionError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    fn auth(&mut self, user: &str, password: &str) -> PyResult<()> {
+        self.inner.auth(user, password).map_err(py_client_error)
+    }
+
+    fn get(&mut self, key: &str) -> PyResult<Option<String>> {
+        self.inner.get(key).map_err(py_client_error)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> PyResult<()> {
+        self.inner.set(key, value).map_err(py_client_error)
+    }
+
+    /// Removes `key`, returning whether the server held it
+    fn delete(&mut self, key: &str) -> PyResult<bool> {
+        self.inner.delete(key).map_err(py_client_error)
+    }
+
+    /// Sends a raw REPL command and returns the server's reply
+    fn command(&mut self, line: &str) -> PyResult<String> {
+        self.inner.command(line).map_err(py_client_error)
+    }
+
+    fn __getitem__(&mut self, key: &str) -> PyResult<String> {
+        self.get(key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __setitem__(&mut self, key: &str, value: &str) -> PyResult<()> {
+        self.set(key, value)
+    }
+}
+
+/// Entry point of the `vsassist_db` Python package
+#[pymodule]
+fn vsassist_db(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMemoryDatabase>()?;
+    m.add_class::<PyDbOperation>()?;
+    m.add_class::<PyClient>()?;
+    m.add("RefusedError", m.py().get_type::<RefusedError>())?;
+    m.add("ServerError", m.py().get_type::<ServerError>())?;
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! Byte-size limits on what a database holds
+//!
+//! Usage counts the key and value payloads only, not hash table or `Arc`
+//! overhead, so it is a lower bound on the memory actually in use.
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::sync::Arc;
+
+use crate::eviction::{EvictionPolicy, Lru};
+use crate::{compact, stored, Error, MemoryDatabase, StrDatabase};
+
+/// Approximate number of bytes a key or value occupies
+pub trait ApproxSize {
+    fn approx_size(&self) -> usize;
+}
+
+impl ApproxSize for str {
+    fn approx_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ApproxSize for String {
+    fn approx_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: ApproxSize + ?Sized> ApproxSize for Arc<T> {
+    fn approx_size(&self) -> usize {
+        (**self).approx_size()
+    }
+}
+
+impl ApproxSize for [u8] {
+    fn approx_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ApproxSize for Vec<u8> {
+    fn approx_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ApproxSize for compact::CompactValue {
+    fn approx_size(&self) -> usize {
+        self.len()
+    }
+}
+
+macro_rules! fixed_size {
+    ($($ty:ty),*) => {
+        $(impl ApproxSize for $ty {
+            fn approx_size(&self) -> usize {
+                mem::size_of::<$ty>()
+            }
+        })*
+    };
+}
+
+fixed_size!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, bool, char);
+
+/// What a database over its byte limit does with a new write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaPolicy {
+    /// Reject the write with `Error::OverQuota`
+    Refuse,
+    /// Evict existing entries until the write fits
+    Evict(EvictionPolicy),
+}
+
+/// Byte limit and current usage of a database
+#[derive(Debug, Clone)]
+pub(crate) struct Quota<K, V> {
+    pub(crate) max_bytes: usize,
+    pub(crate) used: usize,
+    size_of: fn(&K, &V) -> usize,
+}
+
+impl<K, V> Quota<K, V> {
+    pub(crate) fn size(&self, key: &K, value: &V) -> usize {
+        (self.size_of)(key, value)
+    }
+}
+
+pub(crate) fn entry_size<K: ApproxSize, V: ApproxSize>(key: &K, value: &V) -> usize {
+    key.approx_size() + value.approx_size()
+}
+
+impl<K: ApproxSize, V: ApproxSize> MemoryDatabase<K, V> {
+    /// Creates a database holding at most `max_bytes` of keys and values
+    pub fn with_max_bytes(max_bytes: usize, policy: QuotaPolicy) -> Self {
+        let eviction = match policy {
+            QuotaPolicy::Refuse => None,
+            QuotaPolicy::Evict(EvictionPolicy::Lru) => Some(Lru::new(usize::MAX)),
+        };
+        Self {
+            quota: Some(Quota {
+                max_bytes,
+                used: 0,
+                size_of: entry_size::<K, V>,
+            }),
+            eviction,
+            ..Self::default()
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Approximate bytes held, or `None` when no byte limit is set
+    pub fn used_bytes(&self) -> Option<usize> {
+        self.quota.as_ref().map(|quota| quota.used)
+    }
+
+    /// Like `put`, but fails with `Error::OverQuota` when the entry can't fit
+    ///
+    /// An evicting database only refuses entries larger than its whole limit.
+    /// A refusing `BeforeInsert` hook fails it with `Error::Rejected`, and a
+    /// full bucket with `Error::BucketFull` or `Error::BucketOverQuota`.
+    pub fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, Error> {
+        self.check_put(&key, &value)?;
+        Ok(self.put_entry(key, value))
+    }
+
+    /// Runs every check `try_put` does without storing anything
+    pub(crate) fn check_put(&self, key: &K, value: &V) -> Result<(), Error> {
+        self.hooks.check_insert(key, value)?;
+        self.check_bucket_quotas(key, value)?;
+        if let Some(quota) = &self.quota {
+            let size = quota.size(key, value);
+            let freed = self.store.get(key).map_or(0, |old| quota.size(key, old));
+            let needed = quota.used - freed + size;
+            let can_evict = self.eviction.is_some() && size <= quota.max_bytes;
+            if needed > quota.max_bytes && !can_evict {
+                return Err(Error::OverQuota {
+                    needed,
+                    limit: quota.max_bytes,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Like `Database::insert`, but fails with `Error::OverQuota` when full
+    /// or `Error::InvalidKey` when the key policy rejects the key
+    pub fn try_insert(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+    ) -> Result<(), Error> {
+        let key = self.fold_owned_key(key.into());
+        self.check_key(&key)?;
+        self.try_put(key, stored(value)).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, DbOperation};
+
+    #[test]
+    fn test_refusing_quota() {
+        let mut db = MemoryDatabase::with_max_bytes(10, QuotaPolicy::Refuse);
+        db.try_insert("abc", "12345").unwrap();
+        assert_eq!(db.used_bytes(), Some(8));
+
+        let err = db.try_insert("de", "12").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::OverQuota {
+                needed: 12,
+                limit: 10
+            }
+        ));
+        db.try_insert("abc", "1234567").unwrap();
+        assert_eq!(db.used_bytes(), Some(10));
+
+        db.insert("dropped", "x");
+        assert_eq!(db.retrieve("dropped"), None);
+        let op = DbOperation::Insert {
+            key: "k".into(),
+            value: "v".into(),
+        };
+        assert_eq!(
+            op.execute(&mut db),
+            Err("Over quota: needs 12 bytes, limit is 10".to_string())
+        );
+
+        db.remove("abc");
+        assert_eq!(db.used_bytes(), Some(0));
+    }
+
+    #[test]
+    fn test_evicting_quota() {
+        let mut db: MemoryDatabase<u32, Vec<u8>> =
+            MemoryDatabase::with_max_bytes(20, QuotaPolicy::Evict(EvictionPolicy::Lru));
+        db.try_put(1, vec![0; 6]).unwrap();
+        db.try_put(2, vec![0; 6]).unwrap();
+        assert_eq!(db.used_bytes(), Some(20));
+
+        db.get(&1);
+        db.try_put(3, vec![0; 2]).unwrap();
+        assert!(db.contains_key(&1));
+        assert!(!db.contains_key(&2));
+        assert_eq!(db.used_bytes(), Some(16));
+
+        assert!(db.try_put(4, vec![0; 17]).is_err());
+        assert_eq!(MemoryDatabase::new().used_bytes(), None);
+    }
+}
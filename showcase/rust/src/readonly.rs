@@ -0,0 +1,129 @@
+//! Databases opened for reading only
+//!
+//! [`ReadOnlyDatabase`] forwards only the lookups of the `StrDatabase` it
+//! wraps. Writes, and saves that could overwrite the file it was loaded
+//! from, are unavailable at compile time:
+//!
+//! ```compile_fail
+//! use gvs_showcase::{Database, MemoryDatabase};
+//!
+//! let mut db = MemoryDatabase::open_read_only("shared.db").unwrap();
+//! db.insert("key", "value");
+//! ```
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io;
+use std::time::Duration;
+
+use crate::pipeline::Iter;
+use crate::stats::Stats;
+use crate::{Database, DbOperation, MemoryDatabase, StrDatabase};
+
+/// A loaded database that can be read but not written
+#[derive(Debug, Clone)]
+pub struct ReadOnlyDatabase<S = RandomState> {
+    db: StrDatabase<S>,
+}
+
+impl MemoryDatabase {
+    /// Loads the file at `path` behind a read-only view
+    ///
+    /// The file itself is only read, here and later.
+    pub fn open_read_only(path: &str) -> io::Result<ReadOnlyDatabase> {
+        Self::load_from_file(path).map(ReadOnlyDatabase::from)
+    }
+}
+
+impl<S> From<StrDatabase<S>> for ReadOnlyDatabase<S> {
+    /// Freezes `db`; there is no way back to a writable database
+    fn from(db: StrDatabase<S>) -> Self {
+        Self { db }
+    }
+}
+
+impl<S: BuildHasher + Clone> ReadOnlyDatabase<S> {
+    pub fn retrieve(&self, key: &str) -> Option<&str> {
+        self.db.retrieve(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        Database::contains_key(&self.db, key)
+    }
+
+    /// Number of live entries
+    pub fn len(&self) -> usize {
+        self.db.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.db.is_empty()
+    }
+
+    /// Iterates over every live entry, in arbitrary order
+    pub fn iter(&self) -> Iter<'_, S> {
+        self.db.iter()
+    }
+
+    /// Time left before `key` expires, or `None` if it has no TTL or is gone
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        self.db.ttl(key)
+    }
+
+    /// Live keys matching the glob `pattern`, sorted
+    pub fn keys_matching(&self, pattern: &str) -> Vec<&str> {
+        self.db.keys_matching(pattern)
+    }
+
+    /// Keys whose values contain every token in `query`, sorted
+    pub fn search(&self, query: &str) -> Vec<&str> {
+        self.db.search(query)
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.db.stats()
+    }
+
+    /// Runs a lookup, refusing every operation that would write
+    pub fn execute(&self, op: &DbOperation) -> Result<String, String> {
+        op.execute_lookup(|key| self.db.retrieve(key))
+            .unwrap_or_else(|| Err("The database is read-only".to_string()))
+    }
+}
+
+impl<'a, S: BuildHasher + Clone> IntoIterator for &'a ReadOnlyDatabase<S> {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a, S>;
+
+    fn into_iter(self) -> Iter<'a, S> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_read_only() {
+        let path = std::env::temp_dir().join("gvs_read_only.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert("region", "eu-west");
+        db.save_to_file(path).unwrap();
+
+        let shared = MemoryDatabase::open_read_only(path).unwrap();
+        assert_eq!(shared.retrieve("region"), Some("eu-west"));
+        assert_eq!(shared.keys_matching("reg*"), ["region"]);
+        assert_eq!((&shared).into_iter().count(), 1);
+        assert_eq!(shared.stats().hits, 1);
+        let get = crate::patterns::parse_command("get region").unwrap();
+        assert_eq!(shared.execute(&get).unwrap(), "Retrieved: region = eu-west");
+        let set = crate::patterns::parse_command("set region us").unwrap();
+        assert_eq!(
+            shared.execute(&set).unwrap_err(),
+            "The database is read-only"
+        );
+        assert!(MemoryDatabase::open_read_only("/nonexistent/gvs.db").is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+//! Fixed-size record headers for the binary persistence format
+//!
+//! Each record is a 16-byte `RecordHeader` followed by the key and value
+//! bytes. Headers are written little-endian with `to_le_bytes`; the
+//! `AsBytes`/`FromBytes` traits and the `HeaderBytes` union expose the same
+//! layout in native byte order for code that maps files directly.
+use std::mem::{align_of, size_of};
+use std::ptr;
+
+use crate::arena::Zeroable;
+use crate::backup::crc32;
+use crate::enums::OpCode;
+
+/// Header preceding every record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct RecordHeader {
+    pub opcode: u8,
+    pub flags: u8,
+    pub reserved: u16,
+    pub key_len: u32,
+    pub value_len: u32,
+    /// CRC-32 of the key bytes followed by the value bytes
+    pub checksum: u32,
+}
+
+/// Encoded size of a `RecordHeader`
+pub const HEADER_LEN: usize = size_of::<RecordHeader>();
+
+// The encoding below relies on there being no padding between fields
+const _: () = assert!(HEADER_LEN == 16 && align_of::<RecordHeader>() == 4);
+
+/// Set when the value is stored compressed
+pub const FLAG_COMPRESSED: u8 = 1 << 0;
+/// Set on the last record of a batch
+pub const FLAG_END_OF_BATCH: u8 = 1 << 1;
+
+impl RecordHeader {
+    /// Header for `key` and `value`, or `None` if either is longer than `u32::MAX`
+    pub fn new(opcode: OpCode, key: &[u8], value: &[u8]) -> Option<Self> {
+        Some(Self {
+            opcode: opcode.as_byte(),
+            flags: 0,
+            reserved: 0,
+            key_len: u32::try_from(key.len()).ok()?,
+            value_len: u32::try_from(value.len()).ok()?,
+            checksum: crc32(&[key, value].concat()),
+        })
+    }
+
+    /// Little-endian encoding, independent of the host
+    pub fn to_le_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut out = [0u8; HEADER_LEN];
+        out[0] = self.opcode;
+        out[1] = self.flags;
+        out[2..4].copy_from_slice(&self.reserved.to_le_bytes());
+        out[4..8].copy_from_slice(&self.key_len.to_le_bytes());
+        out[8..12].copy_from_slice(&self.value_len.to_le_bytes());
+        out[12..16].copy_from_slice(&self.checksum.to_le_bytes());
+        out
+    }
+
+    pub fn from_le_bytes(bytes: &[u8; HEADER_LEN]) -> Self {
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Self {
+            opcode: bytes[0],
+            flags: bytes[1],
+            reserved: u16::from_le_bytes([bytes[2], bytes[3]]),
+            key_len: u32_at(4),
+            value_len: u32_at(8),
+            checksum: u32_at(12),
+        }
+    }
+
+    pub fn opcode(&self) -> Result<OpCode, String> {
+        OpCode::try_from(self.opcode)
+    }
+
+    /// Checks the lengths and checksum against the payload that followed the header
+    pub fn verify(&self, key: &[u8], value: &[u8]) -> bool {
+        key.len() == self.key_len as usize
+            && value.len() == self.value_len as usize
+            && crc32(&[key, value].concat()) == self.checksum
+    }
+}
+
+/// Types whose bytes can be viewed directly
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` or primitive with no padding bytes, so
+/// every byte of a value is initialized.
+pub unsafe trait AsBytes: Copy {
+    fn as_bytes(&self) -> &[u8] {
+        let ptr = (self as *const Self).cast::<u8>();
+        // SAFETY: the trait contract guarantees every byte is initialized
+        unsafe { std::slice::from_raw_parts(ptr, size_of::<Self>()) }
+    }
+}
+
+/// Types that are valid for any byte pattern of the right length
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value, which rules out `bool`, `char`,
+/// references and most enums.
+pub unsafe trait FromBytes: Copy {
+    /// Reads a value from exactly `size_of::<Self>()` bytes in native order
+    fn read_from(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != size_of::<Self>() {
+            return None;
+        }
+        // SAFETY: the length matches and the trait contract makes any bytes valid;
+        // `read_unaligned` copes with `bytes` not being aligned for `Self`
+        Some(unsafe { ptr::read_unaligned(bytes.as_ptr().cast::<Self>()) })
+    }
+}
+
+// SAFETY: `repr(C)` with only integer fields, and the const assertion above
+// rules out padding
+unsafe impl AsBytes for RecordHeader {}
+// SAFETY: every field is an integer, for which any bytes are valid
+unsafe impl FromBytes for RecordHeader {}
+// SAFETY: as above, so all-zero bytes are a valid header
+unsafe impl Zeroable for RecordHeader {}
+
+/// The same 16 bytes seen as a header or as raw native-order bytes
+#[repr(C)]
+pub union HeaderBytes {
+    pub header: RecordHeader,
+    pub bytes: [u8; HEADER_LEN],
+}
+
+impl HeaderBytes {
+    pub fn native(header: RecordHeader) -> [u8; HEADER_LEN] {
+        // SAFETY: both fields are plain bytes of the same size with no padding
+        unsafe { HeaderBytes { header }.bytes }
+    }
+
+    pub fn from_native(bytes: [u8; HEADER_LEN]) -> RecordHeader {
+        // SAFETY: any 16 bytes are a valid `RecordHeader`, see `FromBytes`
+        unsafe { HeaderBytes { bytes }.header }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_little_endian_round_trip() {
+        let mut header = RecordHeader::new(OpCode::Insert, b"lang", b"Rust").unwrap();
+        header.flags |= FLAG_END_OF_BATCH;
+        let bytes = header.to_le_bytes();
+
+        assert_eq!(&bytes[..8], &[0x01, 0b10, 0, 0, 4, 0, 0, 0]);
+        assert_eq!(RecordHeader::from_le_bytes(&bytes), header);
+        assert_eq!(header.opcode(), Ok(OpCode::Insert));
+        assert!(header.verify(b"lang", b"Rust"));
+        assert!(!header.verify(b"lang", b"Rust!"));
+        assert!(!header.verify(b"lan", b"gRust"));
+    }
+
+    #[test]
+    fn test_native_views_agree() {
+        let header = RecordHeader::new(OpCode::Append, b"log", b"line").unwrap();
+        let native = HeaderBytes::native(header);
+
+        assert_eq!(header.as_bytes(), &native);
+        assert_eq!(HeaderBytes::from_native(native), header);
+        assert_eq!(RecordHeader::read_from(&native[..]), Some(header));
+        assert_eq!(RecordHeader::read_from(&native[1..]), None);
+        if cfg!(target_endian = "little") {
+            assert_eq!(native, header.to_le_bytes());
+        }
+    }
+}
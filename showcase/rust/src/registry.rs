@@ -0,0 +1,239 @@
+//! Pluggable command handlers dispatched by name
+//!
+//! Handlers are stored as `Box<dyn Handler>` and reach the store through
+//! `DynDatabase`, an object-safe mirror of `Database` whose generic `insert`
+//! would otherwise rule out `dyn`.
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::Database;
+
+/// Object-safe view of any `Database`
+pub trait DynDatabase {
+    fn dyn_insert(&mut self, key: Arc<str>, value: Arc<str>);
+    fn dyn_retrieve(&self, key: &str) -> Option<&str>;
+}
+
+impl<D: Database> DynDatabase for D {
+    fn dyn_insert(&mut self, key: Arc<str>, value: Arc<str>) {
+        self.insert(key, value);
+    }
+
+    fn dyn_retrieve(&self, key: &str) -> Option<&str> {
+        self.retrieve(key)
+    }
+}
+
+/// A named command run against the database
+pub trait Handler: Any + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn handle(&self, db: &mut dyn DynDatabase, args: &[&str]) -> Result<String, String>;
+
+    /// Boxes a concrete handler; unavailable on `dyn Handler` itself
+    fn boxed(self) -> Box<dyn Handler>
+    where
+        Self: Sized,
+    {
+        Box::new(self)
+    }
+}
+
+/// `GET <key>`
+pub struct GetHandler;
+
+impl Handler for GetHandler {
+    fn name(&self) -> &str {
+        "GET"
+    }
+
+    fn handle(&self, db: &mut dyn DynDatabase, args: &[&str]) -> Result<String, String> {
+        let [key] = args else {
+            return Err("usage: GET <key>".to_string());
+        };
+        db.dyn_retrieve(key)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Key not found: {}", key))
+    }
+}
+
+/// `SET <key> <value>`
+pub struct SetHandler;
+
+impl Handler for SetHandler {
+    fn name(&self) -> &str {
+        "SET"
+    }
+
+    fn handle(&self, db: &mut dyn DynDatabase, args: &[&str]) -> Result<String, String> {
+        let [key, value] = args else {
+            return Err("usage: SET <key> <value>".to_string());
+        };
+        db.dyn_insert((*key).into(), (*value).into());
+        Ok("OK".to_string())
+    }
+}
+
+/// Wraps another handler and counts how often it runs
+pub struct Counted {
+    inner: Box<dyn Handler>,
+    calls: AtomicUsize,
+}
+
+impl Counted {
+    pub fn new(inner: impl Handler) -> Self {
+        Self {
+            inner: inner.boxed(),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+impl Handler for Counted {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn handle(&self, db: &mut dyn DynDatabase, args: &[&str]) -> Result<String, String> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.inner.handle(db, args)
+    }
+}
+
+type HandlerFn = dyn Fn(&mut dyn DynDatabase, &[&str]) -> Result<String, String> + Send + Sync;
+
+struct FnHandler {
+    name: String,
+    run: Box<HandlerFn>,
+}
+
+impl Handler for FnHandler {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn handle(&self, db: &mut dyn DynDatabase, args: &[&str]) -> Result<String, String> {
+        (self.run)(db, args)
+    }
+}
+
+/// Handlers keyed by command name
+#[derive(Default)]
+pub struct Registry {
+    handlers: BTreeMap<String, Box<dyn Handler>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in `GET` and `SET` commands
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(GetHandler);
+        registry.register(SetHandler);
+        registry
+    }
+
+    /// Adds `handler`, replacing any handler with the same name
+    pub fn register(&mut self, handler: impl Handler) {
+        self.register_boxed(handler.boxed());
+    }
+
+    pub fn register_boxed(&mut self, handler: Box<dyn Handler>) {
+        self.handlers
+            .insert(handler.name().to_ascii_uppercase(), handler);
+    }
+
+    /// Adds a closure as a handler
+    pub fn register_fn<F>(&mut self, name: &str, run: F)
+    where
+        F: Fn(&mut dyn DynDatabase, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.register(FnHandler {
+            name: name.to_string(),
+            run: Box::new(run),
+        });
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Downcasts the handler registered as `name` to its concrete type
+    pub fn get<T: Handler>(&self, name: &str) -> Option<&T> {
+        let handler: &dyn Any = self.handlers.get(&name.to_ascii_uppercase())?.as_ref();
+        handler.downcast_ref()
+    }
+
+    /// Runs a whitespace-separated command line such as `SET language Rust`
+    pub fn dispatch(&self, db: &mut dyn DynDatabase, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("Empty command")?;
+        let args: Vec<&str> = words.collect();
+        self.handlers
+            .get(&command.to_ascii_uppercase())
+            .ok_or_else(|| format!("Unknown command: {}", command))?
+            .handle(db, &args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_dispatch_builtins_and_closures() {
+        let mut registry = Registry::with_builtins();
+        registry.register_fn("APPEND", |db, args| {
+            let [key, suffix] = args else {
+                return Err("usage: APPEND <key> <suffix>".to_string());
+            };
+            let value = format!("{}{}", db.dyn_retrieve(key).unwrap_or(""), suffix);
+            db.dyn_insert((*key).into(), value.as_str().into());
+            Ok(value)
+        });
+
+        let mut db = MemoryDatabase::new();
+        assert_eq!(
+            registry.dispatch(&mut db, "set language Rust"),
+            Ok("OK".into())
+        );
+        assert_eq!(
+            registry.dispatch(&mut db, "APPEND language 2021"),
+            Ok("Rust2021".into())
+        );
+        assert_eq!(
+            registry.dispatch(&mut db, "GET language"),
+            Ok("Rust2021".into())
+        );
+        assert!(registry.dispatch(&mut db, "GET").is_err());
+        assert!(registry.dispatch(&mut db, "DROP everything").is_err());
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["APPEND", "GET", "SET"]
+        );
+    }
+
+    #[test]
+    fn test_downcast_registered_handler() {
+        let mut registry = Registry::new();
+        registry.register(Counted::new(GetHandler));
+
+        let mut db = MemoryDatabase::new();
+        db.insert("year", "2010");
+        registry.dispatch(&mut db, "GET year").unwrap();
+        registry.dispatch(&mut db, "GET missing").unwrap_err();
+
+        assert_eq!(registry.get::<Counted>("get").map(Counted::calls), Some(2));
+        assert!(registry.get::<GetHandler>("GET").is_none());
+    }
+}
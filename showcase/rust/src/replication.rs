@@ -0,0 +1,227 @@
+//! Leader/follower replication over change events
+//!
+//! A `Replicator` subscribes to the leader's change events and turns each
+//! into a `wal::LogRecord` carrying the key's current value, so a burst of
+//! writes to one key may reach followers as repeats of its final value.
+//! Applying records is idempotent, which is what makes that safe. A new
+//! follower first receives every live entry, then the stream. Records use
+//! the WAL line format, so they can also be sent over a socket with
+//! `LogRecord::encode` and fed to `Follower::apply` on the other end. TTLs
+//! are not replicated; expiry on the leader reaches followers as a removal.
+use std::hash::BuildHasher;
+use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use crate::changes::{ChangeEvent, ChangeKind};
+use crate::wal::LogRecord;
+use crate::{Database, MemoryDatabase, StrDatabase};
+
+/// Forwards a leader's writes to its followers
+#[derive(Debug)]
+pub struct Replicator {
+    events: Receiver<ChangeEvent>,
+    followers: Vec<Sender<LogRecord>>,
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Makes this database a leader, returning the replicator to drive it
+    pub fn replicate(&mut self) -> Replicator {
+        Replicator {
+            events: self.subscribe(),
+            followers: Vec::new(),
+        }
+    }
+}
+
+impl Replicator {
+    /// Registers a follower, queueing a full copy of `leader` for it first
+    pub fn add_follower<S: BuildHasher + Clone>(
+        &mut self,
+        leader: &StrDatabase<S>,
+    ) -> Receiver<LogRecord> {
+        let (sender, receiver) = mpsc::channel();
+        for (key, value) in leader.iter() {
+            let record = LogRecord::Insert {
+                key: key.into(),
+                value: value.into(),
+            };
+            // the receiver is still in hand, so this can't fail
+            let _ = sender.send(record);
+        }
+        self.followers.push(sender);
+        receiver
+    }
+
+    /// Number of followers still connected as of the last `forward`
+    pub fn followers(&self) -> usize {
+        self.followers.len()
+    }
+
+    /// Sends every change made since the last call, returning how many
+    ///
+    /// Followers whose receiver was dropped are forgotten.
+    pub fn forward<S: BuildHasher + Clone>(&mut self, leader: &StrDatabase<S>) -> usize {
+        let mut forwarded = 0;
+        for ChangeEvent { key, kind } in self.events.try_iter() {
+            let record = match (kind, leader.live(&key)) {
+                (ChangeKind::Inserted | ChangeKind::Updated, Some(value)) => LogRecord::Insert {
+                    value: value.into(),
+                    key,
+                },
+                // written and then removed before this call
+                _ => LogRecord::Remove { key },
+            };
+            self.followers
+                .retain(|follower| follower.send(record.clone()).is_ok());
+            forwarded += 1;
+        }
+        forwarded
+    }
+}
+
+/// A database kept in step with a leader by the records it receives
+///
+/// Derefs to the database for reads; write to the leader instead.
+#[derive(Debug)]
+pub struct Follower {
+    db: MemoryDatabase,
+    records: Option<Receiver<LogRecord>>,
+}
+
+impl Follower {
+    /// Starts an empty follower applying the records from `records`
+    pub fn new(records: Receiver<LogRecord>) -> Self {
+        Self {
+            db: MemoryDatabase::new(),
+            records: Some(records),
+        }
+    }
+
+    /// Applies one record, as received from a leader by other means
+    pub fn apply(&mut self, record: LogRecord) {
+        apply(&mut self.db, record);
+    }
+
+    /// Applies the records already waiting, returning how many
+    pub fn catch_up(&mut self) -> usize {
+        let Some(records) = &self.records else {
+            return 0;
+        };
+        let mut applied = 0;
+        for record in records.try_iter() {
+            apply(&mut self.db, record);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Waits up to `timeout` for the next record and applies it
+    ///
+    /// Returns false on timeout or once the leader's replicator is gone.
+    pub fn apply_next(&mut self, timeout: Duration) -> bool {
+        let Some(records) = &self.records else {
+            return false;
+        };
+        match records.recv_timeout(timeout) {
+            Ok(record) => {
+                self.apply(record);
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                self.records = None;
+                false
+            }
+        }
+    }
+
+    /// Stops following, returning the database as replicated so far
+    pub fn into_inner(self) -> MemoryDatabase {
+        self.db
+    }
+}
+
+fn apply(db: &mut MemoryDatabase, record: LogRecord) {
+    match record {
+        LogRecord::Insert { key, value } => db.insert(key, value),
+        LogRecord::Remove { key } => {
+            db.remove(&key);
+        }
+    }
+}
+
+impl Deref for Follower {
+    type Target = MemoryDatabase;
+
+    fn deref(&self) -> &MemoryDatabase {
+        &self.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_follower_syncs_then_streams() {
+        let mut leader = MemoryDatabase::new();
+        leader.insert("existing", "1");
+        let mut replicator = leader.replicate();
+        let mut follower = Follower::new(replicator.add_follower(&leader));
+        assert_eq!(follower.catch_up(), 1);
+        assert_eq!(follower.retrieve("existing"), Some("1"));
+
+        leader.insert("a", "1");
+        leader.insert("a", "2");
+        leader.remove("existing");
+        let mut tx = leader.begin();
+        tx.insert("draft", "x");
+        tx.rollback();
+        leader.insert("gone", "soon");
+        leader.remove("gone");
+        assert_eq!(replicator.forward(&leader), 5);
+
+        follower.catch_up();
+        assert_eq!(follower.retrieve("a"), Some("2"));
+        assert_eq!(follower.retrieve("existing"), None);
+        assert_eq!(follower.retrieve("draft"), None);
+        assert_eq!(follower.len(), leader.len());
+
+        drop(follower);
+        leader.insert("b", "3");
+        replicator.forward(&leader);
+        assert_eq!(replicator.followers(), 0);
+    }
+
+    #[test]
+    fn test_follower_on_another_thread() {
+        let mut leader = MemoryDatabase::new();
+        let mut replicator = leader.replicate();
+        let records = replicator.add_follower(&leader);
+        let follower = thread::spawn(move || {
+            let mut follower = Follower::new(records);
+            while follower.apply_next(Duration::from_secs(10)) {}
+            follower.into_inner()
+        });
+
+        for i in 0..100 {
+            leader.insert(format!("key{}", i), i.to_string());
+        }
+        replicator.forward(&leader);
+        drop(replicator);
+        let replica = follower.join().unwrap();
+        assert_eq!(replica.len(), 100);
+        assert_eq!(replica.retrieve("key42"), Some("42"));
+
+        let line = LogRecord::Insert {
+            key: "k".into(),
+            value: "v".into(),
+        }
+        .encode();
+        let mut remote = Follower::new(mpsc::channel().1);
+        remote.apply(LogRecord::decode(&line).unwrap());
+        assert_eq!(remote.retrieve("k"), Some("v"));
+    }
+}
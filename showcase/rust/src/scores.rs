@@ -0,0 +1,221 @@
+//! Numeric scores and a sorted, indexable view of the database
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Deref, Index, Mul, Sub};
+use std::str::FromStr;
+
+use crate::StrDatabase;
+
+/// A non-negative score that saturates instead of overflowing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Score(u32);
+
+impl Score {
+    pub const ZERO: Score = Score(0);
+    pub const MAX: Score = Score(u32::MAX);
+
+    pub const fn new(points: u32) -> Self {
+        Score(points)
+    }
+
+    pub const fn points(self) -> u32 {
+        self.0
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, rhs: Score) -> Score {
+        Score(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Score) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: Score) -> Score {
+        Score(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Mul<u32> for Score {
+    type Output = Score;
+
+    fn mul(self, factor: u32) -> Score {
+        Score(self.0.saturating_mul(factor))
+    }
+}
+
+impl Sum for Score {
+    fn sum<I: Iterator<Item = Score>>(iter: I) -> Score {
+        iter.fold(Score::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Score> for Score {
+    fn sum<I: Iterator<Item = &'a Score>>(iter: I) -> Score {
+        iter.copied().sum()
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} pts", self.0)
+    }
+}
+
+impl FromStr for Score {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.trim().trim_end_matches("pts").trim_end();
+        digits
+            .parse()
+            .map(Score)
+            .map_err(|_| format!("Invalid score: {}", s))
+    }
+}
+
+/// Entries of a database sorted by key, indexable by position or by key
+pub struct SortedView<'a> {
+    entries: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> SortedView<'a> {
+    pub fn new<S: BuildHasher + Clone>(db: &'a StrDatabase<S>) -> Self {
+        let mut entries: Vec<_> = db.scan().collect();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        Self { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        let index = self.entries.binary_search_by_key(&key, |&(k, _)| k).ok()?;
+        Some(self.entries[index].1)
+    }
+
+    /// Values that parse as a `Score`, in key order
+    pub fn scores(&self) -> Scores<'_, 'a> {
+        Scores {
+            entries: self.entries.iter(),
+        }
+    }
+}
+
+impl<'a> Deref for SortedView<'a> {
+    type Target = [(&'a str, &'a str)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<'a> Index<usize> for SortedView<'a> {
+    type Output = (&'a str, &'a str);
+
+    fn index(&self, position: usize) -> &Self::Output {
+        &self.entries[position]
+    }
+}
+
+impl Index<&str> for SortedView<'_> {
+    type Output = str;
+
+    /// Panics when `key` is missing, like `HashMap`'s `Index`
+    fn index(&self, key: &str) -> &str {
+        self.get(key)
+            .unwrap_or_else(|| panic!("no entry for key {:?}", key))
+    }
+}
+
+impl<'v, 'a> IntoIterator for &'v SortedView<'a> {
+    type Item = &'v (&'a str, &'a str);
+    type IntoIter = std::slice::Iter<'v, (&'a str, &'a str)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Iterator over the `(key, Score)` pairs of a `SortedView`
+pub struct Scores<'v, 'a> {
+    entries: std::slice::Iter<'v, (&'a str, &'a str)>,
+}
+
+impl<'a> Iterator for Scores<'_, 'a> {
+    type Item = (&'a str, Score);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .by_ref()
+            .find_map(|&(key, value)| Some((key, value.parse().ok()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_score_arithmetic() {
+        let mut total = Score::new(10) + Score::new(5);
+        total += Score::new(1);
+
+        assert_eq!(total, Score::new(16));
+        assert_eq!(Score::new(3) - Score::new(5), Score::ZERO);
+        assert_eq!(Score::MAX * 2, Score::MAX);
+        assert!(Score::new(2) < Score::new(10));
+        assert_eq!(
+            [Score::new(1), Score::new(2)].iter().sum::<Score>(),
+            Score::new(3)
+        );
+        assert_eq!(total.to_string(), "16 pts");
+        assert_eq!("16 pts".parse(), Ok(total));
+    }
+
+    #[test]
+    fn test_sorted_view() {
+        let mut db = MemoryDatabase::new();
+        db.insert("carol", "7");
+        db.insert("alice", "12 pts");
+        db.insert("bob", "absent");
+
+        let view = SortedView::new(&db);
+        assert_eq!(view.len(), 3);
+        assert_eq!(view[0], ("alice", "12 pts"));
+        assert_eq!(&view["bob"], "absent");
+        assert_eq!((&view).into_iter().count(), 3);
+
+        let best = view.scores().max_by_key(|&(_, score)| score);
+        assert_eq!(best, Some(("alice", Score::new(12))));
+        assert_eq!(view.scores().map(|(_, s)| s).sum::<Score>(), Score::new(19));
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry for key")]
+    fn test_index_missing_key_panics() {
+        let db = MemoryDatabase::new();
+        let _ = &SortedView::new(&db)["missing"];
+    }
+}
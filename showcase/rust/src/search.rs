@@ -0,0 +1,144 @@
+//! Full-text search over string values
+//!
+//! Values are split into lowercase alphanumeric tokens. Once
+//! `enable_search` is called, an inverted index from each token to the keys
+//! containing it is kept up to date by every write; until then `search`
+//! tokenizes every value on each call.
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+
+use crate::{StoredValue, StrDatabase};
+
+/// Lowercase runs of alphanumeric characters in `text`, without duplicates
+pub fn tokenize(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Keys holding each token, and how to pull tokens out of a value
+#[derive(Debug, Clone)]
+pub(crate) struct TextIndex<K, V> {
+    tokens_of: fn(&V) -> HashSet<String>,
+    postings: HashMap<String, HashSet<K>>,
+}
+
+impl<K: Hash + Eq + Clone, V> TextIndex<K, V> {
+    fn add(&mut self, key: &K, value: &V) {
+        for token in (self.tokens_of)(value) {
+            self.postings.entry(token).or_default().insert(key.clone());
+        }
+    }
+
+    /// Moves `key` from the tokens of `old` to those of `new`
+    pub(crate) fn update(&mut self, key: &K, old: Option<&V>, new: Option<&V>) {
+        if let Some(old) = old {
+            for token in (self.tokens_of)(old) {
+                if let Some(keys) = self.postings.get_mut(&token) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+        if let Some(new) = new {
+            self.add(key, new);
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Builds the inverted index `search` reads from, walking every entry once
+    pub fn enable_search(&mut self) {
+        let mut text = TextIndex {
+            tokens_of: |value: &StoredValue| tokenize(value),
+            postings: HashMap::new(),
+        };
+        for (key, value) in self.live_entries() {
+            text.add(key, value);
+        }
+        self.text_index = Some(text);
+    }
+
+    /// Keys of the live entries whose values contain every token in `query`, sorted
+    ///
+    /// Matching is on whole tokens, ignoring case; a query without tokens
+    /// matches nothing.
+    pub fn search(&self, query: &str) -> Vec<&str> {
+        let tokens = tokenize(query);
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+        let mut keys: Vec<&str> = match &self.text_index {
+            Some(text) => {
+                let mut postings = Vec::with_capacity(tokens.len());
+                for token in &tokens {
+                    match text.postings.get(token) {
+                        Some(keys) => postings.push(keys),
+                        None => return Vec::new(),
+                    }
+                }
+                // intersect starting from the rarest token
+                postings.sort_by_key(|keys| keys.len());
+                let (rarest, rest) = postings.split_first().expect("query has tokens");
+                rarest
+                    .iter()
+                    .filter(|key| rest.iter().all(|keys| keys.contains(*key)))
+                    .filter(|key| self.contains_key(*key))
+                    .map(|key| &**key)
+                    .collect()
+            }
+            None => self
+                .iter()
+                .filter(|(_, value)| tokenize(value).is_superset(&tokens))
+                .map(|(key, _)| key)
+                .collect(),
+        };
+        keys.sort_unstable();
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_search_matches_all_tokens() {
+        let mut db = MemoryDatabase::new();
+        db.insert("post:1", "Rust ownership, explained");
+        db.insert("post:2", "Why Rust? Speed and safety");
+        db.insert("post:3", "Gardening in spring");
+
+        for indexed in [false, true] {
+            if indexed {
+                db.enable_search();
+            }
+            assert_eq!(db.search("rust"), ["post:1", "post:2"]);
+            assert_eq!(db.search("RUST safety"), ["post:2"]);
+            assert!(db.search("rust gardening").is_empty());
+            assert!(db.search("rus").is_empty());
+            assert!(db.search("  ,").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_index_tracks_writes() {
+        let mut db = MemoryDatabase::new();
+        db.enable_search();
+        db.insert("a", "rust tips");
+        db.insert("b", "rust news");
+        db.insert("a", "python tips");
+        db.remove("b");
+        db.insert_with_ttl("c", "rust", std::time::Duration::ZERO);
+
+        assert!(db.search("rust").is_empty());
+        assert_eq!(db.search("tips"), ["a"]);
+        let text = db.text_index.as_ref().unwrap();
+        assert!(!text.postings.contains_key("news"));
+        assert_eq!(text.postings.get("rust").map(HashSet::len), Some(1));
+    }
+}
@@ -0,0 +1,134 @@
+//! Serde support for databases, used with the `serde` feature
+//!
+//! A `MemoryDatabase` serializes as a map of its live entries, leaving out
+//! expired ones along with TTLs, limits, indexes and subscribers, so any
+//! serde format can carry it. Deserializing builds a fresh database with
+//! default settings. `DbOperation`, `DbState` and `ServerConfig` derive the
+//! traits directly.
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::MemoryDatabase;
+
+impl<K, V, S> Serialize for MemoryDatabase<K, V, S>
+where
+    K: Serialize + Hash + Eq + Clone,
+    V: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let live = self
+            .store
+            .iter()
+            .filter(|(key, _)| !self.deadlines.expired(*key));
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in live {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+struct DatabaseVisitor<K, V, S>(PhantomData<MemoryDatabase<K, V, S>>);
+
+impl<'de, K, V, S> Visitor<'de> for DatabaseVisitor<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq + Clone,
+    V: Deserialize<'de> + Clone,
+    S: BuildHasher + Clone + Default,
+{
+    type Value = MemoryDatabase<K, V, S>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of database entries")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<Self::Value, A::Error> {
+        let mut db = MemoryDatabase::default();
+        while let Some((key, value)) = entries.next_entry()? {
+            db.put(key, value);
+        }
+        Ok(db)
+    }
+}
+
+impl<'de, K, V, S> Deserialize<'de> for MemoryDatabase<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq + Clone,
+    V: Deserialize<'de> + Clone,
+    S: BuildHasher + Clone + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(DatabaseVisitor(PhantomData))
+    }
+}
+
+#[cfg(feature = "compact-values")]
+impl Serialize for crate::compact::CompactValue {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "compact-values")]
+impl<'de> Deserialize<'de> for crate::compact::CompactValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Self::from(&*text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::enums::DbState;
+    use crate::{Database, DbOperation, MemoryDatabase, ServerConfig};
+
+    #[test]
+    fn test_database_round_trips_live_entries() {
+        let mut db = MemoryDatabase::new();
+        db.insert("name", "ferris");
+        db.insert("motto", "fast: and safe");
+        db.insert_with_ttl("session", "gone", Duration::ZERO);
+
+        let json = serde_json::to_value(&db).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "name": "ferris", "motto": "fast: and safe" })
+        );
+        let restored: MemoryDatabase = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.retrieve("motto"), Some("fast: and safe"));
+
+        let counts: MemoryDatabase<u32, Vec<u8>> =
+            serde_json::from_str(r#"{"7": [1, 2]}"#).unwrap();
+        assert_eq!(counts.get(&7), Some(&vec![1, 2]));
+        assert!(serde_json::from_str::<MemoryDatabase>("[1]").is_err());
+    }
+
+    #[test]
+    fn test_operations_and_config_derive() {
+        let op = DbOperation::Increment {
+            key: "visits".into(),
+            by: 2,
+        };
+        let json = serde_json::to_string(&op).unwrap();
+        assert_eq!(json, r#"{"op":"increment","key":"visits","by":2}"#);
+        assert_eq!(serde_json::from_str::<DbOperation>(&json).unwrap(), op);
+
+        let state = DbState::Recovering { replayed: 3 };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<DbState>(&json).unwrap(), state);
+
+        let config = ServerConfig::default();
+        let owned = serde_json::to_string(&config).unwrap();
+        let parsed: ServerConfig = serde_json::from_str(&owned).unwrap();
+        assert_eq!(parsed, config);
+    }
+}
@@ -0,0 +1,149 @@
+//! A small graph of services sharing one database
+//!
+//! The `Hub` owns its services through `Rc`; services point back at the hub
+//! and at each other through `Weak`, so the graph is freed as soon as the hub
+//! is dropped. The database itself is shared across threads behind
+//! `Arc<Mutex<_>>`.
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+
+use crate::{Database, MemoryDatabase};
+
+/// Lowercases and trims `key`, borrowing it when it is already normalized
+pub fn normalize_key(key: &str) -> Cow<'_, str> {
+    let trimmed = key.trim();
+    if trimmed.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(trimmed.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(trimmed)
+    }
+}
+
+/// Owner of every service in the graph
+pub struct Hub {
+    db: Arc<Mutex<MemoryDatabase>>,
+    services: RefCell<Vec<Rc<ServiceNode>>>,
+}
+
+impl Hub {
+    pub fn new(db: Arc<Mutex<MemoryDatabase>>) -> Rc<Self> {
+        Rc::new(Self {
+            db,
+            services: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Creates a service registered with this hub
+    pub fn spawn(self: &Rc<Self>, name: &str) -> Rc<ServiceNode> {
+        let service = Rc::new(ServiceNode {
+            name: name.to_string(),
+            hub: Rc::downgrade(self),
+            db: Arc::clone(&self.db),
+            dependencies: RefCell::new(Vec::new()),
+            writes: Cell::new(0),
+        });
+        self.services.borrow_mut().push(Rc::clone(&service));
+        service
+    }
+
+    pub fn service_names(&self) -> Vec<String> {
+        self.services
+            .borrow()
+            .iter()
+            .map(|service| service.name.clone())
+            .collect()
+    }
+}
+
+/// A service that writes namespaced keys into the shared database
+pub struct ServiceNode {
+    name: String,
+    hub: Weak<Hub>,
+    db: Arc<Mutex<MemoryDatabase>>,
+    dependencies: RefCell<Vec<Weak<ServiceNode>>>,
+    writes: Cell<usize>,
+}
+
+impl ServiceNode {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn writes(&self) -> usize {
+        self.writes.get()
+    }
+
+    /// Declares that this service needs `other`, without keeping it alive
+    pub fn depend_on(&self, other: &Rc<ServiceNode>) {
+        self.dependencies.borrow_mut().push(Rc::downgrade(other));
+    }
+
+    /// Names of the dependencies that are still alive
+    pub fn dependencies(&self) -> Vec<String> {
+        self.dependencies
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|service| service.name.clone())
+            .collect()
+    }
+
+    /// Other services registered with the same hub
+    pub fn peers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let hub = self.hub.upgrade().ok_or("hub has shut down")?;
+        let mut names = hub.service_names();
+        names.retain(|name| *name != self.name);
+        Ok(names)
+    }
+
+    /// Stores `value` under `<service>.<normalized key>`
+    pub fn record(&self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let key = normalize_key(key);
+        if key.is_empty() {
+            return Err("key must not be empty".into());
+        }
+        let mut db = self.db.lock().map_err(|e| e.to_string())?;
+        db.insert(format!("{}.{}", self.name, key), value);
+        self.writes.set(self.writes.get() + 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_key_borrows_when_possible() {
+        assert!(matches!(normalize_key("port"), Cow::Borrowed("port")));
+        assert!(matches!(normalize_key(" port "), Cow::Borrowed("port")));
+        assert_eq!(normalize_key("Port"), Cow::<str>::Owned("port".to_string()));
+    }
+
+    #[test]
+    fn test_graph_shares_database_and_frees_cleanly() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let hub = Hub::new(Arc::clone(&db));
+        let api = hub.spawn("api");
+        let cache = hub.spawn("cache");
+        api.depend_on(&cache);
+
+        api.record("Port", "8080").unwrap();
+        assert!(api.record("  ", "x").is_err());
+        assert_eq!(db.lock().unwrap().retrieve("api.port"), Some("8080"));
+        assert_eq!(api.writes(), 1);
+        assert_eq!(api.peers().unwrap(), vec!["cache"]);
+        assert_eq!(api.dependencies(), vec!["cache"]);
+        assert_eq!(Rc::strong_count(&cache), 2);
+        assert_eq!(Rc::weak_count(&cache), 1);
+
+        drop(hub);
+        assert!(api.peers().is_err());
+        drop(cache);
+        assert!(api.dependencies().is_empty());
+        assert_eq!(Arc::strong_count(&db), 2);
+    }
+}
@@ -0,0 +1,263 @@
+//! Server settings file, deserialized with serde
+//!
+//! Durations are written as `"250ms"` or `"30s"` and sizes as `"64MiB"`, so
+//! the file stays readable; unknown top-level keys are kept in `extra` rather
+//! than rejected, for forward compatibility.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A size in bytes, written with a binary unit suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteSize(pub u64);
+
+const UNITS: [(&str, u64); 4] = [
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+    ("B", 1),
+];
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (unit, scale) = UNITS
+            .into_iter()
+            .find(|&(_, scale)| self.0 >= scale && self.0.is_multiple_of(scale))
+            .unwrap_or(("B", 1));
+        write!(f, "{}{}", self.0 / scale, unit)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (digits, scale) = UNITS
+            .into_iter()
+            .find_map(|(unit, scale)| Some((s.strip_suffix(unit)?, scale)))
+            .unwrap_or((s, 1));
+        digits
+            .trim_end()
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(scale))
+            .map(ByteSize)
+            .ok_or_else(|| format!("Invalid byte size: {}", s))
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    /// Accepts `"64MiB"`-style strings or a plain number of bytes
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteSizeVisitor;
+
+        impl Visitor<'_> for ByteSizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a byte count or a size such as \"64MiB\"")
+            }
+
+            fn visit_u64<E: de::Error>(self, bytes: u64) -> Result<ByteSize, E> {
+                Ok(ByteSize(bytes))
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<ByteSize, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
+
+/// `#[serde(with)]` module writing a `ByteSize` as a bare integer
+pub mod byte_count {
+    use super::ByteSize;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(size: &ByteSize, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(size.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ByteSize, D::Error> {
+        u64::deserialize(deserializer).map(ByteSize)
+    }
+}
+
+/// `#[serde(with)]` module writing a `Duration` as `"250ms"` or `"30s"`
+pub mod duration_str {
+    use std::time::Duration;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = duration.as_millis();
+        if millis.is_multiple_of(1000) {
+            serializer.collect_str(&format_args!("{}s", millis / 1000))
+        } else {
+            serializer.collect_str(&format_args!("{}ms", millis))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let parsed = match s.strip_suffix("ms") {
+            Some(millis) => millis.parse().map(Duration::from_millis),
+            None => s.trim_end_matches('s').parse().map(Duration::from_secs),
+        };
+        parsed.map_err(|_| D::Error::custom(format!("invalid duration: {}", s)))
+    }
+}
+
+/// Where the server keeps its data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Backend {
+    Memory,
+    File {
+        path: String,
+        #[serde(default)]
+        fsync: bool,
+    },
+    Replica {
+        url: String,
+        #[serde(with = "duration_str")]
+        max_lag: Duration,
+    },
+}
+
+/// Per-request limits, written inline with the top-level settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Limits {
+    #[serde(with = "byte_count", default = "default_max_value_size")]
+    pub max_value_size: ByteSize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_keys: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_value_size: default_max_value_size(),
+            max_keys: None,
+        }
+    }
+}
+
+/// Top-level settings file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    #[serde(with = "duration_str", default = "default_request_timeout")]
+    pub request_timeout: Duration,
+    /// Memory budget; zero means unlimited
+    #[serde(default)]
+    pub max_memory: ByteSize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backends: Vec<Backend>,
+    #[serde(flatten)]
+    pub limits: Limits,
+    /// Keys this version does not know about
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_max_value_size() -> ByteSize {
+    ByteSize(1 << 20)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            listen: default_listen(),
+            request_timeout: default_request_timeout(),
+            max_memory: ByteSize::default(),
+            backends: Vec::new(),
+            limits: Limits::default(),
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_defaults_and_flatten() {
+        let json = r#"{
+            "request-timeout": "250ms",
+            "max-memory": "64MiB",
+            "max-keys": 10000,
+            "backends": [
+                {"type": "memory"},
+                {"type": "file", "path": "/var/lib/gvs/data.db"},
+                {"type": "replica", "url": "gvs://replica:7000", "max_lag": "2s"}
+            ],
+            "experimental-ttl": true
+        }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+
+        assert_eq!(settings.listen, "127.0.0.1:8080");
+        assert_eq!(settings.request_timeout, Duration::from_millis(250));
+        assert_eq!(settings.max_memory, ByteSize(64 << 20));
+        assert_eq!(settings.limits.max_keys, Some(10_000));
+        assert_eq!(settings.limits.max_value_size, ByteSize(1 << 20));
+        assert_eq!(
+            settings.backends[1],
+            Backend::File {
+                path: "/var/lib/gvs/data.db".to_string(),
+                fsync: false
+            }
+        );
+        assert_eq!(
+            settings.extra["experimental-ttl"],
+            serde_json::Value::Bool(true)
+        );
+
+        let round_trip: Settings =
+            serde_json::from_str(&serde_json::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(round_trip, settings);
+    }
+
+    #[test]
+    fn test_custom_formats() {
+        let json = serde_json::to_value(Settings::default()).unwrap();
+        assert_eq!(json["request-timeout"], "5s");
+        assert_eq!(json["max-memory"], "0B");
+        assert_eq!(json["max-value-size"], 1_048_576);
+        assert!(json.get("backends").is_none() && json.get("max-keys").is_none());
+
+        assert_eq!("4 KiB".parse(), Ok(ByteSize(4096)));
+        assert_eq!(ByteSize(1536).to_string(), "1536B");
+        assert_eq!(
+            serde_json::from_str::<ByteSize>("512").unwrap(),
+            ByteSize(512)
+        );
+        assert!(serde_json::from_str::<Settings>(r#"{"request-timeout": "soon"}"#).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Keys partitioned across independently locked databases
+//!
+//! Writes to different shards never wait on each other, so write throughput
+//! scales with the shard count until the threads outnumber it. Operations
+//! touching several keys lock one shard at a time and are not atomic.
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use crate::concurrent::{ConcurrentDatabase, SharedDatabase};
+use crate::lending::{LendingDatabase, ValueGuard};
+use crate::{DbOperation, StrDatabase};
+
+/// Shard count used by `ShardedDatabase::new`
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// A `SharedDatabase` with one lock per shard
+#[derive(Debug)]
+pub struct ShardedDatabase<S = RandomState> {
+    shards: Box<[ConcurrentDatabase<S>]>,
+    hasher: S,
+}
+
+impl ShardedDatabase {
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// Creates a database split into `shards` partitions, at least one
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl Default for ShardedDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: BuildHasher + Clone> ShardedDatabase<S> {
+    /// Creates a database whose shards and routing all hash with `hasher`
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = (0..shards.max(1))
+            .map(|_| ConcurrentDatabase::from(StrDatabase::with_hasher(hasher.clone())))
+            .collect();
+        Self { shards, hasher }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The shard holding `key`
+    pub fn shard(&self, key: &str) -> &ConcurrentDatabase<S> {
+        // the maps inside hash with the same state, so route by bits they don't use
+        let hash = self.hasher.hash_one(key) >> 40;
+        &self.shards[hash as usize % self.shards.len()]
+    }
+
+    /// Entries over all shards, each counted under its own lock
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs `op` on the shard holding its key
+    pub fn execute(&self, op: &DbOperation) -> Result<String, String> {
+        self.shard(op.key()).execute(op)
+    }
+}
+
+impl<S: BuildHasher + Clone + Send + Sync> SharedDatabase for ShardedDatabase<S> {
+    fn insert(&self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        let key = key.into();
+        self.shard(&key).insert(Arc::clone(&key), value);
+    }
+
+    fn retrieve(&self, key: &str) -> Option<Arc<str>> {
+        self.shard(key).retrieve(key)
+    }
+
+    fn remove(&self, key: &str) -> bool {
+        self.shard(key).remove(key)
+    }
+}
+
+impl<S: BuildHasher + Clone> LendingDatabase for ShardedDatabase<S> {
+    type Guard<'a>
+        = ValueGuard<'a, S>
+    where
+        S: 'a;
+
+    fn lend<'a>(&'a self, key: &str) -> Option<ValueGuard<'a, S>> {
+        self.shard(key).lend(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_keys_spread_across_shards() {
+        let db = ShardedDatabase::with_shards(8);
+        for i in 0..800 {
+            db.insert(format!("key{}", i), i.to_string());
+        }
+        assert_eq!(db.len(), 800);
+        assert!(db.shards.iter().all(|shard| shard.read().len() > 50));
+        assert_eq!(db.retrieve("key7").as_deref(), Some("7"));
+        assert_eq!(ShardedDatabase::with_shards(0).shard_count(), 1);
+
+        let op = DbOperation::Increment {
+            key: "key7".into(),
+            by: 3,
+        };
+        assert_eq!(db.execute(&op), Ok("Incremented: key7 = 10".to_string()));
+        assert_eq!(&*db.lend("key7").unwrap(), "10");
+    }
+
+    #[test]
+    fn test_writers_on_other_shards_proceed() {
+        let db = Arc::new(ShardedDatabase::new());
+        db.insert("locked", "1");
+        let held = db.shard("locked").write();
+
+        let other = (0..)
+            .map(|i| format!("free{}", i))
+            .find(|key| !std::ptr::eq(db.shard(key), db.shard("locked")))
+            .unwrap();
+        let writer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || db.insert(other, "2"))
+        };
+        writer.join().unwrap();
+        drop(held);
+        assert_eq!(db.len(), 2);
+    }
+}
@@ -0,0 +1,259 @@
+//! Copy-on-write storage and cheap immutable snapshots
+//!
+//! The store is split into shards, each behind an `Arc`. Taking a snapshot
+//! clones the shard pointers only; a later write copies just the shard it
+//! touches, so readers holding a snapshot never see a half-applied change and
+//! never hold up the writer for longer than one shard copy.
+use std::borrow::{Borrow, Cow};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::case::FoldCase;
+use crate::ttl::Deadlines;
+use crate::{persist, pipeline, StoredValue, StrDatabase};
+
+const SHARDS: usize = 16;
+
+/// `HashMap` split into `Arc`-shared shards that are copied on first write
+#[derive(Debug, Clone)]
+pub(crate) struct CowMap<K, V, S> {
+    shards: Vec<Arc<HashMap<K, V, S>>>,
+    hasher: S,
+}
+
+impl<K, V, S: Clone> CowMap<K, V, S> {
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        let shards = (0..SHARDS)
+            .map(|_| Arc::new(HashMap::with_hasher(hasher.clone())))
+            .collect();
+        Self { shards, hasher }
+    }
+}
+
+impl<K, V, S> CowMap<K, V, S> {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    pub(crate) fn shards(&self) -> &[Arc<HashMap<K, V, S>>] {
+        &self.shards
+    }
+
+    pub(crate) fn into_shards(self) -> Vec<Arc<HashMap<K, V, S>>> {
+        self.shards
+    }
+
+    /// Entries the shards can hold between them without reallocating
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.shards.iter().map(|shard| shard.capacity()).sum()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> CowMap<K, V, S> {
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        // the shards hash with the same state, so pick bits the maps don't index by
+        (self.hasher.hash_one(key) >> 32) as usize % SHARDS
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shards[self.shard(key)].get(key)
+    }
+
+    pub(crate) fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shards[self.shard(key)].get_key_value(key)
+    }
+
+    pub(crate) fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shards[self.shard(key)].contains_key(key)
+    }
+}
+
+impl<K, V, S> CowMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let shard = self.shard(&key);
+        Arc::make_mut(&mut self.shards[shard]).insert(key, value)
+    }
+
+    /// Makes room for `additional` more entries spread evenly over the shards
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        for shard in &mut self.shards {
+            Arc::make_mut(shard).reserve(additional.div_ceil(SHARDS));
+        }
+    }
+
+    pub(crate) fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = self.shard(key);
+        if !self.shards[shard].contains_key(key) {
+            // don't copy a shared shard just to find nothing to remove
+            return None;
+        }
+        Arc::make_mut(&mut self.shards[shard]).remove_entry(key)
+    }
+}
+
+/// Immutable, point-in-time view of a `MemoryDatabase`
+///
+/// Holds the shared shards and TTLs only, so cloning a snapshot is as cheap
+/// as taking one, and it can be sent to other threads for exports while the
+/// live database keeps changing. Reads leave the database's hit counters and
+/// LRU order alone.
+#[derive(Debug, Clone)]
+pub struct Snapshot<S = RandomState> {
+    store: CowMap<Arc<str>, StoredValue, S>,
+    deadlines: Deadlines<Arc<str>>,
+    fold_case: bool,
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Captures the current contents without copying any entries
+    pub fn snapshot(&self) -> Snapshot<S> {
+        Snapshot {
+            store: self.store.clone(),
+            deadlines: self.deadlines.clone(),
+            fold_case: self.fold_case,
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> Snapshot<S> {
+    pub fn retrieve(&self, key: &str) -> Option<&str> {
+        self.get(key).map(|value| &**value)
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&StoredValue> {
+        let key = if self.fold_case {
+            key.fold_case()
+        } else {
+            Cow::Borrowed(key)
+        };
+        let value = self.store.get(&*key)?;
+        (!self.deadlines.expired(&*key)).then_some(value)
+    }
+
+    /// Number of entries `iter` yields, leaving out expired ones
+    pub fn len(&self) -> usize {
+        let expired = self.deadlines.due_keys(Instant::now());
+        self.store.len() - expired.filter(|key| self.store.contains_key(*key)).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the entries as they were when the snapshot was taken
+    ///
+    /// Entries whose TTL has passed since then are skipped, as when reading.
+    pub fn iter(&self) -> pipeline::Iter<'_, S> {
+        pipeline::Iter::new(&self.store, &self.deadlines)
+    }
+
+    /// Exports the snapshot in the `save_to_file` format
+    pub fn save_to_file(&self, path: &str) -> io::Result<()> {
+        let options = persist::SaveOptions::default();
+        persist::write_atomically(path, |file| {
+            persist::write_entries(file, self.iter(), &options)
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, MemoryDatabase};
+    use std::thread;
+
+    #[test]
+    fn test_snapshot_is_isolated_from_writes() {
+        let mut db = MemoryDatabase::new();
+        db.insert("language", "Rust");
+        db.insert("year", "2010");
+
+        let snapshot = db.snapshot();
+        db.insert("language", "Rust 2021");
+        db.insert("edition", "2021");
+
+        assert_eq!(snapshot.retrieve("language"), Some("Rust"));
+        assert_eq!(snapshot.retrieve("edition"), None);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(db.retrieve("language"), Some("Rust 2021"));
+    }
+
+    #[test]
+    fn test_write_copies_only_touched_shard() {
+        let mut db = MemoryDatabase::new();
+        for i in 0..256 {
+            db.insert(format!("key{}", i), "value");
+        }
+
+        let snapshot = db.snapshot();
+        db.insert("key0", "changed");
+
+        let shared = db
+            .store
+            .shards
+            .iter()
+            .zip(&snapshot.store.shards)
+            .filter(|(live, frozen)| Arc::ptr_eq(live, frozen))
+            .count();
+        assert_eq!(shared, SHARDS - 1);
+    }
+
+    #[test]
+    fn test_snapshot_reads_leave_the_database_alone() {
+        let mut db = MemoryDatabase::new_case_insensitive();
+        db.insert("Language", "Rust");
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.retrieve("LANGUAGE"), Some("Rust"));
+        assert_eq!(snapshot.retrieve("missing"), None);
+        assert_eq!(db.stats().hits + db.stats().misses, 0);
+    }
+
+    #[test]
+    fn test_snapshot_export_on_another_thread() {
+        let mut db = MemoryDatabase::new();
+        db.insert("language", "Rust");
+        let snapshot = db.snapshot();
+
+        db.insert_with_ttl("session", "abc", std::time::Duration::ZERO);
+        let expired = db.snapshot();
+
+        let reader = thread::spawn(move || snapshot.iter().count());
+        db.insert("writer", "keeps going");
+
+        assert_eq!(reader.join().unwrap(), 1);
+        assert_eq!(expired.iter().collect::<Vec<_>>(), [("language", "Rust")]);
+        assert_eq!(expired.len(), 1);
+        assert!(!expired.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Ordered string store for range queries
+//!
+//! `MemoryDatabase` hashes its keys, so its iteration order is arbitrary.
+//! `SortedDatabase` keeps keys in a `BTreeMap` instead, trading O(1) lookups
+//! for O(log n) ones in exchange for ordered scans and ranges.
+use std::collections::btree_map::{self, BTreeMap};
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+use crate::transfer::Entries;
+use crate::Database;
+
+/// A `Database` whose entries iterate in key order
+#[derive(Debug, Clone, Default)]
+pub struct SortedDatabase {
+    map: BTreeMap<Arc<str>, Arc<str>>,
+}
+
+type Pair<'a> = (&'a Arc<str>, &'a Arc<str>);
+
+fn as_strs<'a>((key, value): Pair<'a>) -> (&'a str, &'a str) {
+    (key, value)
+}
+
+/// Entries of a `SortedDatabase` in key order
+pub type Iter<'a> =
+    std::iter::Map<btree_map::Iter<'a, Arc<str>, Arc<str>>, fn(Pair<'a>) -> (&'a str, &'a str)>;
+
+impl SortedDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over every entry in key order
+    pub fn iter(&self) -> Iter<'_> {
+        self.map.iter().map(as_strs)
+    }
+
+    /// Entries whose keys fall in `range`, in key order
+    pub fn range<'a, R>(&self, range: R) -> impl DoubleEndedIterator<Item = (&str, &str)>
+    where
+        R: RangeBounds<&'a str>,
+    {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.map.range::<str, _>(bounds).map(as_strs)
+    }
+
+    /// Entries whose keys start with `prefix`, in key order
+    ///
+    /// Seeks to `prefix` and stops at the first key past it, so only the
+    /// matching entries are visited.
+    pub fn scan_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.map
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .map(as_strs)
+            .take_while(move |(key, _)| key.starts_with(prefix))
+    }
+
+    /// Entry with the smallest key
+    pub fn first(&self) -> Option<(&str, &str)> {
+        self.map.first_key_value().map(as_strs)
+    }
+
+    /// Entry with the largest key
+    pub fn last(&self) -> Option<(&str, &str)> {
+        self.map.last_key_value().map(as_strs)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Arc<str>> {
+        self.map.remove(key)
+    }
+}
+
+impl Database for SortedDatabase {
+    fn insert(&mut self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        self.map.insert(key.into(), value.into());
+    }
+
+    fn retrieve(&self, key: &str) -> Option<&str> {
+        self.map.get(key).map(|value| &**value)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        SortedDatabase::iter(self)
+    }
+
+    fn delete(&mut self, key: &str) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl<'a> IntoIterator for &'a SortedDatabase {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for SortedDatabase {
+    type Item = (Arc<str>, Arc<str>);
+    type IntoIter = btree_map::IntoIter<Arc<str>, Arc<str>>;
+
+    /// Consumes the database, yielding its entries in key order
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl Entries for SortedDatabase {
+    type Iter<'a> = Iter<'a>;
+
+    fn entries(&self) -> Iter<'_> {
+        self.iter()
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Arc<str>> + AsRef<str>> FromIterator<(K, V)> for SortedDatabase {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let map = iter
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        Self { map }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::Backup;
+
+    #[test]
+    fn test_range_and_bounds() {
+        let db: SortedDatabase = [
+            ("user:3", "c"),
+            ("user:1", "a"),
+            ("user:2", "b"),
+            ("zone", "z"),
+        ]
+        .into_iter()
+        .collect();
+
+        let keys: Vec<&str> = db.range("user:1".."user:3").map(|(key, _)| key).collect();
+        assert_eq!(keys, ["user:1", "user:2"]);
+        let tail: Vec<&str> = db.range("user:2"..).rev().map(|(_, value)| value).collect();
+        assert_eq!(tail, ["z", "c", "b"]);
+
+        let users: Vec<&str> = db.scan_prefix("user:").map(|(_, value)| value).collect();
+        assert_eq!(users, ["a", "b", "c"]);
+        assert_eq!(db.scan_prefix("zz").count(), 0);
+
+        assert_eq!(db.first(), Some(("user:1", "a")));
+        assert_eq!(db.last(), Some(("zone", "z")));
+        assert_eq!(SortedDatabase::new().first(), None);
+    }
+
+    #[test]
+    fn test_sorted_backup_is_ordered() {
+        let mut db = SortedDatabase::new();
+        db.insert("b", "2");
+        db.insert("a", "1");
+        db.insert("c", "3");
+        assert_eq!(db.remove("c").as_deref(), Some("3"));
+
+        let mut out = Vec::new();
+        assert_eq!(db.backup_to(&mut out).unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "a:1\nb:2\n");
+        assert_eq!(db.retrieve("a"), Some("1"));
+    }
+}
@@ -0,0 +1,231 @@
+//! Per-database operation counters
+//!
+//! Reads only hold `&self`, so the counters are relaxed atomics; each
+//! operation costs one atomic increment. Clones, snapshots included,
+//! start from the counts of the database they were taken from.
+use std::hash::{BuildHasher, Hash};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::quota::ApproxSize;
+use crate::MemoryDatabase;
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    inserts: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        let copy = |counter: &AtomicU64| AtomicU64::new(counter.load(Ordering::Relaxed));
+        Self {
+            hits: copy(&self.hits),
+            misses: copy(&self.misses),
+            inserts: copy(&self.inserts),
+            deletes: copy(&self.deletes),
+        }
+    }
+}
+
+impl Counters {
+    pub(crate) fn record_read(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_insert(&self) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_delete(&self) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Number of size buckets; the last one holds everything from 1 GiB up
+const BUCKETS: usize = 32;
+
+/// Counts of sizes grouped into power-of-two buckets
+///
+/// Bucket 0 holds empty keys or values and bucket `i` sizes from `2^(i-1)`
+/// up to `2^i`, which is precise enough for capacity planning at a fixed
+/// cost however large the database grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizeHistogram {
+    counts: [usize; BUCKETS],
+}
+
+impl SizeHistogram {
+    pub fn record(&mut self, size: usize) {
+        let bucket = (usize::BITS - size.leading_zeros()) as usize;
+        self.counts[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    /// Number of sizes recorded
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Each non-empty bucket's size range and count, smallest first
+    pub fn buckets(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(bucket, &count)| {
+                let start = if bucket == 0 { 0 } else { 1 << (bucket - 1) };
+                let end = if bucket == BUCKETS - 1 {
+                    usize::MAX
+                } else {
+                    1 << bucket
+                };
+                (start..end, count)
+            })
+    }
+}
+
+impl FromIterator<usize> for SizeHistogram {
+    fn from_iter<I: IntoIterator<Item = usize>>(sizes: I) -> Self {
+        let mut histogram = Self::default();
+        sizes.into_iter().for_each(|size| histogram.record(size));
+        histogram
+    }
+}
+
+/// Point-in-time view of a database's counters and contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// Reads that found a live entry
+    pub hits: u64,
+    /// Reads of a missing or expired key
+    pub misses: u64,
+    /// Writes, counting overwrites of an existing key
+    pub inserts: u64,
+    /// Entries removed, evicted or purged after expiring
+    pub deletes: u64,
+    /// Live entries, leaving out expired ones not yet purged
+    pub entries: usize,
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+    /// Stored key sizes, as measured by `ApproxSize`
+    pub key_sizes: SizeHistogram,
+    pub value_sizes: SizeHistogram,
+}
+
+impl Stats {
+    /// Fraction of reads that hit, or 0 before the first read
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            reads => self.hits as f64 / reads as f64,
+        }
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone + ApproxSize,
+    V: Clone + ApproxSize,
+    S: BuildHasher + Clone,
+{
+    /// Current counters, with byte totals as measured by `ApproxSize`
+    ///
+    /// The counters are read without a lock, so under concurrent reads they
+    /// may be a few operations apart. Byte totals and histograms walk every
+    /// entry once.
+    pub fn stats(&self) -> Stats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let (mut key_bytes, mut value_bytes) = (0, 0);
+        let mut key_sizes = SizeHistogram::default();
+        let mut value_sizes = SizeHistogram::default();
+        for (key, value) in self.live_entries() {
+            let (key, value) = (key.approx_size(), value.approx_size());
+            key_bytes += key;
+            value_bytes += value;
+            key_sizes.record(key);
+            value_sizes.record(value);
+        }
+        Stats {
+            hits: load(&self.counters.hits),
+            misses: load(&self.counters.misses),
+            inserts: load(&self.counters.inserts),
+            deletes: load(&self.counters.deletes),
+            entries: self.len(),
+            key_bytes,
+            value_bytes,
+            key_sizes,
+            value_sizes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_counts_reads_and_writes() {
+        let mut db = MemoryDatabase::new();
+        db.insert("name", "alice");
+        db.insert("name", "bob");
+        db.insert("id", "42");
+        assert_eq!(db.retrieve("name"), Some("bob"));
+        assert_eq!(db.retrieve("missing"), None);
+        db.remove("id");
+        db.remove("id");
+
+        let stats = db.stats();
+        assert_eq!(
+            stats,
+            Stats {
+                hits: 1,
+                misses: 1,
+                inserts: 3,
+                deletes: 1,
+                entries: 1,
+                key_bytes: 4,
+                value_bytes: 3,
+                key_sizes: [4].into_iter().collect(),
+                value_sizes: [3].into_iter().collect(),
+            }
+        );
+        assert_eq!(stats.hit_rate(), 0.5);
+        assert_eq!(Stats::default().hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_evictions_count_as_deletes() {
+        let mut db: MemoryDatabase<u32, u32> =
+            MemoryDatabase::with_capacity_policy(1, crate::eviction::EvictionPolicy::Lru);
+        db.put(1, 10);
+        db.put(2, 20);
+        assert_eq!(db.get(&1), None);
+        let stats = db.stats();
+        assert_eq!((stats.inserts, stats.deletes, stats.misses), (2, 1, 1));
+        assert_eq!(stats.key_bytes + stats.value_bytes, 8);
+        assert_eq!(db.clone().stats(), stats);
+    }
+
+    #[test]
+    fn test_size_histograms() {
+        let mut db = MemoryDatabase::new();
+        db.insert("", "");
+        db.insert("a", "x".repeat(100));
+        db.insert("bb", "x".repeat(127));
+        db.insert("ccc", "x".repeat(128));
+
+        let stats = db.stats();
+        let keys: Vec<_> = stats.key_sizes.buckets().collect();
+        assert_eq!(keys, [(0..1, 1), (1..2, 1), (2..4, 2)]);
+        let values: Vec<_> = stats.value_sizes.buckets().collect();
+        assert_eq!(values, [(0..1, 1), (64..128, 2), (128..256, 1)]);
+        assert_eq!(stats.value_sizes.total(), stats.entries);
+
+        let huge: SizeHistogram = [usize::MAX].into_iter().collect();
+        assert_eq!(huge.buckets().next(), Some((1 << 30..usize::MAX, 1)));
+    }
+}
@@ -0,0 +1,146 @@
+//! Paged, cursor-based scans behind an async trait
+//!
+//! `AsyncScan` is what a networked store would implement; the in-memory
+//! implementations below never wait, so `block_on` can drive them without a
+//! runtime.
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use crate::{Database, StrDatabase};
+
+/// One page of a scan, in key order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Page {
+    pub entries: Vec<(String, String)>,
+    /// Cursor for the following page, or `None` after the last one
+    pub next: Option<String>,
+}
+
+// Callers drive scans from one thread, so the futures need not be `Send`
+#[allow(async_fn_in_trait)]
+pub trait AsyncScan {
+    /// Up to `limit` entries with keys after `cursor`; a limit of 0 is treated as 1
+    async fn page(&self, cursor: Option<&str>, limit: usize) -> Page;
+
+    /// Every entry, fetched `page_size` at a time
+    async fn collect_all(&self, page_size: usize) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self.page(cursor.as_deref(), page_size).await;
+            entries.extend(page.entries);
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => return entries,
+            }
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> AsyncScan for StrDatabase<S> {
+    async fn page(&self, cursor: Option<&str>, limit: usize) -> Page {
+        let limit = limit.max(1);
+        let mut entries: Vec<_> = sorted_after(self.scan(), cursor).collect();
+        entries.truncate(limit.saturating_add(1));
+        let next = (entries.len() > limit).then(|| {
+            entries.pop();
+            entries[limit - 1].0.to_string()
+        });
+        Page {
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            next,
+        }
+    }
+}
+
+/// Entries of `entries` with keys after `cursor`, sorted by key
+fn sorted_after<'a>(
+    entries: impl Iterator<Item = (&'a str, &'a str)>,
+    cursor: Option<&'a str>,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    let mut sorted: Vec<_> = entries
+        .filter(|&(key, _)| cursor.is_none_or(|cursor| key > cursor))
+        .collect();
+    sorted.sort_unstable_by_key(|&(key, _)| key);
+    sorted.into_iter()
+}
+
+/// Keys starting with `prefix`, in no particular order
+pub fn keys_with_prefix<'a, S: BuildHasher + Clone>(
+    db: &'a StrDatabase<S>,
+    prefix: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    db.scan_prefix(prefix).map(|(key, _)| key)
+}
+
+/// Inserts every pair from `entries`, returning how many were inserted
+pub fn insert_all(
+    db: &mut impl Database,
+    entries: impl IntoIterator<Item = (impl Into<Arc<str>>, impl Into<Arc<str>> + AsRef<str>)>,
+) -> usize {
+    entries
+        .into_iter()
+        .map(|(key, value)| db.insert(key, value))
+        .count()
+}
+
+/// Polls `future` to completion on the current thread
+///
+/// Meant for futures that never wait on I/O, like the in-memory scans here;
+/// a future that returns `Pending` is simply polled again.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_paged_scan() {
+        let mut db = MemoryDatabase::new();
+        insert_all(&mut db, [("c", "3"), ("a", "1"), ("b", "2")]);
+
+        let first = block_on(db.page(None, 2));
+        assert_eq!(
+            first.entries,
+            [("a".into(), "1".into()), ("b".into(), "2".into())]
+        );
+        assert_eq!(first.next.as_deref(), Some("b"));
+        let last = block_on(db.page(first.next.as_deref(), 2));
+        assert_eq!((last.entries.len(), last.next), (1, None));
+
+        assert_eq!(block_on(db.collect_all(1)).len(), 3);
+        assert_eq!(block_on(db.collect_all(0)).len(), 3);
+        assert_eq!(block_on(db.page(Some("c"), 5)), Page::default());
+    }
+
+    #[test]
+    fn test_impl_trait_helpers() {
+        let mut db = MemoryDatabase::new();
+        let pairs = vec![
+            (String::from("user:1"), "ferris"),
+            ("user:2".into(), "corro"),
+        ];
+        assert_eq!(insert_all(&mut db, pairs), 2);
+        db.insert("theme", "gruv");
+
+        let mut users: Vec<_> = keys_with_prefix(&db, "user:").collect();
+        users.sort_unstable();
+        assert_eq!(users, ["user:1", "user:2"]);
+    }
+}
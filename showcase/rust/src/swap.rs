@@ -0,0 +1,152 @@
+//! A shared database whose readers never take a lock
+//!
+//! Readers load the latest published `Snapshot` through an `ArcSwap`, which
+//! costs a few atomic operations and never waits on a writer. Writers take
+//! turns on one mutex, change the database in place, then publish a fresh
+//! snapshot. Publishing copies only the shards written since the last one,
+//! so this suits read-dominated workloads; secondary indexes, search and
+//! history are copied whole on every write, so leave them off here.
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use arc_swap::ArcSwap;
+
+use crate::concurrent::SharedDatabase;
+use crate::snapshot::Snapshot;
+use crate::{shared, Database, DbOperation, MemoryDatabase, StrDatabase};
+
+/// A `SharedDatabase` read through published snapshots
+///
+/// Subscriptions and other state that clones don't share live on the
+/// writer's copy, so `subscribe` inside `write` sees every change.
+#[derive(Debug)]
+pub struct SwapDatabase<S = RandomState> {
+    published: ArcSwap<Snapshot<S>>,
+    writer: Mutex<StrDatabase<S>>,
+}
+
+impl SwapDatabase {
+    pub fn new() -> Self {
+        Self::from(MemoryDatabase::new())
+    }
+}
+
+impl<S: Default + BuildHasher + Clone> Default for SwapDatabase<S> {
+    fn default() -> Self {
+        Self::from(StrDatabase::default())
+    }
+}
+
+impl<S: BuildHasher + Clone> From<StrDatabase<S>> for SwapDatabase<S> {
+    fn from(db: StrDatabase<S>) -> Self {
+        Self {
+            published: ArcSwap::from_pointee(db.snapshot()),
+            writer: Mutex::new(db),
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> SwapDatabase<S> {
+    /// The latest published contents, unaffected by later writes
+    pub fn snapshot(&self) -> Arc<Snapshot<S>> {
+        self.published.load_full()
+    }
+
+    /// Runs `write` on the database, then publishes the result to readers
+    ///
+    /// Group several writes in one call to publish them together. A panic
+    /// in `write` publishes nothing; the next write publishes what it left.
+    pub fn write<R>(&self, write: impl FnOnce(&mut StrDatabase<S>) -> R) -> R {
+        let mut db = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = write(&mut db);
+        self.published.store(Arc::new(db.snapshot()));
+        result
+    }
+
+    /// Runs `op`, reading from the latest snapshot without locking
+    pub fn execute(&self, op: &DbOperation) -> Result<String, String> {
+        let snapshot = self.published.load();
+        if let Some(result) = op.execute_lookup(|key| snapshot.retrieve(key)) {
+            return result;
+        }
+        self.write(|db| op.execute(db))
+    }
+
+    pub fn into_inner(self) -> StrDatabase<S> {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<S: BuildHasher + Clone + Send + Sync> SharedDatabase for SwapDatabase<S> {
+    fn insert(&self, key: impl Into<Arc<str>>, value: impl Into<Arc<str>> + AsRef<str>) {
+        self.write(|db| db.insert(key, value));
+    }
+
+    fn retrieve(&self, key: &str) -> Option<Arc<str>> {
+        self.published.load().get(key).cloned().map(shared)
+    }
+
+    fn remove(&self, key: &str) -> bool {
+        self.write(|db| db.remove(key).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_readers_see_whole_writes() {
+        let db = Arc::new(SwapDatabase::new());
+        db.insert("from", "10");
+        db.insert("to", "0");
+        let before = db.snapshot();
+
+        let reader = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    let snapshot = db.snapshot();
+                    let total: i64 = ["from", "to"]
+                        .iter()
+                        .map(|key| snapshot.retrieve(key).unwrap().parse::<i64>().unwrap())
+                        .sum();
+                    assert_eq!(total, 10);
+                }
+            })
+        };
+        let transfer = [
+            DbOperation::Increment {
+                key: "from".into(),
+                by: -1,
+            },
+            DbOperation::Increment {
+                key: "to".into(),
+                by: 1,
+            },
+        ];
+        for _ in 0..10 {
+            db.write(|db| db.execute_all(&transfer)).unwrap();
+        }
+        reader.join().unwrap();
+
+        assert_eq!(before.retrieve("from"), Some("10"));
+        assert_eq!(db.retrieve("to").as_deref(), Some("10"));
+        assert!(db.remove("to"));
+        assert_eq!(db.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_subscribers_live_on_the_writer() {
+        let db = SwapDatabase::new();
+        let events = db.write(|db| db.subscribe());
+        db.insert("k", "v");
+        let op = DbOperation::Retrieve { key: "k".into() };
+        assert_eq!(db.execute(&op), Ok("Retrieved: k = v".to_string()));
+        assert_eq!(events.try_iter().count(), 1);
+    }
+}
@@ -0,0 +1,122 @@
+//! Background removal of expired entries
+//!
+//! TTL entries only read as absent once expired; the sweeper drops them from
+//! a shared database every `interval` so memory is reclaimed between reads.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{perf, Error, MemoryDatabase, Service};
+
+/// Periodically calls `purge_expired` on a shared database
+pub struct Sweeper {
+    db: Arc<Mutex<MemoryDatabase>>,
+    interval: Duration,
+    purged: Arc<AtomicUsize>,
+    worker: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl Sweeper {
+    pub fn new(db: Arc<Mutex<MemoryDatabase>>, interval: Duration) -> Self {
+        Self {
+            db,
+            interval,
+            purged: Arc::default(),
+            worker: None,
+        }
+    }
+
+    /// Expired entries removed since the sweeper was created
+    pub fn purged(&self) -> usize {
+        self.purged.load(Ordering::Relaxed)
+    }
+}
+
+impl Service for Sweeper {
+    /// Starts sweeping every `interval` on a background thread
+    fn start(&mut self) -> Result<(), Error> {
+        if self.worker.is_some() {
+            return Err(Error::AlreadyRunning("Sweeper"));
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let db = Arc::clone(&self.db);
+        let purged = Arc::clone(&self.purged);
+        let interval = self.interval;
+        // a stop message or a dropped sender ends the loop
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let waiting = perf::start();
+                let mut guard = db.lock().unwrap_or_else(|e| e.into_inner());
+                perf::record_lock_wait(waiting);
+                purged.fetch_add(guard.purge_expired(), Ordering::Relaxed);
+            }
+        });
+        self.worker = Some((stop, handle));
+        Ok(())
+    }
+
+    /// Signals the background thread and waits for it to finish
+    fn stop(&mut self) -> Result<(), Error> {
+        if let Some((stop, handle)) = self.worker.take() {
+            let _ = stop.send(());
+            handle
+                .join()
+                .map_err(|_| Error::WorkerPanicked("Sweeper"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+impl MemoryDatabase {
+    /// Starts a `Sweeper` over `db`; it stops when the returned handle is dropped
+    pub fn start_sweeper(db: &Arc<Mutex<Self>>, interval: Duration) -> Sweeper {
+        let mut sweeper = Sweeper::new(Arc::clone(db), interval);
+        sweeper.start().expect("a new sweeper is not running yet");
+        sweeper
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_sweeper_purges_in_background() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        {
+            let mut db = db.lock().unwrap();
+            db.insert_with_ttl("gone", "1", Duration::ZERO);
+            db.insert_with_ttl("kept", "2", Duration::from_secs(3600));
+        }
+
+        let sweeper = MemoryDatabase::start_sweeper(&db, Duration::from_millis(5));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(sweeper.purged(), 1);
+        drop(sweeper);
+
+        let db = db.lock().unwrap();
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.retrieve("kept"), Some("2"));
+    }
+
+    #[test]
+    fn test_sweeper_lifecycle() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let mut sweeper = Sweeper::new(db, Duration::from_millis(5));
+        sweeper.start().unwrap();
+        assert!(matches!(sweeper.start(), Err(Error::AlreadyRunning(_))));
+        sweeper.stop().unwrap();
+        sweeper.stop().unwrap();
+        assert_eq!(sweeper.purged(), 0);
+    }
+}
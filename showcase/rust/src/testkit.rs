@@ -0,0 +1,235 @@
+//! Conformance checks for `Database` implementations
+//!
+//! Backends call `run_conformance` with a constructor from their own tests;
+//! every check panics with a description of the first mismatch found.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{Database, DbOperation};
+
+/// Deterministic xorshift generator for keys, values and operation sequences
+#[derive(Debug, Clone)]
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// Returns a value in `0..bound`
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound.max(1) as u64) as usize
+    }
+
+    /// Short keys drawn from a small space so sequences revisit them
+    pub fn key(&mut self) -> String {
+        format!("key:{}", self.below(16))
+    }
+
+    /// Values mixing ASCII, spaces and multi-byte characters
+    pub fn value(&mut self) -> String {
+        const ALPHABET: [char; 8] = ['a', 'z', '0', '9', ' ', '-', 'é', '🦀'];
+        (0..self.below(12))
+            .map(|_| ALPHABET[self.below(ALPHABET.len())])
+            .collect()
+    }
+
+    /// Inserts, retrievals and deletes, the operations every `Database` supports
+    pub fn ops(&mut self, len: usize) -> Vec<DbOperation> {
+        (0..len)
+            .map(|_| match self.below(6) {
+                0 | 1 => DbOperation::Retrieve {
+                    key: self.key().into(),
+                },
+                2 => DbOperation::Delete {
+                    key: self.key().into(),
+                },
+                _ => DbOperation::Insert {
+                    key: self.key().into(),
+                    value: self.value().into(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Runs every check against fresh databases built by `make`
+pub fn run_conformance<D, F>(mut make: F)
+where
+    D: Database,
+    F: FnMut() -> D,
+{
+    check_missing_key(&mut make());
+    check_insert_then_retrieve(&mut make());
+    check_overwrite(&mut make());
+    check_delete(&mut make());
+    check_clear(&mut make());
+    check_try_insert(&mut make());
+    for seed in 1..=32 {
+        check_against_model(&mut make(), seed, 64);
+    }
+}
+
+pub fn check_missing_key<D: Database>(db: &mut D) {
+    assert!(
+        db.retrieve("missing").is_none(),
+        "empty database returned a value"
+    );
+}
+
+pub fn check_insert_then_retrieve<D: Database>(db: &mut D) {
+    db.insert("language".to_string(), "Rust".to_string());
+    assert_eq!(
+        db.retrieve("language"),
+        Some("Rust"),
+        "inserted value not returned"
+    );
+}
+
+pub fn check_overwrite<D: Database>(db: &mut D) {
+    db.insert("key".to_string(), "first".to_string());
+    db.insert("key".to_string(), "second".to_string());
+    assert_eq!(
+        db.retrieve("key"),
+        Some("second"),
+        "insert did not replace the previous value"
+    );
+}
+
+pub fn check_delete<D: Database>(db: &mut D) {
+    db.insert("key".to_string(), "value".to_string());
+    assert!(db.delete("key"), "delete of a stored key returned false");
+    assert!(!db.delete("key"), "second delete returned true");
+    assert!(!db.contains_key("key"), "deleted key still present");
+    assert!(
+        db.is_empty(),
+        "database not empty after deleting its only key"
+    );
+}
+
+pub fn check_clear<D: Database>(db: &mut D) {
+    db.insert("a".to_string(), "1".to_string());
+    db.insert("b".to_string(), "2".to_string());
+    assert_eq!(db.len(), 2, "len not counting two inserts");
+    db.clear();
+    assert!(db.is_empty(), "clear left entries behind");
+    assert_eq!(db.iter().count(), 0, "iter yielded entries after clear");
+}
+
+pub fn check_try_insert<D: Database>(db: &mut D) {
+    assert!(
+        db.try_insert("key".to_string(), "value".to_string())
+            .is_ok(),
+        "unbounded database refused a write"
+    );
+    assert_eq!(db.retrieve("key"), Some("value"), "try_insert not stored");
+}
+
+/// Checks a database whose only entry, under `key`, has expired unpurged
+///
+/// For stores with TTLs: every accessor has to treat the entry as absent,
+/// before and after a generated sequence runs around it.
+pub fn check_expired_entry<D: Database>(db: &mut D, key: &str) {
+    assert!(db.retrieve(key).is_none(), "expired entry returned");
+    assert!(!db.contains_key(key), "expired entry still contained");
+    assert_eq!(db.len(), 0, "len counting an expired entry");
+    assert!(db.is_empty(), "is_empty false with only an expired entry");
+    assert_eq!(db.iter().count(), 0, "iter yielded an expired entry");
+    check_against_model(db, 1, 64);
+    assert!(!db.delete(key), "delete of an expired entry returned true");
+}
+
+/// Replays a generated sequence, comparing every read, the entry count and
+/// the full contents with a `HashMap` model after each step
+pub fn check_against_model<D: Database>(db: &mut D, seed: u64, len: usize) {
+    let mut model: HashMap<Arc<str>, Arc<str>> = HashMap::new();
+    for (step, op) in Gen::new(seed).ops(len).into_iter().enumerate() {
+        let context = format!("seed {} step {}: {:?}", seed, step, op);
+        match op {
+            DbOperation::Insert { key, value } => {
+                db.insert(Arc::clone(&key), Arc::clone(&value));
+                model.insert(key, value);
+            }
+            DbOperation::Retrieve { key } => assert_eq!(
+                db.retrieve(&key),
+                model.get(&key).map(|value| &**value),
+                "{}: retrieve diverged from model",
+                context
+            ),
+            DbOperation::Delete { key } => assert_eq!(
+                db.delete(&key),
+                model.remove(&key).is_some(),
+                "{}: delete diverged from model",
+                context
+            ),
+            _ => unreachable!("Gen::ops only yields inserts, retrievals and deletes"),
+        }
+        assert_eq!(db.len(), model.len(), "{}: len diverged", context);
+        assert_eq!(
+            db.is_empty(),
+            model.is_empty(),
+            "{}: is_empty diverged",
+            context
+        );
+        for key in model.keys() {
+            assert!(
+                db.contains_key(key),
+                "{}: contains_key({:?}) false",
+                context,
+                key
+            );
+        }
+        let mut entries: Vec<(&str, &str)> = db.iter().collect();
+        entries.sort_unstable();
+        let mut expected: Vec<(&str, &str)> = model
+            .iter()
+            .map(|(key, value)| (&**key, &**value))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(entries, expected, "{}: iter diverged", context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted::SortedDatabase;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_memory_database_conforms() {
+        run_conformance(MemoryDatabase::new);
+    }
+
+    #[test]
+    fn test_sorted_database_conforms() {
+        run_conformance(SortedDatabase::new);
+    }
+
+    #[test]
+    fn test_expired_entries_conform() {
+        let mut db = MemoryDatabase::new();
+        db.insert_with_ttl("session", "abc", std::time::Duration::ZERO);
+        check_expired_entry(&mut db, "session");
+    }
+
+    #[test]
+    fn test_gen_is_deterministic() {
+        let (mut first, mut second) = (Gen::new(7), Gen::new(7));
+        let a: Vec<String> = (0..8).map(|_| first.value()).collect();
+        let b: Vec<String> = (0..8).map(|_| second.value()).collect();
+        assert_eq!(a, b);
+        assert_eq!(Gen::new(3).ops(10).len(), 10);
+    }
+}
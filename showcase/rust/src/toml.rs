@@ -0,0 +1,124 @@
+//! TOML import and export, for databases edited by hand
+//!
+//! Dotted keys are namespaced into sections: `server.host` is written as
+//! `host` under `[server]`, and nested tables are joined back with `.` on
+//! load. Numbers and booleans typed by hand are read as their text.
+use std::hash::BuildHasher;
+use std::io;
+
+use toml::{Table, Value};
+
+use crate::{Database, MemoryDatabase, StrDatabase};
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Serializes the database as TOML, one section per key prefix
+    ///
+    /// Fails with `InvalidData` if a key is also used as a section, e.g.
+    /// both `server` and `server.host` are set.
+    pub fn to_toml(&self) -> io::Result<String> {
+        let mut root = Table::new();
+        for (key, value) in self.scan() {
+            let (path, name) = match key.rsplit_once('.') {
+                Some((section, name)) => (section.split('.').collect(), name),
+                None => (Vec::new(), key),
+            };
+
+            let mut table = &mut root;
+            for part in path {
+                let entry = table
+                    .entry(part)
+                    .or_insert_with(|| Value::Table(Table::new()));
+                table = match entry {
+                    Value::Table(section) => section,
+                    _ => return Err(invalid(format!("Key is also a section: {}", key))),
+                };
+            }
+            if table
+                .insert(name.into(), Value::String(value.into()))
+                .is_some()
+            {
+                return Err(invalid(format!("Key is also a section: {}", key)));
+            }
+        }
+        toml::to_string(&root).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+fn flatten(prefix: &str, table: Table, db: &mut MemoryDatabase) -> io::Result<()> {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        let text = match value {
+            Value::Table(section) => {
+                flatten(&key, section, db)?;
+                continue;
+            }
+            Value::String(s) => s,
+            Value::Integer(n) => n.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Datetime(d) => d.to_string(),
+            Value::Array(_) => return Err(invalid(format!("Arrays are not supported: {}", key))),
+        };
+        db.insert(key, text);
+    }
+    Ok(())
+}
+
+impl MemoryDatabase {
+    /// Parses TOML written by `to_toml` or by hand
+    pub fn from_toml(input: &str) -> io::Result<Self> {
+        let table: Table = input
+            .parse()
+            .map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+        let mut db = MemoryDatabase::new();
+        flatten("", table, &mut db)?;
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_round_trip_uses_sections() {
+        let mut db = MemoryDatabase::new();
+        db.insert("name", "gvs");
+        db.insert("server.host", "localhost");
+        db.insert("server.tls.cert", "a:b\nc");
+
+        let text = db.to_toml().unwrap();
+        assert!(text.starts_with("name = \"gvs\"\n"));
+        assert!(text.contains("[server]\nhost = \"localhost\"\n"));
+        assert!(text.contains("[server.tls]\n"));
+
+        let loaded = MemoryDatabase::from_toml(&text).unwrap();
+        assert_eq!(loaded.scan().count(), 3);
+        assert_eq!(loaded.retrieve("server.tls.cert"), Some("a:b\nc"));
+    }
+
+    #[test]
+    fn test_toml_hand_written_values_and_conflicts() {
+        let input = "debug = true\n[server]\nport = 8080\nratio = 0.5\n";
+        let db = MemoryDatabase::from_toml(input).unwrap();
+        assert_eq!(db.retrieve("debug"), Some("true"));
+        assert_eq!(db.retrieve("server.port"), Some("8080"));
+        assert_eq!(db.retrieve("server.ratio"), Some("0.5"));
+
+        let err = MemoryDatabase::from_toml("tags = [\"a\"]").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut db = MemoryDatabase::new();
+        db.insert("server", "on");
+        db.insert("server.port", "80");
+        assert_eq!(db.to_toml().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
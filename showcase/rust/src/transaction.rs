@@ -0,0 +1,150 @@
+//! All-or-nothing groups of writes
+//!
+//! A `Transaction` works on a clone of the database. Entries and TTLs are
+//! shared copy-on-write, costing one shard copy per shard written to, but
+//! LRU order, indexes, history and the changelog are copied by `begin`.
+//! `commit` swaps the clone in with a single assignment; dropping or
+//! rolling back just discards it.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::changes::{ChangeEvent, Changes};
+use crate::{DbOperation, MemoryDatabase, StoredValue, StrDatabase};
+
+/// Pending writes to a `MemoryDatabase`, applied together on `commit`
+///
+/// Derefs to the working copy, so reads inside the transaction see its own
+/// writes while the database itself stays untouched until `commit`.
+#[must_use = "a transaction is rolled back unless committed"]
+pub struct Transaction<'a, K = Arc<str>, V = StoredValue, S = RandomState> {
+    db: &'a mut MemoryDatabase<K, V, S>,
+    working: MemoryDatabase<K, V, S>,
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Starts a transaction; nothing it writes is visible here until `commit`
+    pub fn begin(&mut self) -> Transaction<'_, K, V, S> {
+        let mut working = self.clone();
+        working.changes = self.changes.as_ref().map(|_| Changes::deferred());
+        working.hooks = self.hooks.deferred();
+        Transaction { working, db: self }
+    }
+}
+
+impl<K: Clone, V: Clone, S> Transaction<'_, K, V, S> {
+    /// Applies every write made through the transaction at once
+    ///
+    /// Subscribers receive the transaction's events, and hooks its writes,
+    /// only now, in order.
+    pub fn commit(mut self) {
+        let calls = self.working.hooks.take_deferred();
+        let events = self
+            .working
+            .changes
+            .as_mut()
+            .map(Changes::take_deferred)
+            .unwrap_or_default();
+        self.working.changes = self.db.changes.take();
+        *self.db = self.working;
+        self.db.hooks.run_deferred(calls);
+        if let Some(changes) = &mut self.db.changes {
+            for ChangeEvent { key, kind } in events {
+                changes.notify(key, kind);
+            }
+        }
+    }
+
+    /// Discards every write made through the transaction
+    pub fn rollback(self) {}
+}
+
+impl<K, V, S> Deref for Transaction<'_, K, V, S> {
+    type Target = MemoryDatabase<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.working
+    }
+}
+
+impl<K, V, S> DerefMut for Transaction<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.working
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Runs `ops` in order, keeping their writes only if every one succeeds
+    ///
+    /// Returns each operation's message, or the first error; any failure,
+    /// including a missing key on `Retrieve`, leaves the database unchanged.
+    pub fn execute_all(&mut self, ops: &[DbOperation]) -> Result<Vec<String>, String> {
+        let mut tx = self.begin();
+        let messages = ops
+            .iter()
+            .map(|op| op.execute(&mut *tx))
+            .collect::<Result<_, _>>()?;
+        tx.commit();
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_commit_and_rollback() {
+        let mut db = MemoryDatabase::new();
+        db.insert("balance", "10");
+
+        let mut tx = db.begin();
+        tx.insert("balance", "5");
+        tx.remove("balance");
+        tx.insert("audit", "debited");
+        assert_eq!(tx.retrieve("audit"), Some("debited"));
+        tx.rollback();
+        assert_eq!(db.retrieve("balance"), Some("10"));
+        assert_eq!(db.retrieve("audit"), None);
+
+        let mut tx = db.begin();
+        tx.insert("balance", "5");
+        tx.insert("audit", "debited");
+        tx.commit();
+        assert_eq!(db.retrieve("balance"), Some("5"));
+        assert_eq!(db.len(), 2);
+    }
+
+    #[test]
+    fn test_execute_all_is_atomic() {
+        let mut db = MemoryDatabase::new();
+        let transfer = |to: &str| {
+            [
+                DbOperation::Increment {
+                    key: "alice".into(),
+                    by: -3,
+                },
+                DbOperation::Update {
+                    key: to.into(),
+                    value: "3".into(),
+                },
+            ]
+        };
+        db.insert("bob", "0");
+
+        let err = db.execute_all(&transfer("carol")).unwrap_err();
+        assert_eq!(err, "Key not found: carol");
+        assert_eq!(db.retrieve("alice"), None);
+
+        let messages = db.execute_all(&transfer("bob")).unwrap();
+        assert_eq!(messages, ["Incremented: alice = -3", "Updated: bob = 3"]);
+        assert_eq!(db.retrieve("alice"), Some("-3"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Generic backup, restore and migration between stores
+//!
+//! Anything that is both a [`Database`] and can list its [`Entries`] gets
+//! [`Backup`] for free through a blanket impl, and [`Migration`] copies
+//! between any two stores, optionally rewriting entries on the way.
+use std::borrow::Cow;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, Write};
+
+use crate::pipeline;
+use crate::snapshot::Snapshot;
+use crate::{persist, Database, StrDatabase};
+
+/// A store that can list every entry it holds
+pub trait Entries {
+    type Iter<'a>: Iterator<Item = (&'a str, &'a str)>
+    where
+        Self: 'a;
+
+    fn entries(&self) -> Self::Iter<'_>;
+}
+
+impl<S: BuildHasher + Clone> Entries for StrDatabase<S> {
+    type Iter<'a>
+        = pipeline::Iter<'a, S>
+    where
+        S: 'a;
+
+    fn entries(&self) -> pipeline::Iter<'_, S> {
+        self.iter()
+    }
+}
+
+impl<S: BuildHasher + Clone> Entries for Snapshot<S> {
+    type Iter<'a>
+        = pipeline::Iter<'a, S>
+    where
+        S: 'a;
+
+    fn entries(&self) -> pipeline::Iter<'_, S> {
+        self.iter()
+    }
+}
+
+/// Streams a store to and from the `key:value` line format
+pub trait Backup {
+    /// Writes every entry, returning how many were written
+    fn backup_to<W: Write>(&self, out: W) -> io::Result<usize>;
+
+    /// Inserts every `key:value` line from `input`, returning how many were read
+    ///
+    /// Accepts files from `save_to_file` too, verifying their checksums.
+    fn restore_from<R: BufRead>(&mut self, input: R) -> io::Result<usize>;
+}
+
+impl<T> Backup for T
+where
+    T: Database + Entries + ?Sized,
+{
+    fn backup_to<W: Write>(&self, mut out: W) -> io::Result<usize> {
+        let mut written = 0;
+        for (key, value) in self.entries() {
+            writeln!(out, "{}", persist::encode_line(key, value))?;
+            written += 1;
+        }
+        out.flush()?;
+        Ok(written)
+    }
+
+    fn restore_from<R: BufRead>(&mut self, mut input: R) -> io::Result<usize> {
+        let mut read = 0;
+        let mut checksums = persist::Checksums::default();
+        let mut buffer = String::new();
+        let mut number = 0;
+        while input.read_line(&mut buffer)? > 0 {
+            number += 1;
+            let line = checksums.verify(number, &buffer)?;
+            if !line.is_empty() {
+                let (key, value) = checksums
+                    .decode(line)
+                    .map_err(|e| persist::invalid_line(number, e))?;
+                self.insert(key, value);
+                read += 1;
+            }
+            buffer.clear();
+        }
+        Ok(read)
+    }
+}
+
+/// Rewrites or drops entries during a [`Migration`]
+pub trait Transform {
+    fn apply<'a>(&self, key: &'a str, value: &'a str) -> Option<(Cow<'a, str>, Cow<'a, str>)>;
+}
+
+/// Copies entries unchanged
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl Transform for Identity {
+    fn apply<'a>(&self, key: &'a str, value: &'a str) -> Option<(Cow<'a, str>, Cow<'a, str>)> {
+        Some((Cow::Borrowed(key), Cow::Borrowed(value)))
+    }
+}
+
+impl<F> Transform for F
+where
+    F: for<'a> Fn(&'a str, &'a str) -> Option<(Cow<'a, str>, Cow<'a, str>)>,
+{
+    fn apply<'a>(&self, key: &'a str, value: &'a str) -> Option<(Cow<'a, str>, Cow<'a, str>)> {
+        self(key, value)
+    }
+}
+
+/// Copies every entry of one store into another through a [`Transform`]
+#[derive(Debug, Clone, Default)]
+pub struct Migration<T = Identity> {
+    transform: T,
+}
+
+impl Migration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Transform> Migration<T> {
+    pub fn with(transform: T) -> Self {
+        Self { transform }
+    }
+
+    /// Runs the migration, returning how many entries were written to `dst`
+    pub fn run<Src, Dst>(&self, src: &Src, dst: &mut Dst) -> usize
+    where
+        Src: Entries + ?Sized,
+        Dst: Database + ?Sized,
+    {
+        src.entries()
+            .filter_map(|(key, value)| self.transform.apply(key, value))
+            .map(|(key, value)| dst.insert(key.into_owned(), value.into_owned()))
+            .count()
+    }
+}
+
+/// Total byte length of a batch of keys or values
+pub fn total_len<I>(items: I) -> usize
+where
+    I: IntoIterator<Item: AsRef<str>>,
+{
+    items.into_iter().map(|item| item.as_ref().len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_blanket_backup_round_trip() {
+        let mut db = MemoryDatabase::new();
+        db.insert("language", "Rust");
+        db.insert("url", "https://rust-lang.org");
+        db.insert_with_ttl("session", "expired", std::time::Duration::ZERO);
+
+        let mut buffer = Vec::new();
+        assert_eq!(db.backup_to(&mut buffer).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap().lines().count(),
+            2
+        );
+
+        let mut restored = MemoryDatabase::new();
+        assert_eq!(restored.restore_from(buffer.as_slice()).unwrap(), 2);
+        assert_eq!(restored.retrieve("url"), Some("https://rust-lang.org"));
+    }
+
+    #[test]
+    fn test_migration_with_transform() {
+        let mut src = MemoryDatabase::new();
+        src.insert("Language", "Rust");
+        src.insert("__internal", "skip me");
+
+        fn lowercase_public<'a>(
+            key: &'a str,
+            value: &'a str,
+        ) -> Option<(Cow<'a, str>, Cow<'a, str>)> {
+            if key.starts_with("__") {
+                return None;
+            }
+            Some((key.to_lowercase().into(), Cow::Borrowed(value)))
+        }
+
+        let mut dst = MemoryDatabase::new();
+        assert_eq!(Migration::with(lowercase_public).run(&src, &mut dst), 1);
+        assert_eq!(dst.retrieve("language"), Some("Rust"));
+
+        let mut copy = MemoryDatabase::new();
+        assert_eq!(Migration::new().run(&src.snapshot(), &mut copy), 2);
+        assert_eq!(total_len(["ab", "cde"]), 5);
+        assert_eq!(total_len(vec![String::from("xyz")]), 3);
+    }
+}
@@ -0,0 +1,221 @@
+//! Per-key expiry deadlines
+//!
+//! Expired entries stay in the store until they are overwritten or purged,
+//! but reads and saves treat them as absent once their deadline passes.
+//! `on_expire` callbacks see each one as it is finally dropped.
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::hooks::Hook;
+use crate::{stored, MemoryDatabase, StrDatabase};
+
+/// Deadlines of the keys inserted with a TTL, shared with snapshots until written
+#[derive(Debug, Clone)]
+pub(crate) struct Deadlines<K> {
+    map: Arc<HashMap<K, Instant>>,
+}
+
+impl<K> Default for Deadlines<K> {
+    fn default() -> Self {
+        Self {
+            map: Arc::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Deadlines<K> {
+    /// Returns true when `key` has a deadline at or before `now`
+    pub(crate) fn expired_at<Q>(&self, key: &Q, now: Instant) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key).is_some_and(|&deadline| deadline <= now)
+    }
+
+    /// Returns true when `key` has already expired
+    pub(crate) fn expired<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        !self.map.is_empty() && self.expired_at(key, Instant::now())
+    }
+
+    pub(crate) fn set(&mut self, key: K, deadline: Instant) {
+        Arc::make_mut(&mut self.map).insert(key, deadline);
+    }
+
+    pub(crate) fn clear<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.map.contains_key(key) {
+            Arc::make_mut(&mut self.map).remove(key);
+        }
+    }
+
+    /// Keys whose deadline is at or before `now`
+    pub(crate) fn due(&self, now: Instant) -> Vec<K> {
+        self.due_keys(now).cloned().collect()
+    }
+
+    pub(crate) fn due_keys(&self, now: Instant) -> impl Iterator<Item = &K> {
+        self.map
+            .iter()
+            .filter(move |(_, &deadline)| deadline <= now)
+            .map(|(key, _)| key)
+    }
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Removes every expired entry, returning how many were dropped
+    pub fn purge_expired(&mut self) -> usize {
+        self.purge_expired_at(Instant::now())
+    }
+
+    pub(crate) fn purge_expired_at(&mut self, now: Instant) -> usize {
+        let due = self.deadlines.due(now);
+        for key in &due {
+            self.expire(key);
+        }
+        due.len()
+    }
+
+    /// Drops the expired entry under `key`, running the expiry hooks
+    pub(crate) fn expire<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some((key, value)) = self.take_entry(key) {
+            self.hooks.expired(&key, &value);
+        }
+    }
+
+    /// Runs `callback` on every expired entry as it is purged or overwritten
+    ///
+    /// Shorthand for installing a `Hook::AfterExpire`; entries that expire
+    /// are only noticed by `purge_expired`, so pair this with a `Sweeper`
+    /// for cleanup that happens without waiting on the next write.
+    pub fn on_expire(&mut self, callback: impl Fn(&K, &V) + Send + Sync + 'static) {
+        self.add_hook(Hook::AfterExpire(Box::new(callback)));
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Inserts an entry that reads as absent once `ttl` has passed
+    ///
+    /// Like `insert`, the entry is dropped if it doesn't fit a refusing byte
+    /// limit or its key fails the key policy.
+    pub fn insert_with_ttl(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>> + AsRef<str>,
+        ttl: Duration,
+    ) {
+        let key = self.fold_owned_key(key.into());
+        if self.check_key(&key).is_err() || self.try_put(Arc::clone(&key), stored(value)).is_err() {
+            return;
+        }
+        // a deadline past the clock's range never arrives
+        if let Some(deadline) = Instant::now().checked_add(ttl) {
+            self.deadlines.set(key, deadline);
+        }
+    }
+
+    /// Time left before `key` expires, or `None` if it has no TTL or is gone
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        let deadline = self.deadlines.map.get(&*self.fold_key(key))?;
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_expired_entries_read_as_absent() {
+        let mut db = MemoryDatabase::new();
+        db.insert_with_ttl("session", "abc", Duration::ZERO);
+        db.insert_with_ttl("token", "xyz", Duration::from_secs(3600));
+        db.insert("plain", "1");
+
+        assert_eq!(db.retrieve("session"), None);
+        assert!(!db.contains_key("session"));
+        let delete = crate::DbOperation::Delete {
+            key: "session".into(),
+        };
+        assert_eq!(
+            delete.execute(&mut db),
+            Err("Key not found: session".into())
+        );
+        assert_eq!(db.len(), 2);
+        db.insert_with_ttl("session", "abc", Duration::ZERO);
+        assert_eq!(db.retrieve("token"), Some("xyz"));
+        assert!(db.ttl("token").unwrap() > Duration::from_secs(3590));
+        assert_eq!(db.ttl("plain"), None);
+
+        db.insert("session", "renewed");
+        assert_eq!(db.retrieve("session"), Some("renewed"));
+        assert_eq!(db.ttl("session"), None);
+    }
+
+    #[test]
+    fn test_purge_and_save_skip_expired() {
+        let path = std::env::temp_dir().join("gvs_ttl_save.db");
+        let path = path.to_str().unwrap();
+        let mut db = MemoryDatabase::new();
+        db.insert_with_ttl("gone", "1", Duration::ZERO);
+        db.insert_with_ttl("kept", "2", Duration::MAX);
+        db.insert("plain", "3");
+
+        db.save_to_file(path).unwrap();
+        let loaded = MemoryDatabase::load_from_file(path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.retrieve("gone"), None);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.stats().value_sizes.total(), 2);
+        assert_eq!(crate::pipeline::key_batches(&db, 5), ["kept,plain"]);
+        assert_eq!(db.purge_expired(), 1);
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.retrieve("kept"), Some("2"));
+    }
+
+    #[test]
+    fn test_on_expire_sees_dropped_entries() {
+        let expired = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut db = MemoryDatabase::new();
+        let seen = Arc::clone(&expired);
+        db.on_expire(move |key, value| {
+            seen.lock().unwrap().push(format!("{}={}", key, value));
+        });
+        db.insert_with_ttl("session", "abc", Duration::ZERO);
+        db.insert_with_ttl("temp", "/tmp/x", Duration::ZERO);
+        db.insert_with_ttl("token", "xyz", Duration::MAX);
+        db.insert("plain", "1");
+        db.remove("plain");
+
+        db.insert("temp", "reused");
+        assert_eq!(*expired.lock().unwrap(), ["temp=/tmp/x"]);
+        assert_eq!(db.purge_expired(), 1);
+        assert_eq!(db.purge_expired(), 0);
+        assert_eq!(expired.lock().unwrap().len(), 2);
+        assert_eq!(expired.lock().unwrap()[1], "session=abc");
+    }
+}
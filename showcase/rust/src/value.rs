@@ -0,0 +1,250 @@
+//! Typed values for databases that store more than strings
+//!
+//! `MemoryDatabase` is generic over its value type, so a `ValueDatabase`
+//! keeps numbers, flags and bytes as themselves: `increment` adds to an
+//! `Int` directly instead of parsing and reformatting a string on every
+//! call. The string-valued `Database` trait keeps its own `StoredValue`.
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use crate::patterns::{classify_value, ValueKind};
+use crate::quota::ApproxSize;
+use crate::MemoryDatabase;
+
+/// A stored value of one of a few primitive shapes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(Arc<str>),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Reads `text` as a bool, integer or float where it is one, else a string
+    pub fn parse(text: &str) -> Self {
+        match classify_value(text) {
+            ValueKind::Boolean => Value::Bool(text == "true"),
+            ValueKind::Integer => text
+                .parse()
+                .map_or_else(|_| Value::Str(text.into()), Value::Int),
+            _ => match text.parse::<f64>() {
+                Ok(float) if text.contains(['.', 'e', 'E']) => Value::Float(float),
+                _ => Value::Str(text.into()),
+            },
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Value::Int(int) => Some(int),
+            _ => None,
+        }
+    }
+
+    /// The value as a float, widening an `Int`
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Float(float) => Some(float),
+            Value::Int(int) => Some(int as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(flag) => Some(flag),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Numbers compare by value across `Int` and `Float`; other values only
+/// compare with their own kind
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.partial_cmp(b),
+            (Value::List(a), Value::List(b)) => a.partial_cmp(b),
+            (a, b) => a.as_float()?.partial_cmp(&b.as_float()?),
+        }
+    }
+}
+
+/// Strings print as-is, bytes as hex and lists as `[a, b]`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(text) => f.write_str(text),
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Float(float) => write!(f, "{:?}", float),
+            Value::Bool(flag) => write!(f, "{}", flag),
+            Value::Bytes(bytes) => {
+                f.write_str("0x")?;
+                bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            Value::List(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Str(text.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Str(text.into())
+    }
+}
+
+impl From<Arc<str>> for Value {
+    fn from(text: Arc<str>) -> Self {
+        Value::Str(text)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(int: i64) -> Self {
+        Value::Int(int)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(float: f64) -> Self {
+        Value::Float(float)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(flag: bool) -> Self {
+        Value::Bool(flag)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::Bytes(bytes)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl ApproxSize for Value {
+    fn approx_size(&self) -> usize {
+        match self {
+            Value::Str(text) => text.len(),
+            Value::Int(_) | Value::Float(_) => 8,
+            Value::Bool(_) => 1,
+            Value::Bytes(bytes) => bytes.len(),
+            Value::List(items) => items.iter().map(ApproxSize::approx_size).sum(),
+        }
+    }
+}
+
+/// A `MemoryDatabase` holding typed values
+pub type ValueDatabase<S = RandomState> = MemoryDatabase<Arc<str>, Value, S>;
+
+impl<S: BuildHasher + Clone> ValueDatabase<S> {
+    /// Adds `by` to the integer under `key`, starting from 0 if it is absent
+    ///
+    /// Returns the new value. Fails, leaving the entry alone, when it holds
+    /// something other than an `Int` or the sum overflows.
+    pub fn increment(&mut self, key: &str, by: i64) -> Result<i64, String> {
+        let current = match self.get(key) {
+            None => 0,
+            Some(Value::Int(int)) => *int,
+            Some(_) => return Err(format!("Not an integer: {}", key)),
+        };
+        let next = current
+            .checked_add(by)
+            .ok_or_else(|| format!("Increment overflows: {}", key))?;
+        self.try_put(key.into(), Value::Int(next))
+            .map_err(|e| e.to_string())?;
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_convert() {
+        assert_eq!(Value::parse("42"), Value::Int(42));
+        assert_eq!(Value::parse("-1.5"), Value::Float(-1.5));
+        assert_eq!(Value::parse("true"), Value::Bool(true));
+        assert_eq!(Value::parse("inf").as_str(), Some("inf"));
+        assert_eq!(Value::parse("99999999999999999999").as_int(), None);
+
+        assert_eq!(Value::from(3).as_float(), Some(3.0));
+        assert!(Value::from(2) < Value::from(2.5));
+        assert!(Value::from("a") < Value::from("b"));
+        assert_eq!(Value::from("1").partial_cmp(&Value::from(1)), None);
+
+        let list = Value::from(vec![Value::from(1), Value::from(vec![0xab_u8]), "x".into()]);
+        assert_eq!(list.to_string(), "[1, 0xab, x]");
+        assert_eq!(list.as_list().map(<[Value]>::len), Some(3));
+    }
+
+    #[test]
+    fn test_increment_without_parsing() {
+        let mut db: ValueDatabase = MemoryDatabase::default();
+        assert_eq!(db.increment("visits", 2), Ok(2));
+        assert_eq!(db.increment("visits", 3), Ok(5));
+        assert_eq!(db.get("visits"), Some(&Value::Int(5)));
+
+        db.put("name".into(), "alice".into());
+        assert_eq!(
+            db.increment("name", 1),
+            Err("Not an integer: name".to_string())
+        );
+        db.put("max".into(), i64::MAX.into());
+        assert!(db.increment("max", 1).is_err());
+        assert_eq!(db.get("max").and_then(Value::as_int), Some(i64::MAX));
+    }
+}
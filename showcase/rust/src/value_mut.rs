@@ -0,0 +1,122 @@
+//! In-place edits of stored values
+//!
+//! `get_mut` hands out a copy of the value behind a guard and writes it back
+//! through `put` when the guard drops, so indexes, quotas, history and
+//! subscribers see the edit like any other write.
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+
+use crate::case::FoldCase;
+use crate::MemoryDatabase;
+
+/// A value borrowed for editing, stored back when dropped
+///
+/// Only a guard that was mutably dereferenced writes anything. A write
+/// refused by a byte limit leaves the old value in place.
+pub struct ValueMut<'a, K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    db: &'a mut MemoryDatabase<K, V, S>,
+    entry: Option<(K, V)>,
+    changed: bool,
+}
+
+impl<K, V, S> MemoryDatabase<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Borrows the value under `key` for editing
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<ValueMut<'_, K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + FoldCase + ?Sized,
+    {
+        let key = self.fold_query(key);
+        let value = self.get_exact(&*key)?.clone();
+        let (key, _) = self.store.get_key_value(&*key)?;
+        Some(ValueMut {
+            entry: Some((key.clone(), value)),
+            db: self,
+            changed: false,
+        })
+    }
+}
+
+impl<K, V, S> Deref for ValueMut<'_, K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.entry.as_ref().expect("entry taken only on drop").1
+    }
+}
+
+impl<K, V, S> DerefMut for ValueMut<'_, K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn deref_mut(&mut self) -> &mut V {
+        self.changed = true;
+        &mut self.entry.as_mut().expect("entry taken only on drop").1
+    }
+}
+
+impl<K, V, S> Drop for ValueMut<'_, K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn drop(&mut self) {
+        if let Some((key, value)) = self.entry.take().filter(|_| self.changed) {
+            self.db.put(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::changes::ChangeKind;
+    use crate::quota::QuotaPolicy;
+    use crate::{Database, MemoryDatabase};
+
+    #[test]
+    fn test_edits_are_written_back() {
+        let mut counts: MemoryDatabase<&str, u32> = MemoryDatabase::default();
+        counts.put("a", 1);
+        *counts.get_mut("a").unwrap() += 2;
+        assert_eq!(counts.get("a"), Some(&3));
+        assert!(counts.get_mut("missing").is_none());
+
+        let mut db = MemoryDatabase::new();
+        db.insert("k", "v");
+        let events = db.subscribe();
+        assert_eq!(&**db.get_mut("k").unwrap(), "v");
+        *db.get_mut("k").unwrap() = "w".into();
+        assert_eq!(db.retrieve("k"), Some("w"));
+        let kinds: Vec<_> = events.try_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, [ChangeKind::Updated]);
+    }
+
+    #[test]
+    fn test_refused_edit_keeps_old_value() {
+        let mut db = MemoryDatabase::with_max_bytes(4, QuotaPolicy::Refuse);
+        db.insert("k", "ab");
+        *db.get_mut("k").unwrap() = "abcdef".into();
+        assert_eq!(db.retrieve("k"), Some("ab"));
+        *db.get_mut("k").unwrap() = "abc".into();
+        assert_eq!(db.retrieve("k"), Some("abc"));
+    }
+}
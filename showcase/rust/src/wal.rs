@@ -0,0 +1,535 @@
+//! Write-ahead logging for crash recovery
+//!
+//! Each mutation is appended to the log as one line: `+key:value` in the
+//! `persist` line format for writes and `-key` for removals, before it is
+//! applied. Writes are checked against the database's limits first, so a
+//! refused write is never logged. A final line cut short by a crash is
+//! ignored on replay and trimmed when the log is reopened. Records are only dropped by `compact`,
+//! so until then `read_log` doubles as an audit trail of every mutation.
+//!
+//! Whenever the clock has moved on since the last record, a `@<unix ms>`
+//! line stamps the records after it, and `compact` starts the log with a
+//! `=<unix ms>` line instead. `recover_to` and `recover_to_op` use them to
+//! rebuild the database as it was at an earlier point.
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::literals::{escape_value, unescape_value};
+use crate::persist::{self, FsyncPolicy};
+use crate::{stored, Database, StrDatabase};
+
+/// One mutation in a write-ahead log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogRecord {
+    Insert { key: Arc<str>, value: Arc<str> },
+    Remove { key: Arc<str> },
+}
+
+impl LogRecord {
+    /// Formats the record as one log line, without the trailing newline
+    pub fn encode(&self) -> String {
+        match self {
+            LogRecord::Insert { key, value } => encode_insert(key, value),
+            LogRecord::Remove { key } => format!("-{}", escape_value(key)),
+        }
+    }
+
+    /// Parses a line written by `encode`
+    pub fn decode(line: &str) -> Result<Self, String> {
+        match line.split_at_checked(1) {
+            Some(("+", entry)) => {
+                let (key, value) = persist::decode_line(entry)?;
+                Ok(LogRecord::Insert {
+                    key: key.into(),
+                    value: value.into(),
+                })
+            }
+            Some(("-", key)) => {
+                let key = unescape_value(key).ok_or("Malformed escape in key")?;
+                Ok(LogRecord::Remove { key: key.into() })
+            }
+            _ => Err("Unknown WAL record".to_string()),
+        }
+    }
+}
+
+fn encode_insert(key: &str, value: &str) -> String {
+    format!("+{}", persist::encode_line(key, value))
+}
+
+/// One record of a log, with where and when it was logged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Position among the log's records, from 1 since it was created or compacted
+    pub sequence: u64,
+    /// `None` for records logged before logs carried timestamps
+    pub logged_at: Option<SystemTime>,
+    /// True for the inserts `compact` wrote in place of the history before it
+    pub compacted: bool,
+    pub record: LogRecord,
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Parses every complete line, returning the entries with the bytes they span
+fn parse_entries(log: &str) -> io::Result<(Vec<LogEntry>, usize)> {
+    let complete = log.rfind('\n').map_or(0, |end| end + 1);
+    let mut entries = Vec::new();
+    let (mut logged_at, mut compacted) = (None, false);
+    for (number, line) in log[..complete].lines().enumerate() {
+        let invalid = |e: String| persist::invalid_line(number + 1, e);
+        match line.split_at_checked(1) {
+            Some((marker @ ("@" | "="), millis)) => {
+                let millis = millis
+                    .parse()
+                    .map_err(|_| invalid("Malformed timestamp".to_string()))?;
+                logged_at = Some(UNIX_EPOCH + Duration::from_millis(millis));
+                compacted = marker == "=";
+            }
+            _ => entries.push(LogEntry {
+                sequence: entries.len() as u64 + 1,
+                logged_at,
+                compacted,
+                record: LogRecord::decode(line).map_err(invalid)?,
+            }),
+        }
+    }
+    Ok((entries, complete))
+}
+
+fn parse_log(log: &str) -> io::Result<(Vec<LogRecord>, usize)> {
+    let (entries, complete) = parse_entries(log)?;
+    Ok((entries.into_iter().map(|e| e.record).collect(), complete))
+}
+
+/// Reads every complete record in the log at `path`, oldest first
+pub fn read_log(path: &str) -> io::Result<Vec<LogRecord>> {
+    read_log_entries(path).map(|entries| entries.into_iter().map(|e| e.record).collect())
+}
+
+/// Like `read_log`, keeping each record's sequence number and timestamp
+pub fn read_log_entries(path: &str) -> io::Result<Vec<LogEntry>> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    parse_entries(&contents).map(|(entries, _)| entries)
+}
+
+/// A `MemoryDatabase` whose mutations are logged before they are applied
+///
+/// Derefs to the database for reads; writes go through `insert` and
+/// `remove` so none can skip the log.
+#[derive(Debug)]
+pub struct WalDatabase<S = RandomState> {
+    db: StrDatabase<S>,
+    path: String,
+    log: File,
+    log_bytes: u64,
+    compact_at: Option<u64>,
+    fsync: FsyncPolicy,
+    unsynced: usize,
+    /// Unix milliseconds of the last `@` line, 0 before the first
+    stamped: u64,
+}
+
+impl<S: BuildHasher + Clone> WalDatabase<S> {
+    /// Replays the log at `path` into `db`, then appends further mutations to it
+    ///
+    /// The log is created if missing. `fsync` is honoured per record, with
+    /// `OnFinish` syncing in `close`.
+    pub fn open(mut db: StrDatabase<S>, path: &str, fsync: FsyncPolicy) -> io::Result<Self> {
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut contents = String::new();
+        log.read_to_string(&mut contents)?;
+        let (records, complete) = parse_log(&contents)?;
+        db.replay(records);
+        if complete < contents.len() {
+            log.set_len(complete as u64)?;
+        }
+        Ok(Self {
+            db,
+            path: path.to_string(),
+            log,
+            log_bytes: complete as u64,
+            compact_at: None,
+            fsync,
+            unsynced: 0,
+            stamped: 0,
+        })
+    }
+
+    /// Compacts automatically whenever a write grows the log past `max_bytes`
+    ///
+    /// Keep the threshold well above the size of the live data, or every
+    /// write will rewrite the whole log.
+    pub fn with_compaction_threshold(mut self, max_bytes: u64) -> Self {
+        self.compact_at = Some(max_bytes);
+        self
+    }
+
+    /// Current size of the log file
+    pub fn log_bytes(&self) -> u64 {
+        self.log_bytes
+    }
+
+    /// Rewrites the log as one insert per live entry
+    ///
+    /// Superseded inserts and removals are dropped, so the audit trail before
+    /// this point is lost. The new log replaces the old one atomically.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let (db, fsync) = (&self.db, self.fsync);
+        let written = persist::write_atomically(&self.path, |file| {
+            let mut writer = BufWriter::new(file);
+            let stamp = format!("={}\n", unix_millis(SystemTime::now()));
+            writer.write_all(stamp.as_bytes())?;
+            let mut written = stamp.len() as u64;
+            for (key, value) in db.iter() {
+                let line = encode_insert(key, value) + "\n";
+                writer.write_all(line.as_bytes())?;
+                written += line.len() as u64;
+            }
+            writer.flush()?;
+            if fsync != FsyncPolicy::Never {
+                writer.get_ref().sync_all()?;
+            }
+            Ok(written)
+        })?;
+        self.log = OpenOptions::new().append(true).open(&self.path)?;
+        self.log_bytes = written;
+        self.unsynced = 0;
+        // later records need an `@` line to tell them from the compacted ones
+        self.stamped = 0;
+        Ok(())
+    }
+
+    /// Checks, logs and then stores the entry
+    ///
+    /// A write refused by a quota, hook or key policy is not logged and
+    /// fails with `ErrorKind::Other` wrapping the `Error`; one that can't be
+    /// logged is not applied.
+    pub fn insert(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Arc<str>>,
+    ) -> io::Result<()> {
+        let key = self.db.fold_owned_key(key.into());
+        let value = value.into();
+        let entry = stored(Arc::clone(&value));
+        self.db.check_key(&key).map_err(io::Error::other)?;
+        self.db.check_put(&key, &entry).map_err(io::Error::other)?;
+        self.append(LogRecord::Insert {
+            key: Arc::clone(&key),
+            value,
+        })?;
+        self.db.put_entry(key, entry);
+        self.compact_if_over()
+    }
+
+    /// Logs and then removes `key`, returning true if it was present
+    pub fn remove(&mut self, key: &str) -> io::Result<bool> {
+        self.append(LogRecord::Remove { key: key.into() })?;
+        let removed = self.db.remove(key).is_some();
+        self.compact_if_over()?;
+        Ok(removed)
+    }
+
+    /// Forces every logged mutation to stable storage
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.unsynced = 0;
+        self.log.sync_data()
+    }
+
+    /// Closes the log, returning the database
+    pub fn close(mut self) -> io::Result<StrDatabase<S>> {
+        if self.fsync != FsyncPolicy::Never {
+            self.sync()?;
+        }
+        Ok(self.db)
+    }
+
+    fn append(&mut self, record: LogRecord) -> io::Result<()> {
+        // one write per record, so a crash can only tear the last line
+        let now = unix_millis(SystemTime::now());
+        let mut line = String::new();
+        if now != self.stamped {
+            line = format!("@{}\n", now);
+        }
+        line += &record.encode();
+        line.push('\n');
+        if let Err(e) = self.log.write_all(line.as_bytes()) {
+            // a partial line would run into the next record, so cut it off
+            self.log.set_len(self.log_bytes)?;
+            return Err(e);
+        }
+        self.stamped = now;
+        self.log_bytes += line.len() as u64;
+        self.unsynced += 1;
+        if let FsyncPolicy::EveryEntries(n) = self.fsync {
+            if self.unsynced >= n.max(1) {
+                self.sync()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compact_if_over(&mut self) -> io::Result<()> {
+        match self.compact_at {
+            Some(max_bytes) if self.log_bytes > max_bytes => self.compact(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S> Deref for WalDatabase<S> {
+    type Target = StrDatabase<S>;
+
+    fn deref(&self) -> &StrDatabase<S> {
+        &self.db
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Applies one logged or replicated mutation
+    pub fn apply_record(&mut self, record: LogRecord) {
+        match record {
+            LogRecord::Insert { key, value } => self.insert(key, value),
+            LogRecord::Remove { key } => {
+                self.remove(&key);
+            }
+        }
+    }
+
+    fn replay(&mut self, records: Vec<LogRecord>) {
+        for record in records {
+            self.apply_record(record);
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> StrDatabase<S> {
+    /// Replays the write-ahead log at `path` into `db`, keeping its limits
+    ///
+    /// Fails with `InvalidData` naming the first malformed record.
+    pub fn recover_from_wal(mut db: Self, path: &str) -> io::Result<Self> {
+        db.replay(read_log(path)?);
+        Ok(db)
+    }
+
+    /// Replays the log's records into `db` up to and including `sequence`
+    ///
+    /// `db` should be the snapshot the log continues from. Fails with
+    /// `InvalidInput` if the log holds fewer records than that.
+    pub fn recover_to_op(mut db: Self, path: &str, sequence: u64) -> io::Result<Self> {
+        let entries = read_log_entries(path)?;
+        if sequence > entries.len() as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("The log holds {} records, not {}", entries.len(), sequence),
+            ));
+        }
+        let kept = entries.into_iter().take(sequence as usize);
+        db.replay(kept.map(|entry| entry.record).collect());
+        Ok(db)
+    }
+
+    /// Replays every record logged at or before `at` into `db`
+    ///
+    /// Fails with `InvalidInput` when `at` falls before the last compaction,
+    /// whose history is gone, or among records logged without timestamps.
+    pub fn recover_to(mut db: Self, path: &str, at: SystemTime) -> io::Result<Self> {
+        let entries = read_log_entries(path)?;
+        let unplaceable = |reason: &str| Err(io::Error::new(io::ErrorKind::InvalidInput, reason));
+        if entries
+            .first()
+            .is_some_and(|first| first.compacted && first.logged_at > Some(at))
+        {
+            return unplaceable("History before the last compaction is gone");
+        }
+        let kept = entries
+            .iter()
+            .take_while(|entry| entry.logged_at.is_none_or(|logged| logged <= at))
+            .count();
+        let undated = entries.iter().take_while(|e| e.logged_at.is_none()).count();
+        if undated > 0 && kept == undated {
+            return unplaceable("Records without timestamps can only be recovered by sequence");
+        }
+        let kept = entries.into_iter().take(kept);
+        db.replay(kept.map(|entry| entry.record).collect());
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn test_recover_after_reopen() {
+        let path = std::env::temp_dir().join("gvs_wal_reopen.log");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut wal = WalDatabase::open(MemoryDatabase::new(), path, FsyncPolicy::Never).unwrap();
+        wal.insert("user:1", "alice").unwrap();
+        wal.insert("a:b", "multi\nline").unwrap();
+        wal.insert("user:1", "bob").unwrap();
+        assert!(wal.remove("a:b").unwrap());
+        assert_eq!(wal.retrieve("user:1"), Some("bob"));
+        drop(wal);
+
+        let records = read_log(path).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3], LogRecord::Remove { key: "a:b".into() });
+        assert_eq!(
+            LogRecord::decode(&records[1].encode()),
+            Ok(records[1].clone())
+        );
+
+        let wal = WalDatabase::open(MemoryDatabase::new(), path, FsyncPolicy::OnFinish).unwrap();
+        assert_eq!(wal.len(), 1);
+        let db = wal.close().unwrap();
+        assert_eq!(db.retrieve("user:1"), Some("bob"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_tail_is_dropped() {
+        let path = std::env::temp_dir().join("gvs_wal_torn.log");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "+k:1\n-k\n+k:2\n+gone:hal").unwrap();
+
+        let db = MemoryDatabase::recover_from_wal(MemoryDatabase::new(), path).unwrap();
+        assert_eq!(db.retrieve("k"), Some("2"));
+        assert_eq!(db.retrieve("gone"), None);
+
+        let mut wal =
+            WalDatabase::open(MemoryDatabase::new(), path, FsyncPolicy::EveryEntries(1)).unwrap();
+        wal.insert("next", "3").unwrap();
+        drop(wal);
+        let contents = std::fs::read_to_string(path).unwrap();
+        let records: Vec<&str> = contents.lines().filter(|l| !l.starts_with('@')).collect();
+        assert_eq!(records, ["+k:1", "-k", "+k:2", "+next:3"]);
+        assert!(contents.ends_with('\n'));
+
+        std::fs::write(path, "+k:1\n?k\n").unwrap();
+        let err = MemoryDatabase::recover_from_wal(MemoryDatabase::new(), path).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: Unknown WAL record");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_recover_to_a_past_point() {
+        let path = std::env::temp_dir().join("gvs_wal_past.log");
+        let path = path.to_str().unwrap();
+        let at = |millis| UNIX_EPOCH + Duration::from_millis(millis);
+        std::fs::write(path, "=1000\n+k:0\n@2000\n+k:1\n+other:x\n@3000\n-k\n").unwrap();
+
+        let db = MemoryDatabase::recover_to_op(MemoryDatabase::new(), path, 2).unwrap();
+        assert_eq!((db.retrieve("k"), db.len()), (Some("1"), 1));
+        let db = MemoryDatabase::recover_to(MemoryDatabase::new(), path, at(2500)).unwrap();
+        assert_eq!((db.retrieve("k"), db.len()), (Some("1"), 2));
+        let db = MemoryDatabase::recover_to(MemoryDatabase::new(), path, at(3000)).unwrap();
+        assert_eq!(db.retrieve("k"), None);
+        let entries = read_log_entries(path).unwrap();
+        assert_eq!(
+            (
+                entries[0].compacted,
+                entries[1].compacted,
+                entries[1].sequence
+            ),
+            (true, false, 2)
+        );
+
+        let err = MemoryDatabase::recover_to(MemoryDatabase::new(), path, at(500)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = MemoryDatabase::recover_to_op(MemoryDatabase::new(), path, 5).unwrap_err();
+        assert_eq!(err.to_string(), "The log holds 4 records, not 5");
+
+        std::fs::write(path, "+k:1\n@2000\n+k:2\n").unwrap();
+        assert!(MemoryDatabase::recover_to(MemoryDatabase::new(), path, at(1000)).is_err());
+        let db = MemoryDatabase::recover_to(MemoryDatabase::new(), path, at(2000)).unwrap();
+        assert_eq!(db.retrieve("k"), Some("2"));
+        std::fs::write(path, "@soon\n").unwrap();
+        let err = read_log_entries(path).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: Malformed timestamp");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_compaction_keeps_latest_records() {
+        let path = std::env::temp_dir().join("gvs_wal_compact.log");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut wal = WalDatabase::open(MemoryDatabase::new(), path, FsyncPolicy::Never).unwrap();
+        for round in 0..10 {
+            wal.insert("counter", round.to_string()).unwrap();
+        }
+        wal.insert("temp", "x").unwrap();
+        wal.remove("temp").unwrap();
+        assert_eq!(read_log(path).unwrap().len(), 12);
+
+        wal.compact().unwrap();
+        assert_eq!(
+            read_log(path).unwrap(),
+            [LogRecord::Insert {
+                key: "counter".into(),
+                value: "9".into()
+            }]
+        );
+        assert_eq!(wal.log_bytes(), std::fs::metadata(path).unwrap().len());
+
+        let mut wal = wal.with_compaction_threshold(40);
+        for round in 0..10 {
+            wal.insert("counter", round.to_string()).unwrap();
+        }
+        assert!(wal.log_bytes() <= 40);
+        drop(wal);
+        let db = MemoryDatabase::recover_from_wal(MemoryDatabase::new(), path).unwrap();
+        assert_eq!(db.retrieve("counter"), Some("9"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_refused_writes_are_not_logged() {
+        use crate::quota::QuotaPolicy;
+
+        let path = std::env::temp_dir().join("gvs_wal_refused.log");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let bounded = MemoryDatabase::with_max_bytes(10, QuotaPolicy::Refuse);
+        let mut wal = WalDatabase::open(bounded, path, FsyncPolicy::Never).unwrap();
+        wal.insert("k", "small").unwrap();
+        let err = wal.insert("big", "far too large").unwrap_err();
+        assert_eq!(err.to_string(), "Over quota: needs 22 bytes, limit is 10");
+        assert_eq!(read_log(path).unwrap().len(), 1);
+
+        // a log that can't be written to leaves the database untouched
+        wal.db.enable_changelog(4);
+        wal.log = File::open(path).unwrap();
+        assert!(wal.insert("k2", "v").is_err());
+        assert_eq!(wal.retrieve("k2"), None);
+        assert_eq!(wal.last_sequence(), Some(0));
+        drop(wal);
+
+        std::fs::write(path, "+k:small\n+big:far too large\n").unwrap();
+        let bounded = MemoryDatabase::with_max_bytes(10, QuotaPolicy::Refuse);
+        let db = MemoryDatabase::recover_from_wal(bounded, path).unwrap();
+        assert_eq!(db.used_bytes(), Some(6));
+        assert_eq!(db.retrieve("big"), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
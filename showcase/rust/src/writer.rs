@@ -0,0 +1,213 @@
+//! Multi-producer writer that funnels operations through one thread
+//!
+//! Producers push onto a bounded channel, so a slow store applies
+//! backpressure instead of queueing without limit. A single writer thread
+//! owns the lock for each batch, and `flush` waits on a `Condvar` until
+//! everything submitted so far has been applied.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Barrier, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::{DbOperation, MemoryDatabase};
+
+/// Most operations the writer applies per lock acquisition
+const BATCH: usize = 64;
+
+/// Totals reported when a writer finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteReport {
+    pub applied: usize,
+    pub failed: usize,
+}
+
+struct Shared {
+    submitted: AtomicUsize,
+    progress: Mutex<WriteReport>,
+    applied: Condvar,
+}
+
+/// Cloneable handle for submitting operations to a `Writer`
+#[derive(Clone)]
+pub struct Producer {
+    sender: SyncSender<DbOperation>,
+    shared: Arc<Shared>,
+}
+
+impl Producer {
+    /// Queues `op`, blocking while the channel is full
+    pub fn submit(&self, op: DbOperation) -> Result<(), String> {
+        self.shared.submitted.fetch_add(1, Ordering::SeqCst);
+        self.sender.send(op).map_err(|_| {
+            self.shared.submitted.fetch_sub(1, Ordering::SeqCst);
+            "Writer has stopped".to_string()
+        })
+    }
+}
+
+/// Background writer applying operations to a shared database
+pub struct Writer {
+    producer: Option<Producer>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Writer {
+    /// Starts the writer thread with room for `capacity` queued operations
+    pub fn start(db: Arc<Mutex<MemoryDatabase>>, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let shared = Arc::new(Shared {
+            submitted: AtomicUsize::new(0),
+            progress: Mutex::new(WriteReport::default()),
+            applied: Condvar::new(),
+        });
+
+        let worker = Arc::clone(&shared);
+        let handle = thread::spawn(move || apply_loop(&db, &receiver, &worker));
+
+        Self {
+            producer: Some(Producer { sender, shared }),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn producer(&self) -> Producer {
+        self.producer.clone().expect("writer is running")
+    }
+
+    /// Blocks until every operation submitted so far has been applied
+    pub fn flush(&self) -> WriteReport {
+        let shared = &self.producer.as_ref().expect("writer is running").shared;
+        let progress = shared.progress.lock().unwrap();
+        let progress = shared
+            .applied
+            .wait_while(progress, |p| {
+                p.applied + p.failed < shared.submitted.load(Ordering::SeqCst)
+            })
+            .unwrap();
+        *progress
+    }
+
+    /// Stops accepting work and waits for the queue to drain
+    ///
+    /// Waits for every outstanding `Producer` to be dropped first.
+    pub fn finish(mut self) -> WriteReport {
+        let shared = Arc::clone(&self.producer.take().expect("writer is running").shared);
+        if let Some(handle) = self.handle.take() {
+            handle.join().expect("writer thread panicked");
+        }
+        let report = *shared.progress.lock().unwrap();
+        report
+    }
+}
+
+fn apply_loop(db: &Mutex<MemoryDatabase>, receiver: &Receiver<DbOperation>, shared: &Shared) {
+    while let Ok(first) = receiver.recv() {
+        let batch: Vec<DbOperation> = std::iter::once(first)
+            .chain(receiver.try_iter().take(BATCH - 1))
+            .collect();
+
+        let mut outcome = WriteReport::default();
+        {
+            let mut db = db.lock().unwrap();
+            for op in &batch {
+                match op.execute(&mut *db) {
+                    Ok(_) => outcome.applied += 1,
+                    Err(_) => outcome.failed += 1,
+                }
+            }
+        }
+
+        let mut progress = shared.progress.lock().unwrap();
+        progress.applied += outcome.applied;
+        progress.failed += outcome.failed;
+        shared.applied.notify_all();
+    }
+}
+
+/// Inserts every batch from its own scoped thread, all starting together
+pub fn parallel_load(
+    db: Arc<Mutex<MemoryDatabase>>,
+    batches: &[Vec<(String, String)>],
+) -> WriteReport {
+    let writer = Writer::start(db, BATCH);
+    let start_line = Barrier::new(batches.len());
+
+    thread::scope(|scope| {
+        for batch in batches {
+            let producer = writer.producer();
+            let start_line = &start_line;
+            scope.spawn(move || {
+                start_line.wait();
+                for (key, value) in batch {
+                    let op = DbOperation::Insert {
+                        key: key.as_str().into(),
+                        value: value.as_str().into(),
+                    };
+                    producer.submit(op).expect("writer outlives the scope");
+                }
+            });
+        }
+    });
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_parallel_load_from_many_producers() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let batches: Vec<Vec<(String, String)>> = (0..4)
+            .map(|p| {
+                (0..250)
+                    .map(|i| (format!("p{}-{}", p, i), i.to_string()))
+                    .collect()
+            })
+            .collect();
+
+        let report = parallel_load(Arc::clone(&db), &batches);
+
+        assert_eq!(
+            report,
+            WriteReport {
+                applied: 1000,
+                failed: 0
+            }
+        );
+        let db = db.lock().unwrap();
+        assert_eq!(db.retrieve("p3-249"), Some("249"));
+    }
+
+    #[test]
+    fn test_flush_waits_for_applied_ops() {
+        let db = Arc::new(Mutex::new(MemoryDatabase::new()));
+        let writer = Writer::start(Arc::clone(&db), 1);
+        let producer = writer.producer();
+
+        producer
+            .submit(DbOperation::Insert {
+                key: "language".into(),
+                value: "Rust".into(),
+            })
+            .unwrap();
+        producer
+            .submit(DbOperation::Delete {
+                key: "missing".into(),
+            })
+            .unwrap();
+
+        assert_eq!(
+            writer.flush(),
+            WriteReport {
+                applied: 1,
+                failed: 1
+            }
+        );
+        assert_eq!(db.lock().unwrap().retrieve("language"), Some("Rust"));
+        drop(producer);
+        assert_eq!(writer.finish().applied, 1);
+    }
+}